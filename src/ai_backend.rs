@@ -0,0 +1,1040 @@
+//! Backends for calling AI models.
+//!
+//! A backend either spawns an external command (`aichat`, `ollama run`, etc.),
+//! or talks to an HTTP API directly: OpenAI-compatible chat completions or
+//! Ollama `/api/generate` endpoint. Responses can be stored in an on-disk cache,
+//! so reruns with same prompts don't query the model again. Failed requests are
+//! retried with exponential backoff, and request rate can be limited.
+
+use crate::util::pipe_to_command_with_timeout;
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default model for AI commands, in aichat format.
+pub const DEFAULT_MODEL: &str = "ollama:translategemma:12b";
+
+/// Default aichat role for AI commands.
+pub const DEFAULT_ROLE: &str = "translate-po";
+
+/// Settings of an HTTP API backend.
+#[derive(Debug, Clone)]
+pub struct HttpApi {
+    /// Full URL of the endpoint.
+    pub endpoint: String,
+    /// Model name, passed to the API verbatim.
+    pub model: String,
+    /// Sampling temperature, if set.
+    pub temperature: Option<f64>,
+    /// Name of the environment variable with the API key, if any.
+    pub api_key_env: Option<String>,
+    /// Timeout for the whole request.
+    pub timeout: Duration,
+}
+
+/// Error status of HTTP API, kept to decide whether the request is worth retrying.
+#[derive(Debug)]
+struct HttpStatusError {
+    code: u16,
+    message: String,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Returns true, when the failed request may succeed later: on rate limiting, server
+/// errors, network errors, and failures of commands. Client errors, e.g. wrong API key
+/// or unknown model, are not retried.
+fn is_retriable(error: &anyhow::Error) -> bool {
+    match error.downcast_ref::<HttpStatusError>() {
+        Some(status) => status.code == 429 || status.code >= 500,
+        None => true,
+    }
+}
+
+/// The way a backend reaches the AI model.
+#[derive(Debug, Clone)]
+pub enum AiProvider {
+    /// External command, which reads prompt from stdin and writes reply to stdout.
+    Command { command: String, args: Vec<String> },
+    /// OpenAI-compatible `/v1/chat/completions` API.
+    OpenAiChat(HttpApi),
+    /// Ollama `/api/generate` API.
+    OllamaGenerate(HttpApi),
+    /// Fixed response, for testing.
+    #[cfg(test)]
+    Mock(String),
+}
+
+/// On-disk cache of AI responses, one file per response.
+///
+/// Responses are keyed by a hash of the backend settings and the full prompt.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    /// Ignore cached responses, but store new ones.
+    refresh: bool,
+}
+
+impl ResponseCache {
+    /// Creates cache in the given directory.
+    pub fn new(dir: PathBuf, refresh: bool) -> Self {
+        Self { dir, refresh }
+    }
+
+    /// Returns default cache directory: `$XDG_CACHE_HOME/po-tools` or `~/.cache/po-tools`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(base.join("po-tools"))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.txt"))
+    }
+
+    /// Returns cached response, unless cache is being refreshed.
+    fn get(&self, key: &str) -> Option<String> {
+        if self.refresh {
+            return None;
+        }
+        std::fs::read_to_string(self.path(key)).ok()
+    }
+
+    /// Stores response in the cache.
+    fn put(&self, key: &str, response: &str) -> Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        std::fs::create_dir_all(&self.dir).with_context(|| {
+            tr!("Cannot create cache directory \"{dir}\".")
+                .replace("{dir}", &self.dir.display().to_string())
+        })?;
+
+        // Write to a temporary file first, so concurrent readers never see partial response
+        let tmp = self.dir.join(format!(
+            "{key}.{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&tmp, response)?;
+        std::fs::rename(&tmp, self.path(key))?;
+        Ok(())
+    }
+}
+
+/// Limits number of requests per minute, by spacing them evenly.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Creates limiter, which allows up to `requests` requests per minute.
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / requests.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Blocks until next request is allowed.
+    fn wait(&self) {
+        let delay = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let start = (*next).max(now);
+            *next = start + self.interval;
+            start - now
+        };
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+/// Backend for calling an AI model.
+#[derive(Debug, Clone)]
+pub struct AiBackend {
+    provider: AiProvider,
+    cache: Option<ResponseCache>,
+    /// Number of retries after a failed request.
+    retries: u32,
+    /// Delay before first retry. Doubled for each next retry.
+    retry_delay: Duration,
+    /// Timeout for external commands. HTTP backends use timeout from `HttpApi`.
+    timeout: Option<Duration>,
+    /// Shared between clones of backend, so parallel workers obey same limit.
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl AiBackend {
+    /// Create a new backend with a specific command and arguments.
+    pub fn new(command: String, args: Vec<String>) -> Self {
+        Self::with_provider(AiProvider::Command { command, args })
+    }
+
+    /// Create a backend from a full command line string (e.g., from --ai-command).
+    pub fn from_command_line(cmd: &str) -> Self {
+        let parts: Vec<String> = cmd.split_whitespace().map(|s| s.to_string()).collect();
+        if parts.is_empty() {
+            // Default to aichat if empty, though this shouldn't normally happen if parsed correctly
+            return Self::with_aichat_defaults(DEFAULT_MODEL, DEFAULT_ROLE, None);
+        }
+        Self::new(parts[0].clone(), parts[1..].to_vec())
+    }
+
+    /// Create a backend for aichat with default options.
+    pub fn with_aichat_defaults(model: &str, role: &str, rag: Option<&str>) -> Self {
+        let mut args = vec![
+            "-r".to_string(),
+            role.to_string(),
+            "-m".to_string(),
+            model.to_string(),
+        ];
+        if let Some(rag_val) = rag {
+            args.push("--rag".to_string());
+            args.push(rag_val.to_string());
+        }
+        Self::new("aichat".to_string(), args)
+    }
+
+    /// Create a backend for OpenAI-compatible chat completions API.
+    pub fn openai_chat(api: HttpApi) -> Self {
+        Self::with_provider(AiProvider::OpenAiChat(api))
+    }
+
+    /// Create a backend for Ollama generate API.
+    pub fn ollama_generate(api: HttpApi) -> Self {
+        Self::with_provider(AiProvider::OllamaGenerate(api))
+    }
+
+    /// Create a mock backend for testing.
+    #[cfg(test)]
+    pub fn mock(response: &str) -> Self {
+        Self::with_provider(AiProvider::Mock(response.to_string()))
+    }
+
+    fn with_provider(provider: AiProvider) -> Self {
+        Self {
+            provider,
+            cache: None,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+            timeout: None,
+            rate_limiter: None,
+        }
+    }
+
+    /// Use the given cache for responses.
+    pub fn with_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Retry failed requests up to `retries` times, doubling delay between attempts.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Kill external commands, which run longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Limit number of requests per minute.
+    pub fn with_rate_limit(mut self, requests_per_minute: Option<u32>) -> Self {
+        self.rate_limiter = requests_per_minute.map(|n| Arc::new(RateLimiter::per_minute(n)));
+        self
+    }
+
+    /// Use the rate limiter of another backend, so both backends obey the same limit.
+    pub fn with_shared_rate_limit(mut self, other: &AiBackend) -> Self {
+        self.rate_limiter = other.rate_limiter.clone();
+        self
+    }
+
+    /// Executes the AI request, discarding warnings.
+    #[cfg(test)]
+    pub fn execute(&self, prompt: &str) -> Result<String> {
+        self.execute_chat(&mut std::io::sink(), "", prompt)
+    }
+
+    /// Executes the AI request with separate system instructions and user message.
+    ///
+    /// Command backends receive both parts as a single text, with instructions first.
+    /// HTTP backends send instructions as the system prompt.
+    /// Warnings about retries and cache failures are written to `err`.
+    pub fn execute_chat(&self, err: &mut dyn Write, system: &str, user: &str) -> Result<String> {
+        let Some(cache) = &self.cache else {
+            return self.execute_with_retries(err, system, user);
+        };
+
+        let key = self.cache_key(system, user);
+        if let Some(response) = cache.get(&key) {
+            return Ok(response);
+        }
+
+        let response = self.execute_with_retries(err, system, user)?;
+        if let Err(e) = cache.put(&key, &response) {
+            writeln!(
+                err,
+                "{}: {e:#}",
+                tr!("WARNING: Cannot store AI response in cache")
+            )?;
+        }
+        Ok(response)
+    }
+
+    /// Returns hash of backend settings and prompt, to use as cache key.
+    fn cache_key(&self, system: &str, user: &str) -> String {
+        let mut hasher = Sha256::new();
+        match &self.provider {
+            AiProvider::Command { command, args } => {
+                hasher.update(b"command\0");
+                hasher.update(command.as_bytes());
+                for arg in args {
+                    hasher.update(b"\0");
+                    hasher.update(arg.as_bytes());
+                }
+            }
+            AiProvider::OpenAiChat(api) | AiProvider::OllamaGenerate(api) => {
+                let kind = match self.provider {
+                    AiProvider::OpenAiChat(_) => "openai",
+                    _ => "ollama",
+                };
+                hasher.update(
+                    format!(
+                        "{kind}\0{}\0{}\0{:?}",
+                        api.endpoint, api.model, api.temperature
+                    )
+                    .as_bytes(),
+                );
+            }
+            #[cfg(test)]
+            AiProvider::Mock(_) => hasher.update(b"mock"),
+        }
+        hasher.update(b"\0");
+        hasher.update(system.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(user.as_bytes());
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn execute_with_retries(
+        &self,
+        err: &mut dyn Write,
+        system: &str,
+        user: &str,
+    ) -> Result<String> {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.wait();
+            }
+
+            match self.execute_uncached(system, user) {
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < self.retries && is_retriable(&e) => {
+                    let delay = self.retry_delay.saturating_mul(1 << attempt.min(16));
+                    writeln!(
+                        err,
+                        "{}: {e:#}",
+                        tr!("WARNING: AI request failed (attempt {attempt} of {total}), retrying in {seconds} seconds")
+                            .replace("{attempt}", &(attempt + 1).to_string())
+                            .replace("{total}", &(self.retries + 1).to_string())
+                            .replace("{seconds}", &format!("{:.1}", delay.as_secs_f64()))
+                    )?;
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) if attempt > 0 => {
+                    return Err(e.context(
+                        tr!("AI request failed after {count} attempts.")
+                            .replace("{count}", &(attempt + 1).to_string()),
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn execute_uncached(&self, system: &str, user: &str) -> Result<String> {
+        match &self.provider {
+            #[cfg(test)]
+            AiProvider::Mock(response) => Ok(response.clone()),
+            AiProvider::Command { command, args } => {
+                let args_ref: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+                pipe_to_command_with_timeout(
+                    command,
+                    &args_ref,
+                    &join_prompt(system, user),
+                    self.timeout,
+                )
+            }
+            AiProvider::OpenAiChat(api) => {
+                let mut messages = Vec::new();
+                if !system.is_empty() {
+                    messages.push(json!({ "role": "system", "content": system }));
+                }
+                messages.push(json!({ "role": "user", "content": user }));
+
+                let mut request = json!({
+                    "model": api.model,
+                    "messages": messages,
+                    "stream": false,
+                });
+                if let Some(t) = api.temperature {
+                    request["temperature"] = json!(t);
+                }
+
+                let reply = post_json(api, &request)?;
+                match reply["choices"][0]["message"]["content"].as_str() {
+                    Some(content) => Ok(content.to_string()),
+                    None => bail!(
+                        "{}: {reply}",
+                        tr!("Unexpected reply from chat completions API")
+                    ),
+                }
+            }
+            AiProvider::OllamaGenerate(api) => {
+                let mut request = json!({
+                    "model": api.model,
+                    "prompt": user,
+                    "stream": false,
+                });
+                if !system.is_empty() {
+                    request["system"] = json!(system);
+                }
+                if let Some(t) = api.temperature {
+                    request["options"] = json!({ "temperature": t });
+                }
+
+                let reply = post_json(api, &request)?;
+                match reply["response"].as_str() {
+                    Some(response) => Ok(response.to_string()),
+                    None => bail!("{}: {reply}", tr!("Unexpected reply from Ollama API")),
+                }
+            }
+        }
+    }
+}
+
+/// Joins system instructions and user message into a single prompt.
+///
+/// Instructions are wrapped in `<instruction>` tag, to separate them from the message.
+fn join_prompt(system: &str, user: &str) -> String {
+    if system.is_empty() {
+        user.to_string()
+    } else {
+        format!("<instruction>\n{system}\n</instruction>\n{user}")
+    }
+}
+
+/// Sends JSON request to the HTTP API and returns parsed JSON reply.
+fn post_json(api: &HttpApi, request: &Value) -> Result<Value> {
+    let agent = ureq::AgentBuilder::new().timeout(api.timeout).build();
+    let mut req = agent
+        .post(&api.endpoint)
+        .set("Content-Type", "application/json");
+
+    if let Some(var) = &api.api_key_env {
+        let key = std::env::var(var).with_context(|| {
+            tr!("Environment variable \"{var}\" with API key is not set.").replace("{var}", var)
+        })?;
+        req = req.set("Authorization", &format!("Bearer {key}"));
+    }
+
+    let body = match req.send_string(&request.to_string()) {
+        Ok(response) => response.into_string()?,
+        Err(ureq::Error::Status(code, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            return Err(HttpStatusError {
+                code,
+                message: format!(
+                    "{}\n{body}",
+                    tr!("Request to \"{endpoint}\" failed with HTTP status {code}.")
+                        .replace("{endpoint}", &api.endpoint)
+                        .replace("{code}", &code.to_string())
+                ),
+            }
+            .into());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| {
+                tr!("Request to \"{endpoint}\" failed.").replace("{endpoint}", &api.endpoint)
+            });
+        }
+    };
+
+    serde_json::from_str(&body).with_context(|| {
+        tr!("Cannot parse JSON reply from \"{endpoint}\".").replace("{endpoint}", &api.endpoint)
+    })
+}
+
+/// Command line options, which select and configure AI backend.
+///
+/// Shared by all commands, which use AI.
+#[derive(Clone)]
+pub struct AiBackendOptions<'a> {
+    /// Model, given by `--model`. Command backends use `DEFAULT_MODEL` without it.
+    pub model: Option<&'a str>,
+    pub role: &'a str,
+    pub rag: Option<&'a str>,
+    pub ai_command: Option<&'a str>,
+    pub api: Option<&'a str>,
+    pub endpoint: Option<&'a str>,
+    pub temperature: Option<f64>,
+    pub api_key_env: Option<&'a str>,
+    pub timeout: Duration,
+    pub no_cache: bool,
+    pub refresh_cache: bool,
+    pub cache_dir: Option<&'a str>,
+    pub retries: u32,
+    pub retry_delay: Duration,
+    pub max_requests_per_minute: Option<u32>,
+}
+
+impl<'a> Default for AiBackendOptions<'a> {
+    fn default() -> Self {
+        Self {
+            model: None,
+            role: DEFAULT_ROLE,
+            rag: None,
+            ai_command: None,
+            api: None,
+            endpoint: None,
+            temperature: None,
+            api_key_env: None,
+            timeout: Duration::from_secs(600),
+            no_cache: false,
+            refresh_cache: false,
+            cache_dir: None,
+            retries: 2,
+            retry_delay: Duration::from_secs(2),
+            max_requests_per_minute: None,
+        }
+    }
+}
+
+impl<'a> AiBackendOptions<'a> {
+    /// Tries to parse an AI backend option at the start of the command line.
+    ///
+    /// Returns the rest of the command line, if option is recognized.
+    pub fn parse_option<'c>(&mut self, cmdline: &'c [&'a str]) -> Result<Option<&'c [&'a str]>> {
+        match cmdline {
+            ["-m", value, rest @ ..] | ["--model", value, rest @ ..] => {
+                self.model = Some(value);
+                Ok(Some(rest))
+            }
+            ["-r", value, rest @ ..] | ["--role", value, rest @ ..] => {
+                self.role = value;
+                Ok(Some(rest))
+            }
+            ["-R", value, rest @ ..] | ["--rag", value, rest @ ..] => {
+                self.rag = Some(value);
+                Ok(Some(rest))
+            }
+            ["-c", value, rest @ ..] | ["--ai-command", value, rest @ ..] => {
+                self.ai_command = Some(value);
+                Ok(Some(rest))
+            }
+            ["--api", value @ ("openai" | "ollama"), rest @ ..] => {
+                self.api = Some(value);
+                Ok(Some(rest))
+            }
+            ["--api", value, ..] => bail!(
+                "{}",
+                tr!("Invalid argument for --api option. Expected: openai or ollama. Actual value: \"{value}\".")
+                    .replace("{value}", value)
+            ),
+            ["--endpoint", value, rest @ ..] => {
+                self.endpoint = Some(value);
+                Ok(Some(rest))
+            }
+            ["--temperature", value, rest @ ..] => match value.parse::<f64>() {
+                Ok(t) if t >= 0.0 => {
+                    self.temperature = Some(t);
+                    Ok(Some(rest))
+                }
+                _ => bail!(
+                    "{}",
+                    tr!("Invalid argument for --temperature option. Expected: non-negative number. Actual value: \"{value}\".")
+                        .replace("{value}", value)
+                ),
+            },
+            ["--api-key-env", value, rest @ ..] => {
+                self.api_key_env = Some(value);
+                Ok(Some(rest))
+            }
+            ["--timeout", value, rest @ ..] => match value.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => {
+                    self.timeout = Duration::from_secs(seconds);
+                    Ok(Some(rest))
+                }
+                _ => bail!(
+                    "{}",
+                    tr!("Invalid argument for --timeout option. Expected: positive number of seconds. Actual value: \"{value}\".")
+                        .replace("{value}", value)
+                ),
+            },
+            ["--no-cache", rest @ ..] => {
+                self.no_cache = true;
+                Ok(Some(rest))
+            }
+            ["--refresh-cache", rest @ ..] => {
+                self.refresh_cache = true;
+                Ok(Some(rest))
+            }
+            ["--cache-dir", value, rest @ ..] => {
+                self.cache_dir = Some(value);
+                Ok(Some(rest))
+            }
+            ["--retries", value, rest @ ..] => match value.parse::<u32>() {
+                Ok(n) => {
+                    self.retries = n;
+                    Ok(Some(rest))
+                }
+                _ => bail!(
+                    "{}",
+                    tr!("Invalid argument for --retries option. Expected: number of retries. Actual value: \"{value}\".")
+                        .replace("{value}", value)
+                ),
+            },
+            ["--retry-delay", value, rest @ ..] => match value.parse::<f64>() {
+                Ok(seconds) if (0.0..=3600.0).contains(&seconds) => {
+                    self.retry_delay = Duration::from_secs_f64(seconds);
+                    Ok(Some(rest))
+                }
+                _ => bail!(
+                    "{}",
+                    tr!("Invalid argument for --retry-delay option. Expected: number of seconds. Actual value: \"{value}\".")
+                        .replace("{value}", value)
+                ),
+            },
+            ["--max-rpm", value, rest @ ..] => match value.parse::<u32>() {
+                Ok(n) if n > 0 => {
+                    self.max_requests_per_minute = Some(n);
+                    Ok(Some(rest))
+                }
+                _ => bail!(
+                    "{}",
+                    tr!("Invalid argument for --max-rpm option. Expected: positive number of requests per minute. Actual value: \"{value}\".")
+                        .replace("{value}", value)
+                ),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Creates AI backend from the options.
+    ///
+    /// HTTP APIs need an explicit model, because the default model is in aichat format.
+    pub fn build(&self) -> Result<AiBackend> {
+        let model = match (self.model, self.api) {
+            (Some(model), _) => model,
+            (None, None) => DEFAULT_MODEL,
+            (None, Some(api)) => bail!(
+                "{}",
+                tr!("Option --model is required with --api {api}.").replace("{api}", api)
+            ),
+        };
+        let http_api = |default_endpoint: &str, default_key_env: Option<&str>| HttpApi {
+            endpoint: self.endpoint.unwrap_or(default_endpoint).to_string(),
+            model: model.to_string(),
+            temperature: self.temperature,
+            api_key_env: self.api_key_env.or(default_key_env).map(|s| s.to_string()),
+            timeout: self.timeout,
+        };
+
+        let backend = match (self.api, self.ai_command) {
+            (Some("openai"), _) => AiBackend::openai_chat(http_api(
+                "https://api.openai.com/v1/chat/completions",
+                Some("OPENAI_API_KEY"),
+            )),
+            (Some(_), _) => {
+                AiBackend::ollama_generate(http_api("http://localhost:11434/api/generate", None))
+            }
+            (None, Some(cmd)) => AiBackend::from_command_line(cmd),
+            (None, None) => AiBackend::with_aichat_defaults(model, self.role, self.rag),
+        };
+
+        let cache = if self.no_cache {
+            None
+        } else {
+            self.cache_dir
+                .map(PathBuf::from)
+                .or_else(ResponseCache::default_dir)
+                .map(|dir| ResponseCache::new(dir, self.refresh_cache))
+        };
+
+        Ok(backend
+            .with_cache(cache)
+            .with_retries(self.retries, self.retry_delay)
+            .with_timeout(Some(self.timeout))
+            .with_rate_limit(self.max_requests_per_minute))
+    }
+}
+
+/// Help text for AI backend options, shared by all AI commands.
+pub fn help_ai_backend_options() -> &'static str {
+    tr!(r#"AI BACKEND OPTIONS:
+
+  -m | --model MODEL    AI model to use. Default value: "ollama:translategemma:12b".
+
+  -r | --role ROLE      AI role to use with aichat.  Default value: "translate-po".
+                        For better reproducibility, set temperature and top_p to 0, to remove randomness.
+
+  -R | --rag RAG        aichat RAG to use.
+
+  -c | --ai-command COMMAND  Custom command to use instead of aichat.
+                        Example: --ai-command "ollama run gemma4:latest"
+                        Options --model, --role, --rag will not work with this option.
+
+  --api openai|ollama   Call HTTP API directly instead of external command:
+                        OpenAI-compatible chat completions, or Ollama generate API.
+                        Model name is passed to the API verbatim, so --model is required.
+
+  --endpoint URL        Endpoint of the API. Default values:
+                        "https://api.openai.com/v1/chat/completions" for openai,
+                        "http://localhost:11434/api/generate" for ollama.
+
+  --temperature T       Sampling temperature for the API.
+
+  --api-key-env VAR     Environment variable with API key. Default value: "OPENAI_API_KEY" for openai.
+
+  --timeout SECONDS     Timeout for a single AI request. Commands running longer are killed.
+                        Default value: 600.
+
+  --retries N           Number of retries after a failed AI request. HTTP client errors,
+                        except 429 Too Many Requests, are not retried. Default value: 2.
+
+  --retry-delay SECONDS  Delay before first retry, doubled for each next retry. Default value: 2.
+
+  --max-rpm N           Maximum number of AI requests per minute.
+
+  --no-cache            Don't use cache of AI responses.
+
+  --refresh-cache       Don't read cached AI responses, but store new ones.
+
+  --cache-dir DIR       Directory for cache of AI responses.
+                        Default value: "$XDG_CACHE_HOME/po-tools" or "~/.cache/po-tools".
+"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Starts HTTP server, which answers single request with given body.
+    ///
+    /// Returns URL of the server and receiver for the body of the request.
+    fn mock_http_server(status: u16, reply: &str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        let reply = reply.to_string();
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut content_length = 0;
+            let mut headers = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                headers.push_str(&line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            sender
+                .send(format!("{headers}\n{}", String::from_utf8(body).unwrap()))
+                .unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 {status} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                reply.len()
+            )
+            .unwrap();
+        });
+
+        (url, receiver)
+    }
+
+    fn test_api(endpoint: String) -> HttpApi {
+        HttpApi {
+            endpoint,
+            model: "test-model".to_string(),
+            temperature: Some(0.0),
+            api_key_env: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    #[test]
+    fn test_ai_backend_mock() -> Result<()> {
+        let backend = AiBackend::mock("custom response");
+        let result = backend.execute("any prompt")?;
+        assert_eq!(result, "custom response");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_ai_backend_command_joins_prompt() -> Result<()> {
+        let backend = AiBackend::new("cat".to_string(), Vec::new());
+        let result = backend.execute_chat(&mut std::io::sink(), "system", "user")?;
+        assert_eq!(result, "<instruction>\nsystem\n</instruction>\nuser");
+        Ok(())
+    }
+
+    #[test]
+    fn test_openai_chat_backend() -> Result<()> {
+        let (url, request) = mock_http_server(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"reply text"}}]}"#,
+        );
+        let backend = AiBackend::openai_chat(test_api(url));
+
+        let result = backend.execute_chat(&mut std::io::sink(), "instructions", "message")?;
+        assert_eq!(result, "reply text");
+
+        let request = request.recv()?;
+        assert!(request.contains(r#""role":"system""#));
+        assert!(request.contains(r#""content":"instructions""#));
+        assert!(request.contains(r#""content":"message""#));
+        assert!(request.contains(r#""model":"test-model""#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ollama_generate_backend() -> Result<()> {
+        let (url, request) = mock_http_server(200, r#"{"response":"reply text","done":true}"#);
+        let backend = AiBackend::ollama_generate(test_api(url));
+
+        let result = backend.execute_chat(&mut std::io::sink(), "instructions", "message")?;
+        assert_eq!(result, "reply text");
+
+        let request = request.recv()?;
+        assert!(request.contains(r#""system":"instructions""#));
+        assert!(request.contains(r#""prompt":"message""#));
+        assert!(request.contains(r#""temperature":0.0"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_backend_api_key() -> Result<()> {
+        let (url, request) = mock_http_server(200, r#"{"response":"ok"}"#);
+        let api = HttpApi {
+            api_key_env: Some("PO_TOOLS_TEST_API_KEY".to_string()),
+            ..test_api(url)
+        };
+        // Variable is used by this test only
+        unsafe { std::env::set_var("PO_TOOLS_TEST_API_KEY", "test-key") };
+        AiBackend::ollama_generate(api).execute("message")?;
+
+        let request = request.recv()?.to_ascii_lowercase();
+        assert!(request.contains("authorization: bearer test-key"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_backend_error_status() {
+        let (url, _request) = mock_http_server(500, r#"{"error":"model not found"}"#);
+        let backend = AiBackend::ollama_generate(test_api(url));
+
+        let result = backend.execute("message");
+        assert!(format!("{:#}", result.unwrap_err()).contains("model not found"));
+    }
+
+    #[test]
+    fn test_client_errors_are_not_retried() {
+        let (url, _request) = mock_http_server(404, r#"{"error":"model not found"}"#);
+        let backend =
+            AiBackend::ollama_generate(test_api(url)).with_retries(2, Duration::from_millis(10));
+
+        let mut err = Vec::new();
+        assert!(backend.execute_chat(&mut err, "", "message").is_err());
+        assert!(err.is_empty());
+
+        let error = anyhow::Error::from(HttpStatusError {
+            code: 503,
+            message: String::new(),
+        });
+        assert!(is_retriable(&error));
+    }
+
+    #[test]
+    fn test_backend_options() -> Result<()> {
+        let mut options = AiBackendOptions::default();
+        let cmdline = ["--api", "ollama", "--temperature", "0.5", "FILE"];
+
+        let rest = options.parse_option(&cmdline)?.unwrap();
+        let rest = options.parse_option(rest)?.unwrap();
+        assert_eq!(rest, ["FILE"]);
+        assert!(options.parse_option(rest)?.is_none());
+
+        // Default model of aichat is not passed to HTTP API
+        assert!(options.build().is_err());
+        options.parse_option(&["-m", "gemma3:12b"])?;
+        assert!(matches!(
+            options.build()?.provider,
+            AiProvider::OllamaGenerate(HttpApi {
+                temperature: Some(0.5),
+                ref model,
+                ..
+            }) if model == "gemma3:12b"
+        ));
+        assert!(options.parse_option(&["--api", "unknown"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_replays_recorded_response() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(dir.path().to_path_buf(), false);
+
+        // Record response from a working backend
+        let backend = AiBackend::mock("recorded").with_cache(Some(cache.clone()));
+        assert_eq!(
+            backend.execute_chat(&mut std::io::sink(), "system", "user")?,
+            "recorded"
+        );
+
+        // Same settings and prompt are answered from cache, without calling the backend
+        let cached = AiBackend::mock("new").with_cache(Some(cache));
+        assert_eq!(
+            cached.execute_chat(&mut std::io::sink(), "system", "user")?,
+            "recorded"
+        );
+        assert_eq!(
+            cached.execute_chat(&mut std::io::sink(), "system", "other")?,
+            "new"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_cache_key_depends_on_backend() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(dir.path().to_path_buf(), false);
+
+        AiBackend::new("cat".to_string(), vec!["-".to_string()])
+            .with_cache(Some(cache.clone()))
+            .execute("prompt")?;
+
+        // Failing command with different arguments must not be answered from cache
+        let other = AiBackend::new("false".to_string(), Vec::new()).with_cache(Some(cache));
+        assert!(other.execute("prompt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_refresh() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = ResponseCache::new(dir.path().to_path_buf(), false);
+        let refresh = ResponseCache::new(dir.path().to_path_buf(), true);
+
+        AiBackend::mock("old")
+            .with_cache(Some(cache.clone()))
+            .execute("prompt")?;
+        let refreshed = AiBackend::mock("new")
+            .with_cache(Some(refresh))
+            .execute("prompt")?;
+        assert_eq!(refreshed, "new");
+
+        // Refreshed response replaces the old one
+        let cached = AiBackend::mock("other")
+            .with_cache(Some(cache))
+            .execute("prompt")?;
+        assert_eq!(cached, "new");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_retries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let marker = dir.path().join("marker");
+        let marker = marker.to_str().unwrap();
+
+        // Command fails on first call and succeeds on second one
+        let script = format!("if [ -e {marker} ]; then echo ok; else touch {marker}; exit 1; fi");
+        let backend = AiBackend::new("sh".to_string(), vec!["-c".to_string(), script.clone()]);
+        assert!(backend.execute("prompt").is_err());
+        std::fs::remove_file(marker)?;
+
+        let backend = AiBackend::new("sh".to_string(), vec!["-c".to_string(), script])
+            .with_retries(1, Duration::from_millis(10));
+        let mut err = Vec::new();
+        assert_eq!(backend.execute_chat(&mut err, "", "prompt")?, "ok\n");
+        assert!(String::from_utf8(err)?.starts_with("WARNING: AI request failed (attempt 1 of 2)"));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_command_timeout() {
+        let backend = AiBackend::new("sleep".to_string(), vec!["10".to_string()])
+            .with_timeout(Some(Duration::from_millis(100)));
+        assert!(backend.execute("prompt").is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::per_minute(1200);
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        // 1200 requests per minute means 50ms between requests
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_shared_rate_limit() -> Result<()> {
+        let first = AiBackend::mock("first").with_rate_limit(Some(1200));
+        let second = AiBackend::mock("second").with_shared_rate_limit(&first);
+
+        let started = Instant::now();
+        for _ in 0..2 {
+            first.execute("prompt")?;
+            second.execute("prompt")?;
+        }
+        // Four requests of both backends are spaced by 50ms
+        assert!(started.elapsed() >= Duration::from_millis(150));
+        Ok(())
+    }
+}
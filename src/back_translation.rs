@@ -0,0 +1,163 @@
+//! Back-translation of AI translations to English, to catch hallucinated translations.
+//!
+//! Translated msgstr is sent to an AI model, ideally a different one, to translate it
+//! back to English. The back-translation is compared with msgid, and translations with
+//! low similarity are reported.
+
+use crate::ai_backend::AiBackend;
+use crate::diagnostics::Diagnostic;
+use crate::parser::{PoMessage, escape_comment};
+use anyhow::Result;
+use std::io::Write;
+use strsim::normalized_levenshtein;
+
+/// Default minimal similarity of back-translation to msgid, from 0.0 to 1.0.
+pub const DEFAULT_MIN_SIMILARITY: f64 = 0.5;
+
+/// Translates msgstr back to English and compares it with msgid.
+pub struct BackTranslator {
+    backend: AiBackend,
+    /// Back-translations with lower similarity to msgid are reported.
+    min_similarity: f64,
+}
+
+impl BackTranslator {
+    pub fn new(backend: AiBackend, min_similarity: f64) -> Self {
+        Self {
+            backend,
+            min_similarity,
+        }
+    }
+
+    /// Asks AI model to translate msgstr of the message back to English.
+    pub fn back_translate(
+        &self,
+        err: &mut dyn Write,
+        language: &str,
+        message: &PoMessage,
+    ) -> Result<String> {
+        let instruction = format!(
+            r#"You are a professional {language} to English (en_US) translator.
+Translate the {language} text in <text></text> tag to English literally, preserving its meaning, placeholders and markup.
+Produce only the translation in <translation></translation> tag, without any additional explanations or commentary."#
+        );
+        let text = format!("<text>{}</text>\n", message.msgstr_first());
+
+        let reply = self.backend.execute_chat(err, &instruction, &text)?;
+        Ok(extract_translation(&reply).to_string())
+    }
+
+    /// Back-translates the message and returns a warning, when back-translation differs
+    /// from msgid too much.
+    pub fn check(
+        &self,
+        err: &mut dyn Write,
+        language: &str,
+        message: &PoMessage,
+    ) -> Result<Option<Diagnostic>> {
+        if message.is_header() || message.msgstr_first().is_empty() {
+            return Ok(None);
+        }
+
+        let back_translation = self.back_translate(err, language, message)?;
+        let similarity = similarity(&message.msgid, &back_translation);
+        if similarity >= self.min_similarity {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            Diagnostic::warning(
+                tr!("Back-translation differs from msgid, similarity is {similarity}%, but minimum is {min}%.")
+                    .replace("{similarity}", &format!("{:.0}", similarity * 100.0))
+                    .replace("{min}", &format!("{:.0}", self.min_similarity * 100.0)),
+            )
+            .with_rule("back-translation")
+            .with_detail(
+                tr!("Back-translation: \"{text}\"")
+                    .replace("{text}", &escape_comment(&back_translation)),
+            ),
+        ))
+    }
+}
+
+/// Returns similarity of two English texts, from 0.0 to 1.0, ignoring case and surrounding whitespace.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    normalized_levenshtein(&a.trim().to_lowercase(), &b.trim().to_lowercase())
+}
+
+/// Extracts text between `<translation>` and `</translation>`, or returns the whole reply.
+fn extract_translation(reply: &str) -> &str {
+    let reply = match reply.rfind("</think>") {
+        Some(start) => &reply[(start + "</think>".len())..],
+        None => reply,
+    };
+    match (reply.rfind("<translation>"), reply.rfind("</translation>")) {
+        (Some(start), Some(end)) if start < end => {
+            reply[(start + "<translation>".len())..end].trim()
+        }
+        _ => reply.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msgid: &str, msgstr: &str) -> PoMessage {
+        PoMessage {
+            msgid: msgid.to_string(),
+            msgstr: vec![msgstr.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_extract_translation() {
+        assert_eq!(
+            extract_translation("<think>hmm</think>\n<translation> Open file </translation>"),
+            "Open file"
+        );
+        assert_eq!(extract_translation("Open file\n"), "Open file");
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Open file", " open file"), 1.0);
+        assert!(similarity("Open file", "Delete all users") < 0.5);
+    }
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let mut err = Vec::new();
+        let checker = BackTranslator::new(
+            AiBackend::mock("<translation>Open the file</translation>"),
+            DEFAULT_MIN_SIMILARITY,
+        );
+        assert_eq!(
+            checker.check(
+                &mut err,
+                "Ukrainian",
+                &message("Open a file", "Відкрити файл")
+            )?,
+            None
+        );
+
+        let warning = checker
+            .check(
+                &mut err,
+                "Ukrainian",
+                &message("Delete all users", "Відкрити файл"),
+            )?
+            .unwrap()
+            .to_comment();
+        assert!(warning.starts_with("# Warning [back-translation]: "));
+        assert!(warning.ends_with("# Back-translation: \"Open the file\"\n"));
+
+        // Untranslated messages are not checked
+        assert_eq!(
+            checker.check(&mut err, "Ukrainian", &message("Open", ""))?,
+            None
+        );
+        Ok(())
+    }
+}
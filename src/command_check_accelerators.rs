@@ -0,0 +1,426 @@
+//! Command to check accelerator keys of menu items and buttons, e.g. `&File` or `_Open`.
+//!
+//! Each translated message must keep the accelerator marker, when msgid has one, and
+//! messages of the same menu must not use the same accelerator letter.
+
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::parser::{Parser, PoMessage, escape_comment};
+use crate::source_context::references;
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Returns accelerator keys in the text: characters after the marker.
+///
+/// A doubled marker, e.g. `&&`, is a literal marker. For `&`, entities like `&amp;`
+/// are not accelerators. Markers inside of words, e.g. `E&xit`, are accelerators too,
+/// except for markers other than `&` in identifiers like `snake_case` or `LC_ALL`.
+pub fn find_accelerators(text: &str, marker: char) -> Vec<char> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut accelerators = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != marker {
+            i += 1;
+            continue;
+        }
+        match chars.get(i + 1) {
+            Some(&c) if c == marker => i += 2,
+            Some(&c)
+                if c.is_alphanumeric() && (marker == '&' || !is_identifier(&chars, i, marker)) =>
+            {
+                let word = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '#')
+                    .count();
+                let is_entity = marker == '&' && chars.get(i + 1 + word) == Some(&';');
+                if !is_entity {
+                    accelerators.push(c);
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+
+    accelerators
+}
+
+/// Returns true, when the marker at position `i` is inside of an identifier-like word,
+/// e.g. `snake_case`, `LC_ALL`, or a word with several markers.
+fn is_identifier(chars: &[char], i: usize, marker: char) -> bool {
+    let is_word_char = |c: &&char| c.is_alphanumeric() || **c == marker;
+    let left: Vec<char> = chars[..i]
+        .iter()
+        .rev()
+        .take_while(is_word_char)
+        .copied()
+        .collect();
+    let right: Vec<char> = chars[i + 1..]
+        .iter()
+        .take_while(is_word_char)
+        .copied()
+        .collect();
+    if left.contains(&marker) || right.contains(&marker) {
+        return true;
+    }
+    // Marker at the start of a word is an accelerator
+    if left.is_empty() {
+        return false;
+    }
+
+    let is_upper = |c: &char| c.is_ascii_uppercase() || c.is_ascii_digit();
+    let is_lower = |c: &char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    (left.iter().all(is_upper) && right.iter().all(is_upper))
+        || (left.iter().all(is_lower) && right.iter().all(is_lower))
+}
+
+/// Checks accelerator markers of a single message.
+///
+/// Returns problems when msgstr doesn't have exactly one accelerator while msgid has one,
+/// or when msgstr has an accelerator while msgid doesn't.
+pub fn check_accelerators(message: &PoMessage, marker: char) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return errors;
+    }
+
+    let expected = find_accelerators(&message.msgid, marker).len();
+    for msgstr in &message.msgstr {
+        let actual = find_accelerators(msgstr, marker).len();
+        if expected > 0 && actual != 1 {
+            errors.push(Diagnostic::error(
+                tr!("msgid has accelerator marker \"{marker}\", but msgstr has {count} accelerators. Expected: exactly one. Actual msgstr: \"{actual}\"")
+                    .replace("{marker}", &marker.to_string())
+                    .replace("{count}", &actual.to_string())
+                    .replace("{actual}", &escape_comment(msgstr)),
+            ));
+        } else if expected == 0 && actual > 0 {
+            errors.push(Diagnostic::warning(
+                tr!("msgstr has accelerator marker \"{marker}\", but msgid has none. Actual msgstr: \"{actual}\"")
+                    .replace("{marker}", &marker.to_string())
+                    .replace("{actual}", &escape_comment(msgstr)),
+            ));
+        }
+    }
+    errors
+}
+
+/// Returns name of the menu of the message: msgctxt, or file from the first reference.
+fn menu_of(message: &PoMessage) -> Option<String> {
+    match &message.msgctxt {
+        Some(msgctxt) => Some(msgctxt.clone()),
+        None => references(message).into_iter().next().map(|(file, _)| file),
+    }
+}
+
+/// Lines and translations of messages, grouped by menu and lowercase accelerator.
+type Menus<'a> = BTreeMap<String, BTreeMap<String, Vec<(usize, &'a str)>>>;
+
+/// Finds messages of the same menu, which use the same accelerator letter.
+///
+/// Returns line of the first message and warning for each collision.
+fn find_collisions(messages: &[(usize, PoMessage)], marker: char) -> Vec<(usize, Diagnostic)> {
+    let mut menus: Menus = BTreeMap::new();
+
+    for (line, message) in messages {
+        if message.is_header() || !message.is_translated() || message.is_plural() {
+            continue;
+        }
+        let Some(menu) = menu_of(message) else {
+            continue;
+        };
+        let msgstr = message.msgstr_first();
+        if let [accelerator] = find_accelerators(msgstr, marker)[..] {
+            menus
+                .entry(menu)
+                .or_default()
+                .entry(accelerator.to_lowercase().collect())
+                .or_default()
+                .push((*line, msgstr));
+        }
+    }
+
+    let mut reports = Vec::new();
+    for (menu, accelerators) in menus {
+        for (accelerator, translations) in accelerators {
+            if translations.len() < 2 {
+                continue;
+            }
+            let mut warning = Diagnostic::warning(
+                tr!("Accelerator \"{accelerator}\" is used by {count} messages in \"{menu}\":")
+                    .replace("{accelerator}", &accelerator)
+                    .replace("{count}", &translations.len().to_string())
+                    .replace("{menu}", &menu),
+            );
+            for (_, msgstr) in &translations {
+                warning = warning.with_detail(format!("  \"{}\"", escape_comment(msgstr)));
+            }
+            reports.push((translations[0].0, warning));
+        }
+    }
+    reports
+}
+
+/// Implementation of the `check-accelerators` command.
+pub fn command_check_accelerators(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut marker = '&';
+    let mut check_collisions = true;
+
+    // Parse "check-accelerators" command options
+    let mut cmdline = cmdline;
+    loop {
+        match cmdline[..] {
+            ["-m", value, ref tail @ ..] | ["--marker", value, ref tail @ ..] => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_alphanumeric() => marker = c,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --marker option. Expected: single non-alphanumeric character, e.g. \"&\", \"_\", or \"~\". Actual value: \"{value}\".")
+                            .replace("{value}", value)
+                    ),
+                }
+                cmdline = tail;
+            }
+            ["--no-collisions", ref tail @ ..] => {
+                check_collisions = false;
+                cmdline = tail;
+            }
+            ["-h", ..] | ["--help", ..] => {
+                help(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let mut reporter = Reporter::new(options.output_format, "check-accelerators");
+    let mut violations = 0;
+    for file in cmdline {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            let errors = check_accelerators(message, marker);
+            if !errors.is_empty() {
+                violations += 1;
+                reporter.report(ctx, file, *line, message, &errors)?;
+            }
+        }
+
+        if check_collisions {
+            for (line, warning) in find_collisions(&messages, marker) {
+                violations += 1;
+                reporter.report_group(ctx, file, line, &[warning])?;
+            }
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} problems with accelerators.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-accelerators [OPTIONS] [--] FILE...
+
+Check accelerator keys of menu items and buttons, e.g. "&File" or "_Open":
+
+  * when msgid has an accelerator, msgstr must have exactly one accelerator;
+  * when msgid has no accelerator, msgstr must have none;
+  * messages of the same menu must not use the same accelerator letter. Messages
+    belong to the same menu when they have the same msgctxt, or, without msgctxt,
+    when their first reference is to the same source file.
+
+A doubled marker, e.g. "&&", is a literal character, not an accelerator.
+
+OPTIONS:
+
+  -m | --marker CHAR    Accelerator marker, e.g. "&" (Qt, Windows), "_" (GTK), or "~" (LibreOffice).
+                        Default value: "&".
+
+  --no-collisions       Don't check for duplicate accelerator letters.
+
+Exit status is non-zero when problems are found.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_find_accelerators() {
+        assert_eq!(find_accelerators("&File", '&'), vec!['F']);
+        assert_eq!(find_accelerators("Save && &Quit", '&'), vec!['Q']);
+        assert_eq!(
+            find_accelerators("Copy &amp; paste", '&'),
+            Vec::<char>::new()
+        );
+        assert_eq!(find_accelerators("_Відкрити", '_'), vec!['В']);
+        assert_eq!(find_accelerators("snake_case", '_'), Vec::<char>::new());
+        assert_eq!(find_accelerators("Set LC_ALL", '_'), Vec::<char>::new());
+        assert_eq!(find_accelerators("Save _As", '_'), vec!['A']);
+        assert_eq!(find_accelerators("(_Open)", '_'), vec!['O']);
+        assert_eq!(find_accelerators("Ends with &", '&'), Vec::<char>::new());
+        assert_eq!(find_accelerators("E&xit", '&'), vec!['x']);
+        assert_eq!(find_accelerators("Від&крити", '&'), vec!['к']);
+        assert_eq!(find_accelerators("Від_крити", '_'), vec!['к']);
+        assert_eq!(find_accelerators("my_long_name", '_'), Vec::<char>::new());
+    }
+
+    #[test]
+    fn test_check_accelerators() -> Result<()> {
+        let parser = Parser::new(None);
+
+        let message = parser.parse_message_from_str("msgid \"&Open\"\nmsgstr \"&Відкрити\"\n")?;
+        assert!(check_accelerators(&message, '&').is_empty());
+
+        let message = parser.parse_message_from_str("msgid \"&Open\"\nmsgstr \"Від&крити\"\n")?;
+        assert!(check_accelerators(&message, '&').is_empty());
+        let message = parser.parse_message_from_str("msgid \"E&xit\"\nmsgstr \"&Вийти\"\n")?;
+        assert!(check_accelerators(&message, '&').is_empty());
+
+        let message = parser.parse_message_from_str("msgid \"&Open\"\nmsgstr \"Відкрити\"\n")?;
+        assert!(
+            check_accelerators(&message, '&')[0]
+                .text
+                .contains("msgstr has 0 accelerators")
+        );
+
+        let message =
+            parser.parse_message_from_str("msgid \"&Open\"\nmsgstr \"&Відкрити &файл\"\n")?;
+        assert!(
+            check_accelerators(&message, '&')[0]
+                .text
+                .contains("msgstr has 2 accelerators")
+        );
+
+        let message = parser.parse_message_from_str("msgid \"Open\"\nmsgstr \"&Відкрити\"\n")?;
+        assert!(
+            check_accelerators(&message, '&')[0]
+                .text
+                .contains("but msgid has none")
+        );
+
+        // Multi-line msgstr stays in a single comment line
+        let message = parser
+            .parse_message_from_str("msgid \"&Open\\nfile\"\nmsgstr \"Відкрити\\nфайл\"\n")?;
+        let errors = to_comments(&check_accelerators(&message, '&'));
+        assert!(errors.ends_with("Actual msgstr: \"Відкрити\\nфайл\"\n"));
+        assert_eq!(errors.lines().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_accelerators_command() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+#: src/menu.c:10
+msgid "_File"
+msgstr "_Файл"
+
+#: src/menu.c:11
+msgid "_Format"
+msgstr "_формат"
+
+#: src/dialog.c:5
+msgid "_Find"
+msgstr "_Знайти"
+
+msgctxt "Edit menu"
+msgid "_Zoom"
+msgstr "_Збільшити"
+"#,
+        )?;
+
+        let result = command_check_accelerators(
+            &parser,
+            &GlobalOptions::default(),
+            &["-m", "_", f.path().to_str().unwrap()],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Warning: Accelerator "ф" is used by 2 messages in "src/menu.c":
+#   "_Файл"
+#   "_формат"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_marker() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        assert!(
+            command_check_accelerators(
+                &parser,
+                &GlobalOptions::default(),
+                &["-m", "ab", "file.po"],
+                &mut ctx
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! Command to check consistency of translations across PO files.
+//!
+//! Finds the same msgid translated differently in different contexts or files,
+//! and different msgids with the same translation.
+
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::parser::{Parser, PoMessage, escape_comment};
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Location of a message in checked files.
+#[derive(Clone)]
+struct Location<'a> {
+    /// Index of the file in the command line.
+    index: usize,
+    file: &'a str,
+    line: usize,
+    /// File name, context, and the first source reference, for humans.
+    text: String,
+}
+
+/// Locations of messages by text of their msgid or msgstr.
+type Groups<'a> = BTreeMap<Vec<String>, BTreeMap<Vec<String>, Vec<Location<'a>>>>;
+
+/// Implementation of the `check-consistency` command.
+pub fn command_check_consistency(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    match cmdline {
+        ["-h", ..] | ["--help", ..] => help_check_consistency(ctx.out)?,
+
+        files if !files.is_empty() => {
+            let mut messages = Vec::new();
+            for file in files {
+                messages.push((*file, parser.parse_numbered_messages_from_file(file)?));
+            }
+            let mut reporter = Reporter::new(options.output_format, "check-consistency");
+            check_consistency_and_report(ctx, &mut reporter, &messages)?;
+            reporter.finish(ctx)?;
+        }
+
+        _ => bail!(tr!(
+            "Expected at least one argument: the name of the file to check."
+        )),
+    }
+
+    Ok(())
+}
+
+fn check_consistency_and_report(
+    ctx: &mut IoContext,
+    reporter: &mut Reporter,
+    files: &[(&str, Vec<(usize, PoMessage)>)],
+) -> Result<()> {
+    // msgid -> translation -> locations, and translation -> msgid -> locations
+    let mut by_msgid: Groups = BTreeMap::new();
+    let mut by_msgstr: Groups = BTreeMap::new();
+
+    for (index, (file, messages)) in files.iter().enumerate() {
+        for (line, message) in messages {
+            if message.is_header() || !message.is_translated() || message.is_fuzzy() {
+                continue;
+            }
+
+            let mut msgid = vec![message.msgid.clone()];
+            msgid.extend(message.msgid_plural.iter().cloned());
+            let location = Location {
+                index,
+                file,
+                line: *line,
+                text: location(file, message),
+            };
+
+            by_msgid
+                .entry(msgid.clone())
+                .or_default()
+                .entry(message.msgstr.clone())
+                .or_default()
+                .push(location.clone());
+            by_msgstr
+                .entry(message.msgstr.clone())
+                .or_default()
+                .entry(msgid)
+                .or_default()
+                .push(location);
+        }
+    }
+
+    let mut msgid_count = 0;
+    for (msgid, translations) in by_msgid.iter().filter(|(_, v)| v.len() > 1) {
+        msgid_count += 1;
+        let warning = Diagnostic::warning(
+            tr!("Message {msgid} has {count} different translations:")
+                .replace("{msgid}", &quote(msgid))
+                .replace("{count}", &translations.len().to_string()),
+        );
+        report_group(ctx, reporter, warning, translations)?;
+    }
+
+    let mut msgstr_count = 0;
+    for (msgstr, msgids) in by_msgstr.iter().filter(|(_, v)| v.len() > 1) {
+        msgstr_count += 1;
+        let warning = Diagnostic::warning(
+            tr!("Translation {msgstr} is used for {count} different messages:")
+                .replace("{msgstr}", &quote(msgstr))
+                .replace("{count}", &msgids.len().to_string()),
+        );
+        report_group(ctx, reporter, warning, msgids)?;
+    }
+
+    writeln!(
+        ctx.err,
+        "{}: {}",
+        tr!("INFO"),
+        tr!("Found {msgids} messages with different translations and {msgstrs} translations used for different messages.")
+            .replace("{msgids}", &msgid_count.to_string())
+            .replace("{msgstrs}", &msgstr_count.to_string())
+    )?;
+
+    Ok(())
+}
+
+/// Reports variants of the group, most frequent first, with counts and locations,
+/// at the first location of the group.
+fn report_group(
+    ctx: &mut IoContext,
+    reporter: &mut Reporter,
+    mut warning: Diagnostic,
+    variants: &BTreeMap<Vec<String>, Vec<Location>>,
+) -> Result<()> {
+    let mut variants: Vec<_> = variants.iter().collect();
+    variants.sort_by_key(|(_, locations)| std::cmp::Reverse(locations.len()));
+
+    for (text, locations) in &variants {
+        let locations: Vec<&str> = locations.iter().map(|l| l.text.as_str()).collect();
+        warning = warning.with_detail(format!(
+            "  {} ({}): {}",
+            quote(text),
+            locations.len(),
+            locations.join(", ")
+        ));
+    }
+
+    let first = variants
+        .iter()
+        .flat_map(|(_, locations)| locations.iter())
+        .min_by_key(|l| (l.index, l.line))
+        .expect("Group has at least one location");
+    reporter.report_group(ctx, first.file, first.line, &[warning])
+}
+
+/// Formats list of strings as quoted strings separated by `|`.
+fn quote(strings: &[String]) -> String {
+    strings
+        .iter()
+        .map(|s| format!("\"{}\"", escape_comment(s)))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Returns location of the message: file name, context, and the first source reference.
+fn location(file: &str, message: &PoMessage) -> String {
+    let mut location = file.to_string();
+    if let Some(msgctxt) = &message.msgctxt {
+        location.push_str(&format!(" [msgctxt \"{}\"]", escape_comment(msgctxt)));
+    }
+    if let Some(reference) = message
+        .comments
+        .iter()
+        .filter_map(|c| c.strip_prefix("#:"))
+        .flat_map(|refs| refs.split_whitespace())
+        .next()
+    {
+        location.push_str(&format!(" ({reference})"));
+    }
+    location
+}
+
+fn help_check_consistency(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-consistency FILE...
+
+Check consistency of translations across one or more PO files:
+
+  * same msgid translated differently in different contexts or files;
+  * different msgids with the same translation.
+
+Results are grouped, with number of occurrences and locations of each variant.
+Fuzzy and untranslated messages are ignored.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::OutputFormat;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_check_consistency_positive() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f1 = NamedTempFile::new()?;
+        fs::write(
+            f1.path(),
+            r#"
+#: src/menu.c:10
+msgctxt "menu"
+msgid "Open"
+msgstr "Відкрити"
+
+msgid "Close"
+msgstr "Закрити"
+
+msgid "Save"
+msgstr "Зберегти"
+"#,
+        )?;
+
+        let f2 = NamedTempFile::new()?;
+        fs::write(
+            f2.path(),
+            r#"
+msgid "Open"
+msgstr "Відкрий"
+
+msgid "Open"
+msgstr "Відкрий"
+
+msgid "Shut"
+msgstr "Закрити"
+
+#, fuzzy
+msgid "Save"
+msgstr "Запис"
+"#,
+        )?;
+
+        let f1_name = f1.path().to_str().unwrap();
+        let f2_name = f2.path().to_str().unwrap();
+        command_check_consistency(
+            &parser,
+            &GlobalOptions::default(),
+            &[f1_name, f2_name],
+            &mut ctx,
+        )?;
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            format!(
+                r#"# Warning: Message "Open" has 2 different translations:
+#   "Відкрий" (2): {f2_name}, {f2_name}
+#   "Відкрити" (1): {f1_name} [msgctxt "menu"] (src/menu.c:10)
+
+# Warning: Translation "Закрити" is used for 2 different messages:
+#   "Close" (1): {f1_name}
+#   "Shut" (1): {f2_name}
+
+"#
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_consistency_json() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+        let options = GlobalOptions {
+            output_format: OutputFormat::Json,
+            ..Default::default()
+        };
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Open\"\nmsgstr \"Відкрити\"\n\nmsgctxt \"menu\"\nmsgid \"Open\"\nmsgstr \"Відкрий\"\n",
+        )?;
+        let file = f.path().to_str().unwrap();
+        command_check_consistency(&parser, &options, &[file], &mut ctx)?;
+
+        let result: serde_json::Value = serde_json::from_slice(&out)?;
+        assert_eq!(result[0]["file"], file);
+        assert_eq!(result[0]["line"], 1);
+        assert_eq!(result[0]["rule"], "check-consistency");
+        assert_eq!(result[0]["severity"], "warning");
+        assert_eq!(
+            result[0]["message"],
+            "Message \"Open\" has 2 different translations:"
+        );
+        assert_eq!(result[0]["details"].as_array().unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result = command_check_consistency(&parser, &GlobalOptions::default(), &[], &mut ctx);
+        assert!(result.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+//! Command to check format strings in translations.
+//!
+//! Unlike `check-symbols`, placeholders are parsed according to the format of the
+//! message, so reordered arguments and changed punctuation are handled correctly.
+
+use crate::diagnostics::{Reporter, Severity};
+use crate::format_string::check_format;
+use crate::parser::Parser;
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use std::io::Write;
+
+/// Implementation of the `check-format` command.
+pub fn command_check_format(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let files = match cmdline {
+        ["-h", ..] | ["--help", ..] => {
+            help(ctx.out)?;
+            return Ok(());
+        }
+        ["--", files @ ..] => files,
+        [arg, ..] if arg.starts_with('-') => bail!(
+            "{}",
+            tr!("Unknown option: \"{}\". Use --help for list of options.").replace("{}", arg)
+        ),
+        files => files,
+    };
+
+    if files.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let mut reporter = Reporter::new(options.output_format, "check-format");
+    let mut violations = 0;
+    for file in files {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            let problems = check_format(message);
+            if problems.is_empty() {
+                continue;
+            }
+            if problems.iter().any(|p| p.severity == Severity::Error) {
+                violations += 1;
+            }
+            reporter.report(ctx, file, *line, message, &problems)?;
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages with invalid format strings.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-format [--] FILE...
+
+Check that placeholders in msgstr match placeholders in msgid, like `msgfmt -c` does:
+the same arguments must be used, with compatible types. Arguments may be reordered
+using positional placeholders, e.g. "%2$d" or "{1}".
+
+Supported formats:
+
+  c-format                C printf: %s, %5.2f, %lu, %1$s.
+  python-format           Python: %s, %(name)s.
+  qt-format               Qt: %1, %L2.
+  python-brace-format,
+  rust-format,
+  csharp-format           Brace format: {}, {0}, {name}, {name:>10}.
+
+The format is selected by the `#, xxx-format` flag of the message, or detected
+automatically. The `#, no-xxx-format` flag disables the check for the message.
+
+Like `msgfmt -c`, only messages with the format flag have errors. Problems in
+messages with detected format are warnings, because text like "{curly} braces"
+might not be a format string.
+
+Messages with problems are printed. Exit status is non-zero when errors are found.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::OutputFormat;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_check_format() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+#, c-format
+msgid "%s: %d files"
+msgstr "%2$d файлів у %1$s!"
+
+#, c-format
+msgid "%s: %d files"
+msgstr "%s: %s файлів"
+
+msgid "100% done"
+msgstr "Готово на 100 %"
+
+msgid "%d files"
+msgstr "файлів"
+"#,
+        )?;
+
+        let result = command_check_format(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Error: Format specifications for argument 2 have different types: "%d" in msgid, but "%s" in msgstr.
+#, c-format
+msgid "%s: %d files"
+msgstr "%s: %s файлів"
+
+# Warning: Format specification "%d" for argument 1 from msgid is missing in msgstr.
+msgid "%d files"
+msgstr "файлів"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detected_format_warnings() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Use {curly} braces to group\"\nmsgstr \"Використовуйте {фігурні} дужки\"\n",
+        )?;
+
+        // Warnings don't fail the check
+        command_check_format(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        )?;
+        assert!(String::from_utf8(out)?.starts_with("# Warning: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_format_json() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+        let options = GlobalOptions {
+            output_format: OutputFormat::Json,
+            ..Default::default()
+        };
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Done\"\nmsgstr \"Готово\"\n\n#, c-format\nmsgid \"%d files\"\nmsgstr \"файлів\"\n",
+        )?;
+
+        let file = f.path().to_str().unwrap();
+        assert!(command_check_format(&parser, &options, &[file], &mut ctx).is_err());
+
+        let result: serde_json::Value = serde_json::from_slice(&out)?;
+        assert_eq!(
+            result,
+            serde_json::json!([{
+                "file": file,
+                "line": 4,
+                "rule": "check-format",
+                "severity": "error",
+                "message_key": { "msgctxt": null, "msgid": "%d files" },
+                "message": "Format specification \"%d\" for argument 1 from msgid is missing in msgstr.",
+                "details": [],
+            }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        assert!(command_check_format(&parser, &GlobalOptions::default(), &[], &mut ctx).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Command to check length constraints of translations.
+
+use crate::diagnostics::Reporter;
+use crate::length::check_length;
+use crate::parser::Parser;
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use std::io::Write;
+
+/// Implementation of the `check-length` command.
+pub fn command_check_length(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let files = match cmdline {
+        ["-h", ..] | ["--help", ..] => {
+            help(ctx.out)?;
+            return Ok(());
+        }
+        ["--", files @ ..] => files,
+        [arg, ..] if arg.starts_with('-') => bail!(
+            "{}",
+            tr!("Unknown option: \"{}\". Use --help for list of options.").replace("{}", arg)
+        ),
+        files => files,
+    };
+
+    if files.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let mut reporter = Reporter::new(options.output_format, "check-length");
+    let mut violations = 0;
+    for file in files {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            let errors = check_length(message, options.max_length_ratio);
+            if !errors.is_empty() {
+                violations += 1;
+                reporter.report(ctx, file, *line, message, &errors)?;
+            }
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages with too long or too short translations.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-length [--] FILE...
+
+Check length of translations, e.g. for buttons and labels on small displays.
+Length is measured in characters.
+
+Limits are set per message:
+
+  #, range: MIN..MAX      Minimum and maximum length of msgstr. Ignored in plural
+                          messages, where xgettext uses it for the plural argument.
+  #. max-length: N        Maximum length of msgstr (extracted comment).
+
+With the global `--max-ratio RATIO` option, translations longer than RATIO times
+the length of msgid are reported too. Messages with msgid shorter than 10 characters
+are not checked by ratio.
+
+Messages with errors are printed. Exit status is non-zero when errors are found.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_check_length() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+        let options = GlobalOptions {
+            max_length_ratio: Some(2.0),
+            ..Default::default()
+        };
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+#. max-length: 10
+msgid "Cancel"
+msgstr "Скасувати"
+
+#. max-length: 10
+msgid "Save as"
+msgstr "Зберегти як"
+
+msgid "Print the page"
+msgstr "Надрукувати поточну сторінку документа"
+"#,
+        )?;
+
+        let result =
+            command_check_length(&parser, &options, &[f.path().to_str().unwrap()], &mut ctx);
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Error: msgstr is too long: 11 characters, but maximum length is 10. Actual msgstr: "Зберегти як"
+#. max-length: 10
+msgid "Save as"
+msgstr "Зберегти як"
+
+# Error: msgstr is 2.7 times longer than msgid, but maximum ratio is 2. Actual msgstr: "Надрукувати поточну сторінку документа"
+msgid "Print the page"
+msgstr "Надрукувати поточну сторінку документа"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        assert!(command_check_length(&parser, &GlobalOptions::default(), &[], &mut ctx).is_err());
+        Ok(())
+    }
+}
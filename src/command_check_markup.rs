@@ -0,0 +1,544 @@
+//! Command to check markup consistency between msgid and msgstr.
+//!
+//! Tags of HTML, XML, and Pango markup are parsed in both strings, so the check can
+//! tell a broken or missing tag from a tag moved to another place in the sentence.
+
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::parser::{Parser, PoMessage};
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// HTML elements without closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "br", "hr", "img", "input", "meta", "link", "area", "base", "col", "wbr",
+];
+
+/// Attributes with human-readable text, which are translated.
+const TRANSLATABLE_ATTRIBUTES: &[&str] = &["alt", "title", "aria-label", "placeholder", "label"];
+
+/// Kind of markup token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Opening or self-closing tag with its attributes.
+    Open {
+        name: String,
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+        text: String,
+    },
+    /// Closing tag.
+    Close { name: String, text: String },
+    /// Character or entity reference: `&amp;`, `&#169;`.
+    Entity(String),
+    /// Ampersand, which is not a part of entity.
+    Ampersand,
+}
+
+/// Splits string into markup tokens. Plain text is skipped.
+///
+/// A `<` which is not followed by a letter or `/` is treated as text, e.g. in "a < b".
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '<' if chars
+                .get(i + 1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '/') =>
+            {
+                let end = chars[i..].iter().position(|c| *c == '>').ok_or_else(|| {
+                    tr!("unterminated tag \"{tag}\"")
+                        .replace("{tag}", &chars[i..].iter().collect::<String>())
+                })?;
+                let tag: String = chars[i..=i + end].iter().collect();
+                tokens.push(parse_tag(&tag)?);
+                i += end + 1;
+            }
+            '&' => {
+                let length = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '#')
+                    .count();
+                if length > 0 && chars.get(i + 1 + length) == Some(&';') {
+                    tokens.push(Token::Entity(chars[i..i + length + 2].iter().collect()));
+                    i += length + 2;
+                } else {
+                    tokens.push(Token::Ampersand);
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a single tag, including angle brackets.
+fn parse_tag(tag: &str) -> Result<Token, String> {
+    let inner = &tag[1..tag.len() - 1];
+
+    if let Some(name) = inner.strip_prefix('/') {
+        return Ok(Token::Close {
+            name: name.trim().to_lowercase(),
+            text: tag.to_string(),
+        });
+    }
+
+    let (inner, self_closing) = match inner.strip_suffix('/') {
+        Some(inner) => (inner, true),
+        None => (inner, false),
+    };
+
+    let name_length = inner
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(inner.len());
+    let name = inner[..name_length].to_lowercase();
+
+    // Attributes: name="value", name='value', name=value, or just name
+    let mut attributes = Vec::new();
+    let mut rest = inner[name_length..].trim_start();
+    while !rest.is_empty() {
+        let key_length = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let key = rest[..key_length].to_lowercase();
+        rest = rest[key_length..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).ok_or_else(|| {
+                        tr!("unterminated attribute value in tag \"{tag}\"").replace("{tag}", tag)
+                    })?;
+                    value = after[1..end + 1].to_string();
+                    rest = &after[end + 2..];
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace())
+                        .unwrap_or(after.len());
+                    value = after[..end].to_string();
+                    rest = &after[end..];
+                }
+            }
+        }
+        attributes.push((key, value));
+        rest = rest.trim_start();
+    }
+
+    Ok(Token::Open {
+        name,
+        attributes,
+        self_closing,
+        text: tag.to_string(),
+    })
+}
+
+/// Checks that tags are balanced and properly nested. Returns errors.
+fn check_nesting(tokens: &[Token], field: &str) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    let mut stack: Vec<&Token> = Vec::new();
+
+    for token in tokens {
+        match token {
+            Token::Open {
+                name, self_closing, ..
+            } if !self_closing && !VOID_ELEMENTS.contains(&name.as_str()) => stack.push(token),
+            // Closing tags of void elements, e.g. "</br>", are ignored by browsers
+            Token::Close { name, .. } if VOID_ELEMENTS.contains(&name.as_str()) => {}
+            Token::Close { name, text } => match stack.pop() {
+                Some(Token::Open {
+                    name: open_name,
+                    text: open_text,
+                    ..
+                }) if open_name != name => {
+                    errors.push(Diagnostic::error(
+                        tr!("Markup in {field} is not properly nested: \"{close}\" closes \"{open}\".")
+                            .replace("{field}", field)
+                            .replace("{close}", text)
+                            .replace("{open}", open_text),
+                    ));
+                }
+                Some(_) => {}
+                None => errors.push(Diagnostic::error(
+                    tr!("Closing tag \"{close}\" in {field} has no opening tag.")
+                        .replace("{field}", field)
+                        .replace("{close}", text),
+                )),
+            },
+            _ => {}
+        }
+    }
+
+    for token in stack {
+        if let Token::Open { text, .. } = token {
+            errors.push(Diagnostic::error(
+                tr!("Tag \"{open}\" in {field} is not closed.")
+                    .replace("{field}", field)
+                    .replace("{open}", text),
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Compares markup of msgid and msgstr, appending errors.
+fn check_strings(msgid: &str, msgstr: &str, errors: &mut Vec<Diagnostic>) {
+    let src = match tokenize(msgid) {
+        Ok(tokens) => tokens,
+        // msgid is not a markup, so nothing to compare with
+        Err(_) => return,
+    };
+    if !src.iter().any(|t| {
+        matches!(
+            t,
+            Token::Open { .. } | Token::Close { .. } | Token::Entity(_)
+        )
+    }) {
+        return;
+    }
+
+    let dst = match tokenize(msgstr) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            errors.push(Diagnostic::error(
+                tr!("Invalid markup in msgstr: {error}.").replace("{error}", &e),
+            ));
+            return;
+        }
+    };
+
+    // Broken markup in msgid is a problem of the source, not of the translation
+    if check_nesting(&src, "msgid").is_empty() {
+        errors.extend(check_nesting(&dst, "msgstr"));
+    }
+
+    // Tags are paired by name, in order of appearance, so they can be moved freely
+    let src_tags = tags_by_name(&src);
+    let dst_tags = tags_by_name(&dst);
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for (key, src_token) in tags(&src) {
+        let n = seen.entry(key.clone()).or_default();
+        match dst_tags.get(&key).and_then(|list| list.get(*n)) {
+            None => errors.push(Diagnostic::error(
+                tr!("Tag \"{tag}\" from msgid is missing in msgstr.")
+                    .replace("{tag}", tag_text(src_token)),
+            )),
+            Some(dst_token) => check_attributes(src_token, dst_token, errors),
+        }
+        *n += 1;
+    }
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for (key, dst_token) in tags(&dst) {
+        let n = seen.entry(key.clone()).or_default();
+        if src_tags.get(&key).map_or(0, Vec::len) <= *n {
+            errors.push(Diagnostic::error(
+                tr!("Tag \"{tag}\" in msgstr is not in msgid.")
+                    .replace("{tag}", tag_text(dst_token)),
+            ));
+        }
+        *n += 1;
+    }
+
+    // Entities
+    let mut src_entities: BTreeMap<&str, isize> = BTreeMap::new();
+    for token in &src {
+        if let Token::Entity(entity) = token {
+            *src_entities.entry(entity).or_default() += 1;
+        }
+    }
+    for token in &dst {
+        if let Token::Entity(entity) = token {
+            *src_entities.entry(entity).or_default() -= 1;
+        }
+    }
+    for (entity, count) in src_entities {
+        if count > 0 {
+            errors.push(Diagnostic::warning(
+                tr!("Entity \"{entity}\" from msgid is missing in msgstr.")
+                    .replace("{entity}", entity),
+            ));
+        }
+    }
+
+    if dst.contains(&Token::Ampersand) && !src.contains(&Token::Ampersand) {
+        errors.push(Diagnostic::error(tr!(
+            "msgstr contains unescaped \"&\". Use \"&amp;\" instead."
+        )));
+    }
+}
+
+/// Returns opening and closing tags with their keys: name of the tag, or "/name" for closing tags.
+fn tags(tokens: &[Token]) -> impl Iterator<Item = (String, &Token)> {
+    tokens.iter().filter_map(|token| match token {
+        Token::Open { name, .. } => Some((name.clone(), token)),
+        Token::Close { name, .. } => Some((format!("/{name}"), token)),
+        _ => None,
+    })
+}
+
+/// Groups tags by their keys.
+fn tags_by_name(tokens: &[Token]) -> BTreeMap<String, Vec<&Token>> {
+    let mut map: BTreeMap<String, Vec<&Token>> = BTreeMap::new();
+    for (key, token) in tags(tokens) {
+        map.entry(key).or_default().push(token);
+    }
+    map
+}
+
+fn tag_text(token: &Token) -> &str {
+    match token {
+        Token::Open { text, .. } | Token::Close { text, .. } => text,
+        _ => "",
+    }
+}
+
+/// Checks that non-translatable attributes of the tag are not changed.
+fn check_attributes(src: &Token, dst: &Token, errors: &mut Vec<Diagnostic>) {
+    let (
+        Token::Open {
+            name,
+            attributes: src_attributes,
+            ..
+        },
+        Token::Open {
+            attributes: dst_attributes,
+            ..
+        },
+    ) = (src, dst)
+    else {
+        return;
+    };
+
+    for (key, value) in src_attributes {
+        if TRANSLATABLE_ATTRIBUTES.contains(&key.as_str()) {
+            continue;
+        }
+        match dst_attributes.iter().find(|(k, _)| k == key) {
+            Some((_, other)) if other == value => {}
+            Some((_, other)) => errors.push(Diagnostic::error(
+                tr!("Attribute \"{attribute}\" of tag \"<{tag}>\" is changed: \"{msgid_value}\" in msgid, but \"{msgstr_value}\" in msgstr.")
+                    .replace("{attribute}", key)
+                    .replace("{tag}", name)
+                    .replace("{msgid_value}", value)
+                    .replace("{msgstr_value}", other),
+            )),
+            None => errors.push(Diagnostic::error(
+                tr!("Attribute \"{attribute}\" of tag \"<{tag}>\" is missing in msgstr.")
+                    .replace("{attribute}", key)
+                    .replace("{tag}", name),
+            )),
+        }
+    }
+}
+
+/// Checks markup of a single message.
+///
+/// Returns problems when tags, attributes, or entities of msgstr don't match msgid.
+pub fn check_markup(message: &PoMessage) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return errors;
+    }
+
+    for (n, msgstr) in message.msgstr.iter().enumerate() {
+        let msgid = match &message.msgid_plural {
+            Some(msgid_plural) if n > 0 => msgid_plural,
+            _ => &message.msgid,
+        };
+        check_strings(msgid, msgstr, &mut errors);
+    }
+    errors
+}
+
+/// Implementation of the `check-markup` command.
+pub fn command_check_markup(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let files = match cmdline {
+        ["-h", ..] | ["--help", ..] => {
+            help(ctx.out)?;
+            return Ok(());
+        }
+        ["--", files @ ..] => files,
+        [arg, ..] if arg.starts_with('-') => bail!(
+            "{}",
+            tr!("Unknown option: \"{}\". Use --help for list of options.").replace("{}", arg)
+        ),
+        files => files,
+    };
+
+    if files.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let mut reporter = Reporter::new(options.output_format, "check-markup");
+    let mut violations = 0;
+    for file in files {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            let errors = check_markup(message);
+            if !errors.is_empty() {
+                violations += 1;
+                reporter.report(ctx, file, *line, message, &errors)?;
+            }
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages with inconsistent markup.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-markup [--] FILE...
+
+Check that HTML, XML, or Pango markup of msgstr matches markup of msgid:
+
+  * the same tags are present in msgstr, in any order;
+  * tags in msgstr are balanced and properly nested;
+  * attribute values, e.g. URLs in `<a href="...">`, are unchanged. Human-readable
+    attributes (alt, title, aria-label, placeholder, label) may be translated;
+  * entities, e.g. `&amp;`, are kept, and no unescaped `&` is added.
+
+Only messages with markup in msgid are checked. A `<` which is not followed by a letter
+or `/` is treated as text.
+
+Messages with errors are printed. Exit status is non-zero when errors are found.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    fn check(msgid: &str, msgstr: &str) -> Option<String> {
+        let message = PoMessage {
+            msgid: msgid.to_string(),
+            msgstr: vec![msgstr.to_string()],
+            ..Default::default()
+        };
+        let errors = check_markup(&message);
+        (!errors.is_empty()).then(|| to_comments(&errors))
+    }
+
+    #[test]
+    fn test_check_markup_valid() {
+        assert_eq!(check("<b>Bold</b> text", "Текст <b>жирний</b>"), None);
+        assert_eq!(
+            check(
+                "See <a href=\"https://example.com\" title=\"Site\">site</a>.<br>",
+                "Див. <a title=\"Сайт\" href=\"https://example.com\">сайт</a>.<br>"
+            ),
+            None
+        );
+        assert_eq!(check("Copy &amp; paste", "Копіювати &amp; вставити"), None);
+        assert_eq!(check("Line<br>next</br>", "Рядок<br>наступний</br>"), None);
+        assert_eq!(check("a < b", "а < б"), None);
+        assert_eq!(check("Plain & simple", "Просто & ясно"), None);
+    }
+
+    #[test]
+    fn test_check_markup_errors() {
+        assert_eq!(
+            check("<b>Bold</b>", "<i>Жирний</i>").unwrap(),
+            "# Error: Tag \"<b>\" from msgid is missing in msgstr.\n\
+             # Error: Tag \"</b>\" from msgid is missing in msgstr.\n\
+             # Error: Tag \"<i>\" in msgstr is not in msgid.\n\
+             # Error: Tag \"</i>\" in msgstr is not in msgid.\n"
+        );
+
+        let errors = check("<b><i>Text</i></b>", "<b><i>Текст</b></i>").unwrap();
+        assert!(errors.contains("is not properly nested: \"</b>\" closes \"<i>\""));
+
+        let errors = check("<b>Text</b>", "<b>Текст").unwrap();
+        assert!(errors.contains("Tag \"<b>\" in msgstr is not closed"));
+
+        let errors = check(
+            "<a href=\"https://example.com\">link</a>",
+            "<a href=\"https://example.org\">посилання</a>",
+        )
+        .unwrap();
+        assert_eq!(
+            errors,
+            "# Error: Attribute \"href\" of tag \"<a>\" is changed: \"https://example.com\" in msgid, but \"https://example.org\" in msgstr.\n"
+        );
+
+        let errors = check("Copy &amp; paste", "Копіювати & вставити").unwrap();
+        assert!(errors.contains("Entity \"&amp;\" from msgid is missing"));
+        assert!(errors.contains("unescaped \"&\""));
+
+        let errors = check("<b>Text</b>", "<b>Текст</b").unwrap();
+        assert!(errors.contains("Invalid markup in msgstr: unterminated tag"));
+    }
+
+    #[test]
+    fn test_check_markup_command() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+msgid "<b>Warning:</b> disk is full"
+msgstr "<b>Увага:</b> диск заповнено"
+
+msgid "<span weight=\"bold\">Error</span>"
+msgstr "<span weight=\"normal\">Помилка</span>"
+"#,
+        )?;
+
+        let result = command_check_markup(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Error: Attribute "weight" of tag "<span>" is changed: "bold" in msgid, but "normal" in msgstr.
+msgid "<span weight=\"bold\">Error</span>"
+msgstr "<span weight=\"normal\">Помилка</span>"
+
+"#
+        );
+        Ok(())
+    }
+}
@@ -5,8 +5,9 @@
 
 use std::io::Write;
 
-use crate::parser::{Parser, PoMessage};
-use crate::util::IoContext;
+use crate::diagnostics::{Diagnostic, Reporter, to_comments};
+use crate::parser::{Parser, PoMessage, escape_comment};
+use crate::util::{GlobalOptions, IoContext};
 use anyhow::{Result, bail};
 
 fn strip_non_symbols(s: &str) -> String {
@@ -15,7 +16,8 @@ fn strip_non_symbols(s: &str) -> String {
         .collect::<String>()
 }
 
-fn get_whitespace(s: &str) -> (String, String) {
+/// Returns leading and trailing whitespace of the string.
+pub fn get_whitespace(s: &str) -> (String, String) {
     let leading = s.chars().take_while(|c| c.is_whitespace()).collect();
     let trailing = s
         .chars()
@@ -28,19 +30,15 @@ fn get_whitespace(s: &str) -> (String, String) {
     (leading, trailing)
 }
 
-fn check_strings(src: &str, dst: &str) -> Option<String> {
-    let mut warnings = String::new();
-
+fn check_strings(src: &str, dst: &str, warnings: &mut Vec<Diagnostic>) {
     // Symbols check
     let src_syms = strip_non_symbols(src);
     let dst_syms = strip_non_symbols(dst);
     if src_syms != dst_syms {
-        warnings.push_str(
-            &tr!(
-                "# Warning: Incorrect symbols:\n# msgid:  {msgid_syms}\n# msgstr: {msgstr_syms}\n"
-            )
-            .replace("{msgid_syms}", &src_syms)
-            .replace("{msgstr_syms}", &dst_syms),
+        warnings.push(
+            Diagnostic::warning(tr!("Incorrect symbols:"))
+                .with_detail(format!("msgid:  {src_syms}"))
+                .with_detail(format!("msgstr: {dst_syms}")),
         );
     }
 
@@ -48,62 +46,73 @@ fn check_strings(src: &str, dst: &str) -> Option<String> {
     let src_ws = get_whitespace(src);
     let dst_ws = get_whitespace(dst);
     if src_ws != dst_ws {
-        warnings.push_str(
-            &tr!("# Warning: Whitespace mismatch:\n# msgid:  \"{msgid_ws}\"\n# msgstr: \"{msgstr_ws}\"\n")
-                .replace("{msgid_ws}", &format!("{}{}", src_ws.0, src_ws.1))
-                .replace("{msgstr_ws}", &format!("{}{}", dst_ws.0, dst_ws.1)),
+        warnings.push(
+            Diagnostic::warning(tr!("Whitespace mismatch:"))
+                .with_detail(format!(
+                    "msgid:  \"{}\"",
+                    escape_comment(&(src_ws.0 + &src_ws.1))
+                ))
+                .with_detail(format!(
+                    "msgstr: \"{}\"",
+                    escape_comment(&(dst_ws.0 + &dst_ws.1))
+                )),
         );
     }
-
-    if warnings.is_empty() {
-        None
-    } else {
-        Some(warnings)
-    }
 }
 
 /// Checks a single message for symbol consistency.
 ///
-/// Returns a warning message if symbols in `msgid` don't match those in `msgstr`.
-pub fn check_symbols(message: &PoMessage) -> Option<String> {
+/// Returns warnings if symbols in `msgid` don't match those in `msgstr`.
+pub fn check_symbols(message: &PoMessage) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
     if message.is_header() {
-        return None;
+        return warnings;
     }
 
     if message.is_plural() {
-        let mut all_warnings = String::new();
         for msgstr in &message.msgstr {
-            if let Some(w) = check_strings(&message.msgid, msgstr) {
-                all_warnings.push_str(&w);
-            }
-        }
-        if all_warnings.is_empty() {
-            None
-        } else {
-            Some(all_warnings)
+            check_strings(&message.msgid, msgstr, &mut warnings);
         }
     } else {
-        check_strings(&message.msgid, message.msgstr_first())
+        check_strings(&message.msgid, message.msgstr_first(), &mut warnings);
     }
+    warnings
 }
 
 /// Implementation of the `check-symbols` command.
-pub fn command_check_symbols(parser: &Parser, cmdline: &[&str], ctx: &mut IoContext) -> Result<()> {
+pub fn command_check_symbols(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
     match cmdline {
         ["-h", ..] | ["--help", ..] => help(ctx.out),
 
         files if !files.is_empty() => {
+            let mut reporter = Reporter::new(options.output_format, "check-symbols");
             for file in files {
-                let messages = parser.parse_messages_from_file(file)?;
+                let messages = parser.parse_numbered_messages_from_file(file)?;
 
-                for message in messages.iter() {
+                for (line, message) in messages.iter() {
                     if message.is_header() {
-                        writeln!(ctx.out, "{message}")?;
-                    } else if let Some(errors) = check_symbols(message) {
-                        writeln!(ctx.out, "{errors}\n#, fuzzy\n{message}")?;
+                        if reporter.is_po() {
+                            writeln!(ctx.out, "{message}")?;
+                        }
+                    } else {
+                        let warnings = check_symbols(message);
+                        if warnings.is_empty() {
+                            continue;
+                        }
+                        if reporter.is_po() {
+                            writeln!(ctx.out, "{}\n#, fuzzy\n{message}", to_comments(&warnings))?;
+                        } else {
+                            reporter.report(ctx, file, *line, message, &warnings)?;
+                        }
                     }
                 }
             }
+            reporter.finish(ctx)?;
         }
 
         _ => bail!(tr!("At least one file is expected.")),
@@ -143,7 +152,12 @@ mod tests {
         let f = NamedTempFile::new()?;
         fs::write(f.path(), "msgid \"hello %d\"\nmsgstr \"привіт %d\"\n")?;
 
-        command_check_symbols(&parser, &[f.path().to_str().unwrap()], &mut ctx)?;
+        command_check_symbols(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        )?;
 
         let result = String::from_utf8(out)?;
         // If no symbols are missing, it should NOT print anything for the message (or only header if present)
@@ -164,7 +178,12 @@ mod tests {
         let f = NamedTempFile::new()?;
         fs::write(f.path(), "msgid \"hello %d\"\nmsgstr \"привіт\"\n")?;
 
-        command_check_symbols(&parser, &[f.path().to_str().unwrap()], &mut ctx)?;
+        command_check_symbols(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        )?;
 
         let result = String::from_utf8(out)?;
         assert!(result.contains("Warning"));
@@ -183,7 +202,7 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        command_check_symbols(&parser, &["--help"], &mut ctx)?;
+        command_check_symbols(&parser, &GlobalOptions::default(), &["--help"], &mut ctx)?;
 
         let result = String::from_utf8(out)?;
         assert!(result.contains("Usage:"));
@@ -200,7 +219,7 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let result = command_check_symbols(&parser, &[], &mut ctx);
+        let result = command_check_symbols(&parser, &GlobalOptions::default(), &[], &mut ctx);
         assert!(result.is_err());
         Ok(())
     }
@@ -0,0 +1,346 @@
+//! Command to check that translations follow the glossary.
+//!
+//! For each message whose msgid contains a dictionary term, msgstr must contain
+//! the expected translation of the term or one of its allowed variants.
+
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::dictionary::Dictionary;
+use crate::parser::{Parser, PoMessage, escape_comment};
+use crate::stemmer::stemmer_by_name;
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+
+/// Implementation of the `check-terms` command.
+pub fn command_check_terms(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut dictionary_files: Vec<&str> = Vec::new();
+    let mut stemmer_name = "english";
+
+    // Parse "check-terms" command options
+    let mut cmdline = cmdline;
+    loop {
+        match cmdline[..] {
+            ["-d", dict_file, ref tail @ ..] | ["--dictionary", dict_file, ref tail @ ..] => {
+                dictionary_files.push(dict_file);
+                cmdline = tail;
+            }
+
+            ["--stemmer", name, ref tail @ ..] => {
+                stemmer_name = name;
+                cmdline = tail;
+            }
+            ["-h", ..] | ["--help", ..] => {
+                help_check_terms(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if dictionary_files.is_empty() {
+        bail!(tr!(
+            "Expected at least one dictionary file. Use --dictionary FILE option."
+        ));
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let stemmer = stemmer_by_name(stemmer_name)?;
+    let mut dictionaries = Vec::new();
+    for dict_file in dictionary_files {
+        let dict = Dictionary::from_file(dict_file)
+            .with_context(|| {
+                tr!("Cannot open dictionary file \"{file}\".").replace("{file}", dict_file)
+            })?
+            .with_stemmer(stemmer.clone());
+        dictionaries.push(dict);
+    }
+
+    let mut reporter = Reporter::new(options.output_format, "check-terms");
+    let mut violations = 0;
+    for file in cmdline {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        violations += check_terms_and_report(ctx, &mut reporter, file, &dictionaries, &messages)?;
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages which violate the glossary.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that translations of terms from dictionaries are used in msgstr.
+///
+/// Returns warning for each term without expected translation.
+pub fn check_terms(dictionaries: &[Dictionary], message: &PoMessage) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return errors;
+    }
+
+    for dict in dictionaries {
+        for entry in dict.find_matches(&message.msgid) {
+            for msgstr in &message.msgstr {
+                for forbidden in entry.forbidden_in(msgstr) {
+                    errors.push(Diagnostic::warning(
+                        tr!("Forbidden translation \"{forbidden}\" of term \"{term}\". Actual msgstr: \"{actual}\"")
+                            .replace("{forbidden}", forbidden)
+                            .replace("{term}", &entry.key)
+                            .replace("{actual}", &escape_comment(msgstr)),
+                    ));
+                }
+
+                if entry.translations().next().is_some() && !entry.is_translated_in(msgstr) {
+                    errors.push(Diagnostic::warning(
+                        tr!("Term \"{term}\" must be translated as \"{expected}\". Actual msgstr: \"{actual}\"")
+                            .replace("{term}", &entry.key)
+                            .replace("{expected}", &entry.translations().collect::<Vec<_>>().join("\" | \""))
+                            .replace("{actual}", &escape_comment(msgstr)),
+                    ));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Reports messages which violate the glossary. Returns number of such messages.
+fn check_terms_and_report(
+    ctx: &mut IoContext,
+    reporter: &mut Reporter,
+    file: &str,
+    dictionaries: &[Dictionary],
+    messages: &[(usize, PoMessage)],
+) -> Result<usize> {
+    let mut violations = 0;
+    for (line, message) in messages {
+        let errors = check_terms(dictionaries, message);
+        if !errors.is_empty() {
+            violations += 1;
+            reporter.report(ctx, file, *line, message, &errors)?;
+        }
+    }
+    Ok(violations)
+}
+
+fn help_check_terms(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] check-terms --dictionary DICT_FILE [OPTIONS] [--] FILE...
+
+Check that translations follow the glossary: for each translated message whose msgid
+contains a dictionary term, msgstr must contain the translation of the term.
+Allowed variants of translation, e.g. inflected forms, are separated by "|" in the
+dictionary: "bug<TAB>помилка|помилки|помилок". Terms marked as "do not translate"
+must be kept as is, and forbidden translations must not be used.
+
+Messages which violate the glossary are printed with the term, the expected translation,
+and the actual msgstr. Exit status is non-zero when violations are found.
+
+OPTIONS:
+
+  -d | --dictionary FILE  Dictionary file (TSV, CSV, or TBX) to use. Can be used multiple times.
+
+  --stemmer NAME        Stemmer to match inflected forms of dictionary terms, e.g. "entries" for "entry".
+                        Values: english, none. Default value: english.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_check_terms() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dict = NamedTempFile::new()?;
+        fs::write(dict.path(), "bug\tпомилка|помилки|помилок\nfile\tфайл\n")?;
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+msgid "Fixed 5 bugs"
+msgstr "Виправлено 5 помилок"
+
+msgid "Report a bug"
+msgstr "Повідомити про ваду"
+
+msgid "Open file"
+msgstr ""
+"#,
+        )?;
+
+        let result = command_check_terms(
+            &parser,
+            &GlobalOptions::default(),
+            &[
+                "-d",
+                dict.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Warning: Term "bug" must be translated as "помилка" | "помилки" | "помилок". Actual msgstr: "Повідомити про ваду"
+msgid "Report a bug"
+msgstr "Повідомити про ваду"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_terms_positive() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dict = NamedTempFile::new()?;
+        fs::write(dict.path(), "file\tфайл|файли|файлів\n")?;
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Open file\"\nmsgid_plural \"Open files\"\nmsgstr[0] \"Відкрити файл\"\nmsgstr[1] \"Відкрити файли\"\n",
+        )?;
+
+        command_check_terms(
+            &parser,
+            &GlobalOptions::default(),
+            &[
+                "-d",
+                dict.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        )?;
+
+        assert!(out.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_terms_first_term_is_key() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        // First row is a term, not a header
+        let dict = NamedTempFile::new()?;
+        fs::write(dict.path(), "key\tключ\nfile\tфайл\n")?;
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Open file\"\nmsgstr \"Відкрити документ\"\n",
+        )?;
+
+        let result = command_check_terms(
+            &parser,
+            &GlobalOptions::default(),
+            &[
+                "-d",
+                dict.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        );
+
+        assert!(result.is_err());
+        assert!(String::from_utf8(out)?.contains("msgstr \"Відкрити документ\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_dictionary() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result =
+            command_check_terms(&parser, &GlobalOptions::default(), &["file.po"], &mut ctx);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_terms_forbidden_and_do_not_translate() -> Result<()> {
+        let dict = tempfile::Builder::new().suffix(".csv").tempfile()?;
+        fs::write(
+            dict.path(),
+            "term,translation,dnt,forbidden\nbug,помилка,,баг\nLinux,,yes,Лінукс\n",
+        )?;
+        let dictionaries = vec![Dictionary::from_file(dict.path())?];
+        let parser = Parser::new(None);
+
+        let message =
+            parser.parse_message_from_str("msgid \"Linux bug\"\nmsgstr \"Помилка Linux\"\n")?;
+        assert!(check_terms(&dictionaries, &message).is_empty());
+
+        let message =
+            parser.parse_message_from_str("msgid \"Linux bug\"\nmsgstr \"Баг у Лінукс\"\n")?;
+        let errors = to_comments(&check_terms(&dictionaries, &message));
+        assert!(errors.contains("Forbidden translation \"баг\" of term \"bug\""));
+        assert!(errors.contains("Term \"bug\" must be translated as \"помилка\""));
+        assert!(errors.contains("Forbidden translation \"Лінукс\" of term \"Linux\""));
+        assert!(errors.contains("Term \"Linux\" must be translated as \"Linux\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+//! Command to build a compendium (translation memory) from many PO files.
+//!
+//! Each msgid is kept once. When the same message is translated differently,
+//! the most frequent translation wins, while other variants are listed in comments.
+
+use crate::parser::{Parser, PoMessage, escape_comment};
+use crate::util::IoContext;
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Implementation of the `compendium` command.
+pub fn command_compendium(parser: &Parser, cmdline: &[&str], ctx: &mut IoContext) -> Result<()> {
+    let mut output_file: Option<&str> = None;
+    let mut majority = false;
+
+    // Parse "compendium" command options
+    let mut cmdline = cmdline;
+    loop {
+        match cmdline[..] {
+            ["-o", file, ref tail @ ..] | ["--output", file, ref tail @ ..] => {
+                output_file = Some(file);
+                cmdline = tail;
+            }
+            ["--majority", ref tail @ ..] => {
+                majority = true;
+                cmdline = tail;
+            }
+            ["-h", ..] | ["--help", ..] => {
+                help_compendium(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!(
+            "Expected at least one argument: the name of the file or directory with PO files."
+        ));
+    }
+
+    let mut files = Vec::new();
+    for arg in cmdline {
+        collect_po_files(Path::new(arg), &mut files)?;
+    }
+
+    let mut compendium = Compendium::default();
+    for file in &files {
+        let messages = parser.parse_messages_from_file(file)?;
+        compendium.add_messages(messages, file);
+    }
+
+    writeln!(
+        ctx.err,
+        "{}: {}",
+        tr!("INFO"),
+        tr!("Collected {count} messages from {files} files.")
+            .replace("{count}", &compendium.entries.len().to_string())
+            .replace("{files}", &files.len().to_string())
+    )?;
+
+    match output_file {
+        Some(file) => {
+            let mut out = fs::File::create(file).with_context(|| {
+                tr!("Cannot create output file \"{file}\".").replace("{file}", file)
+            })?;
+            compendium.print(&mut out, majority)?;
+        }
+        None => compendium.print(ctx.out, majority)?,
+    }
+
+    Ok(())
+}
+
+/// Collects PO files from the path: the file itself, or all `*.po` files in the directory, recursively.
+fn collect_po_files(path: &Path, files: &mut Vec<String>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_string_lossy().into_owned());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)
+        .with_context(|| {
+            tr!("Cannot read directory \"{dir}\".").replace("{dir}", &path.to_string_lossy())
+        })?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "po") {
+            collect_po_files(&entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// A variant of translation and the files, where it is used.
+#[derive(Debug, Default)]
+struct Variant {
+    msgstr: Vec<String>,
+    sources: Vec<String>,
+}
+
+/// Collection of translations by message key.
+#[derive(Debug, Default)]
+struct Compendium {
+    entries: BTreeMap<PoMessage, Vec<Variant>>,
+}
+
+impl Compendium {
+    /// Adds translated messages from the file. Header, fuzzy and untranslated messages are dropped.
+    fn add_messages(&mut self, messages: Vec<PoMessage>, source: &str) {
+        for message in messages {
+            if message.is_header() || !message.is_translated() || message.is_fuzzy() {
+                continue;
+            }
+
+            let mut key = message.to_key();
+            key.comments.clear();
+
+            let variants = self.entries.entry(key).or_default();
+            match variants.iter_mut().find(|v| v.msgstr == message.msgstr) {
+                Some(variant) => variant.sources.push(source.to_string()),
+                None => variants.push(Variant {
+                    msgstr: message.msgstr,
+                    sources: vec![source.to_string()],
+                }),
+            }
+        }
+    }
+
+    /// Prints compendium as PO file. The most frequent translation is used for each message;
+    /// other variants are listed in comments, unless `majority` is set.
+    fn print(&self, out: &mut dyn Write, majority: bool) -> Result<()> {
+        let header = PoMessage {
+            msgstr: vec!["Content-Type: text/plain; charset=UTF-8\n".to_string()],
+            ..Default::default()
+        };
+        writeln!(out, "{header}")?;
+
+        for (key, variants) in &self.entries {
+            // Stable sort keeps the first seen variant in case of a tie
+            let mut variants: Vec<&Variant> = variants.iter().collect();
+            variants.sort_by_key(|v| std::cmp::Reverse(v.sources.len()));
+            let (best, others) = variants
+                .split_first()
+                .expect("Entry has at least one variant");
+
+            let mut message = key.clone();
+            message.msgstr = best.msgstr.clone();
+            message.comments.push(source_comment(best, false));
+            if !majority {
+                for variant in others {
+                    let msgstr = variant
+                        .msgstr
+                        .iter()
+                        .map(|s| format!("\"{}\"", escape_comment(s)))
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    message
+                        .comments
+                        .push(format!("{}: {msgstr}", source_comment(variant, true)));
+                }
+            }
+
+            writeln!(out, "{message}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats comment with number of occurrences of the variant and list of its source files.
+fn source_comment(variant: &Variant, alternative: bool) -> String {
+    let mut sources: Vec<&str> = variant.sources.iter().map(|s| s.as_str()).collect();
+    sources.dedup();
+    let count = variant.sources.len();
+    let title = match (alternative, count) {
+        (false, 1) => tr!("# Translation used once in"),
+        (false, _) => tr!("# Translation used {count} times in"),
+        (true, 1) => tr!("# Alternative used once in"),
+        (true, _) => tr!("# Alternative used {count} times in"),
+    };
+    format!(
+        "{} {}",
+        title.replace("{count}", &count.to_string()),
+        sources.join(", ")
+    )
+}
+
+fn help_compendium(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] compendium [OPTIONS] [--] DIR_OR_FILE...
+
+Collect translations from many PO files into one translation memory file.
+Directories are searched for *.po files recursively.
+
+Each message is kept once, with the most frequent translation. Other translations
+are listed in comments as alternatives, with number of occurrences and source files.
+Header, fuzzy and untranslated messages are dropped.
+
+OPTIONS:
+
+  -o | --output FILE    Write compendium to FILE instead of standard output.
+
+  --majority            Keep the most frequent translation only, without alternatives.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compendium_positive() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("p1"))?;
+        fs::write(
+            dir.path().join("p1/uk.po"),
+            "msgid \"Open\"\nmsgstr \"Відкрити\"\n\nmsgid \"Close\"\nmsgstr \"\"\n",
+        )?;
+        fs::write(
+            dir.path().join("p2.po"),
+            "msgid \"Open\"\nmsgstr \"Відкрити\"\n\n#, fuzzy\nmsgid \"Close\"\nmsgstr \"Закрити\"\n",
+        )?;
+        fs::write(
+            dir.path().join("p3.po"),
+            "# comment\nmsgid \"Open\"\nmsgstr \"Відкрий\"\n",
+        )?;
+        fs::write(dir.path().join("notes.txt"), "not a PO file")?;
+
+        let dir_name = dir.path().to_str().unwrap();
+        command_compendium(&parser, &[dir_name], &mut ctx)?;
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            format!(
+                r#"msgid ""
+msgstr "Content-Type: text/plain; charset=UTF-8\n"
+
+# Translation used 2 times in {dir_name}/p1/uk.po, {dir_name}/p2.po
+# Alternative used once in {dir_name}/p3.po: "Відкрий"
+msgid "Open"
+msgstr "Відкрити"
+
+"#
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compendium_majority_to_file() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dir = tempfile::tempdir()?;
+        let f1 = dir.path().join("1.po");
+        let f2 = dir.path().join("2.po");
+        fs::write(&f1, "msgid \"Open\"\nmsgstr \"Відкрити\"\n")?;
+        fs::write(&f2, "msgid \"Open\"\nmsgstr \"Відкрий\"\n")?;
+        let output = dir.path().join("memory.po");
+
+        command_compendium(
+            &parser,
+            &[
+                "--majority",
+                "-o",
+                output.to_str().unwrap(),
+                f1.to_str().unwrap(),
+                f2.to_str().unwrap(),
+            ],
+            &mut ctx,
+        )?;
+
+        assert!(out.is_empty());
+        let messages = parser.parse_messages_from_file(output.to_str().unwrap())?;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].msgstr_first(), "Відкрити");
+        assert_eq!(messages[1].comments.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_alternative() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dir = tempfile::tempdir()?;
+        let f1 = dir.path().join("1.po");
+        let f2 = dir.path().join("2.po");
+        fs::write(&f1, "msgid \"Open\"\nmsgstr \"Відкрити\"\n")?;
+        fs::write(&f2, "msgid \"Open\"\nmsgstr \"Відкрити\\n\\\"файл\\\"\"\n")?;
+
+        command_compendium(
+            &parser,
+            &[f1.to_str().unwrap(), f2.to_str().unwrap()],
+            &mut ctx,
+        )?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains(": \"Відкрити\\n\\\"файл\\\"\"\n"));
+        let messages = parser.parse_messages_from_str(&result)?;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].msgstr_first(), "Відкрити");
+        assert_eq!(messages[1].comments.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result = command_compendium(&parser, &[], &mut ctx);
+        assert!(result.is_err());
+        Ok(())
+    }
+}
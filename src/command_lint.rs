@@ -0,0 +1,536 @@
+//! Command to check punctuation, capitalization, and whitespace of translations.
+//!
+//! Each rule can be enabled or disabled separately. Rules which depend on the
+//! typography of the language, e.g. quotes or ellipsis, are configured per language.
+
+use crate::command_check_symbols::get_whitespace;
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::parser::{Parser, PoMessage, escape_string};
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::sync::LazyLock;
+
+/// Lint rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    /// Leading and trailing whitespace must match msgid.
+    Whitespace,
+    /// Case of the first letter must match msgid.
+    Capitalization,
+    /// Final punctuation must match msgid.
+    Punctuation,
+    /// No double spaces, unless msgid has them.
+    DoubleSpace,
+    /// msgstr must not be a copy of msgid.
+    Untranslated,
+    /// Ellipsis must be written in the style of the language.
+    Ellipsis,
+    /// Quotes must be written in the style of the language.
+    Quotes,
+}
+
+impl Rule {
+    const ALL: [Rule; 7] = [
+        Rule::Whitespace,
+        Rule::Capitalization,
+        Rule::Punctuation,
+        Rule::DoubleSpace,
+        Rule::Untranslated,
+        Rule::Ellipsis,
+        Rule::Quotes,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Rule::Whitespace => "whitespace",
+            Rule::Capitalization => "capitalization",
+            Rule::Punctuation => "punctuation",
+            Rule::DoubleSpace => "double-space",
+            Rule::Untranslated => "untranslated",
+            Rule::Ellipsis => "ellipsis",
+            Rule::Quotes => "quotes",
+        }
+    }
+
+    /// Parses comma-separated list of rule names.
+    fn parse_list(list: &str) -> Result<Vec<Rule>> {
+        let mut rules = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match Rule::ALL.iter().find(|rule| rule.name() == name) {
+                Some(rule) => rules.push(*rule),
+                None => bail!(
+                    "{}",
+                    tr!("Unknown lint rule: \"{name}\". Expected: {rules}.")
+                        .replace("{name}", name)
+                        .replace("{rules}", &Rule::ALL.map(Rule::name).join(", "))
+                ),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Typography of a language.
+#[derive(Debug)]
+pub struct LanguageRules {
+    /// Names and codes of the language.
+    names: &'static [&'static str],
+    /// Opening and closing quotes, when the language has its own.
+    quotes: Option<(char, char)>,
+    /// True, when the language uses single character ellipsis "…" instead of "...".
+    ellipsis_char: bool,
+    /// Replacements of final punctuation of msgid, e.g. "?" is ";" in Greek.
+    punctuation_map: &'static [(char, char)],
+}
+
+const LANGUAGES: &[LanguageRules] = &[
+    LanguageRules {
+        names: &["uk", "uk_UA", "ukrainian"],
+        quotes: Some(('«', '»')),
+        ellipsis_char: true,
+        punctuation_map: &[],
+    },
+    LanguageRules {
+        names: &["en", "en_US", "en_GB", "english"],
+        quotes: None,
+        ellipsis_char: false,
+        punctuation_map: &[],
+    },
+    LanguageRules {
+        names: &["de", "de_DE", "german"],
+        quotes: Some(('„', '“')),
+        ellipsis_char: true,
+        punctuation_map: &[],
+    },
+    LanguageRules {
+        names: &["fr", "fr_FR", "french"],
+        quotes: Some(('«', '»')),
+        ellipsis_char: true,
+        punctuation_map: &[],
+    },
+    LanguageRules {
+        names: &["pl", "pl_PL", "polish"],
+        quotes: Some(('„', '”')),
+        ellipsis_char: true,
+        punctuation_map: &[],
+    },
+    LanguageRules {
+        names: &["el", "el_GR", "greek"],
+        quotes: Some(('«', '»')),
+        ellipsis_char: true,
+        punctuation_map: &[('?', ';')],
+    },
+];
+
+/// Returns typography rules for the language by its name or code, e.g. "uk" or "Ukrainian".
+pub fn language_rules(name: &str) -> Result<&'static LanguageRules> {
+    match LANGUAGES
+        .iter()
+        .find(|lang| lang.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+    {
+        Some(lang) => Ok(lang),
+        None => bail!(
+            "{}",
+            tr!("Unknown language for lint rules: \"{name}\". Expected: {languages}.")
+                .replace("{name}", name)
+                .replace(
+                    "{languages}",
+                    &LANGUAGES
+                        .iter()
+                        .map(|lang| lang.names[0])
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+        ),
+    }
+}
+
+/// Configuration of the linter.
+pub struct LintConfig {
+    pub rules: BTreeSet<Rule>,
+    pub language: &'static LanguageRules,
+}
+
+/// Matches tags, so quotes in attributes are not checked.
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^<>]*>").unwrap());
+
+const WHITESPACE: &[char] = &[' ', '\t', '\n', '\r', '\u{a0}', '\u{202f}'];
+const FINAL_PUNCTUATION: &[char] = &['.', ':', '!', '?', ';', '…'];
+
+/// Returns final punctuation of the text, with "..." normalized to "…".
+fn final_punctuation(text: &str) -> String {
+    let text = text.trim_end_matches(WHITESPACE);
+    let start = text.trim_end_matches(FINAL_PUNCTUATION).len();
+    text[start..].replace("...", "…")
+}
+
+/// Returns first character of the text, if it's a letter with case.
+fn first_cased_letter(text: &str) -> Option<char> {
+    text.trim_start_matches(WHITESPACE)
+        .chars()
+        .next()
+        .filter(|c| c.is_uppercase() || c.is_lowercase())
+}
+
+/// Checks a single msgstr against its msgid, appending warnings.
+fn lint_strings(config: &LintConfig, msgid: &str, msgstr: &str, warnings: &mut Vec<Diagnostic>) {
+    let mut warn = |rule: Rule, text: String| {
+        warnings.push(Diagnostic::warning(text).with_rule(rule.name()));
+    };
+    let enabled = |rule| config.rules.contains(&rule);
+
+    if enabled(Rule::Whitespace) {
+        let src_ws = get_whitespace(msgid);
+        let dst_ws = get_whitespace(msgstr);
+        if src_ws.0 != dst_ws.0 {
+            warn(
+                Rule::Whitespace,
+                tr!("Leading whitespace differs: \"{msgid_ws}\" in msgid, but \"{msgstr_ws}\" in msgstr.")
+                    .replace("{msgid_ws}", &escape_string(&src_ws.0))
+                    .replace("{msgstr_ws}", &escape_string(&dst_ws.0)),
+            );
+        }
+        if src_ws.1 != dst_ws.1 {
+            warn(
+                Rule::Whitespace,
+                tr!("Trailing whitespace differs: \"{msgid_ws}\" in msgid, but \"{msgstr_ws}\" in msgstr.")
+                    .replace("{msgid_ws}", &escape_string(&src_ws.1))
+                    .replace("{msgstr_ws}", &escape_string(&dst_ws.1)),
+            );
+        }
+    }
+
+    if enabled(Rule::Capitalization)
+        && let (Some(src), Some(dst)) = (first_cased_letter(msgid), first_cased_letter(msgstr))
+        && src.is_uppercase() != dst.is_uppercase()
+    {
+        warn(
+            Rule::Capitalization,
+            if src.is_uppercase() {
+                tr!("msgstr must start with a capital letter, like msgid.").to_string()
+            } else {
+                tr!("msgstr must start with a lowercase letter, like msgid.").to_string()
+            },
+        );
+    }
+
+    if enabled(Rule::Punctuation) {
+        let expected: String = final_punctuation(msgid)
+            .chars()
+            .map(|c| {
+                config
+                    .language
+                    .punctuation_map
+                    .iter()
+                    .find(|(from, _)| *from == c)
+                    .map_or(c, |(_, to)| *to)
+            })
+            .collect();
+        let actual = final_punctuation(msgstr);
+        if expected != actual {
+            warn(
+                Rule::Punctuation,
+                tr!("Final punctuation differs: \"{expected}\" expected, but \"{actual}\" found.")
+                    .replace("{expected}", &expected)
+                    .replace("{actual}", &actual),
+            );
+        }
+    }
+
+    if enabled(Rule::DoubleSpace) && msgstr.contains("  ") && !msgid.contains("  ") {
+        warn(
+            Rule::DoubleSpace,
+            tr!("msgstr contains double space.").to_string(),
+        );
+    }
+
+    if enabled(Rule::Untranslated) && msgstr == msgid && msgid.chars().any(char::is_alphabetic) {
+        warn(
+            Rule::Untranslated,
+            tr!("msgstr is the same as msgid.").to_string(),
+        );
+    }
+
+    if enabled(Rule::Ellipsis) && config.language.ellipsis_char && msgstr.contains("...") {
+        warn(
+            Rule::Ellipsis,
+            tr!("Use \"…\" instead of \"...\".").to_string(),
+        );
+    }
+
+    if enabled(Rule::Quotes)
+        && let Some((open, close)) = config.language.quotes
+    {
+        let text = TAG_RE.replace_all(msgstr, "");
+        if let Some(quote) = text
+            .chars()
+            .find(|c| ['"', '“', '”', '„', '«', '»'].contains(c) && *c != open && *c != close)
+        {
+            warn(
+                Rule::Quotes,
+                tr!("Use {open}{close} quotes instead of {quote}.")
+                    .replace("{open}", &open.to_string())
+                    .replace("{close}", &close.to_string())
+                    .replace("{quote}", &quote.to_string()),
+            );
+        }
+    }
+}
+
+/// Checks a single message. Returns warnings, if any.
+pub fn lint_message(config: &LintConfig, message: &PoMessage) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return warnings;
+    }
+
+    for (n, msgstr) in message.msgstr.iter().enumerate() {
+        let msgid = match &message.msgid_plural {
+            Some(msgid_plural) if n > 0 => msgid_plural,
+            _ => &message.msgid,
+        };
+        lint_strings(config, msgid, msgstr, &mut warnings);
+    }
+    warnings
+}
+
+/// Implementation of the `lint` command.
+pub fn command_lint(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut rules: BTreeSet<Rule> = Rule::ALL.into_iter().collect();
+    let mut language = "uk";
+
+    // Parse "lint" command options
+    let mut cmdline = cmdline;
+    loop {
+        match cmdline[..] {
+            ["-l", lang, ref tail @ ..]
+            | ["--lang", lang, ref tail @ ..]
+            | ["--language", lang, ref tail @ ..] => {
+                language = lang;
+                cmdline = tail;
+            }
+            ["--only", list, ref tail @ ..] => {
+                rules = Rule::parse_list(list)?.into_iter().collect();
+                cmdline = tail;
+            }
+            ["--enable", list, ref tail @ ..] => {
+                rules.extend(Rule::parse_list(list)?);
+                cmdline = tail;
+            }
+            ["--disable", list, ref tail @ ..] => {
+                for rule in Rule::parse_list(list)? {
+                    rules.remove(&rule);
+                }
+                cmdline = tail;
+            }
+            ["-h", ..] | ["--help", ..] => {
+                help(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let config = LintConfig {
+        rules,
+        language: language_rules(language)?,
+    };
+
+    let mut reporter = Reporter::new(options.output_format, "lint");
+    let mut violations = 0;
+    for file in cmdline {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            let warnings = lint_message(&config, message);
+            if !warnings.is_empty() {
+                violations += 1;
+                reporter.report(ctx, file, *line, message, &warnings)?;
+            }
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages with lint warnings.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] lint [OPTIONS] [--] FILE...
+
+Check punctuation, capitalization, and whitespace of translations.
+
+RULES:
+
+  whitespace      Leading and trailing whitespace of msgstr must match msgid.
+  capitalization  msgstr must start with a capital letter when msgid does, and vice versa.
+  punctuation     Final punctuation (".", ":", "!", "?", ";", "…") must match msgid,
+                  with language adaptations, e.g. "?" is ";" in Greek.
+  double-space    msgstr must not contain double spaces, unless msgid does.
+  untranslated    msgstr must not be the same as msgid.
+  ellipsis        Use "…" instead of "...", in languages which prefer it.
+  quotes          Use quotes of the language, e.g. «» in Ukrainian, „“ in German.
+
+OPTIONS:
+
+  -l | --language LANG  Language of translations: uk, en, de, fr, pl, el.
+                        Default value: uk.
+  --only RULES          Check only given comma-separated rules.
+  --enable RULES        Enable given comma-separated rules.
+  --disable RULES       Disable given comma-separated rules, e.g. "--disable quotes,ellipsis".
+
+All rules are enabled by default. Exit status is non-zero when warnings are found.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    fn lint(language: &str, msgid: &str, msgstr: &str) -> Option<String> {
+        let config = LintConfig {
+            rules: Rule::ALL.into_iter().collect(),
+            language: language_rules(language).unwrap(),
+        };
+        let message = PoMessage {
+            msgid: msgid.to_string(),
+            msgstr: vec![msgstr.to_string()],
+            ..Default::default()
+        };
+        let warnings = lint_message(&config, &message);
+        (!warnings.is_empty()).then(|| to_comments(&warnings))
+    }
+
+    #[test]
+    fn test_lint_valid() {
+        assert_eq!(lint("uk", "Open file...", "Відкрити файл…"), None);
+        assert_eq!(lint("uk", "Say \"hello\".", "Скажіть «привіт»."), None);
+        assert_eq!(
+            lint(
+                "uk",
+                "<a href=\"x\">Link</a>",
+                "<a href=\"x\">Посилання</a>"
+            ),
+            None
+        );
+        assert_eq!(lint("el", "Continue?", "Συνέχεια;"), None);
+        assert_eq!(lint("en", "Open...", "Open it..."), None);
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        assert_eq!(
+            lint("uk", " Name:", "ім'я").unwrap(),
+            "# Warning [whitespace]: Leading whitespace differs: \" \" in msgid, but \"\" in msgstr.\n\
+             # Warning [capitalization]: msgstr must start with a capital letter, like msgid.\n\
+             # Warning [punctuation]: Final punctuation differs: \":\" expected, but \"\" found.\n"
+        );
+        assert!(lint("uk", "Save  as", "Зберегти  як").is_none_or(|w| !w.contains("double-space")));
+        assert!(
+            lint("uk", "Save as", "Зберегти  як")
+                .unwrap()
+                .contains("[double-space]")
+        );
+        assert!(
+            lint("uk", "Linux", "Linux")
+                .unwrap()
+                .contains("[untranslated]")
+        );
+        assert!(
+            lint("uk", "Wait...", "Зачекайте...")
+                .unwrap()
+                .contains("[ellipsis]")
+        );
+        assert!(
+            lint("uk", "Say \"hi\"", "Скажіть \"привіт\"")
+                .unwrap()
+                .contains("Use «» quotes instead of \"")
+        );
+    }
+
+    #[test]
+    fn test_lint_command() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Loading...\"\nmsgstr \"Завантаження...\"\n\nmsgid \"Done.\"\nmsgstr \"готово\"\n",
+        )?;
+
+        let result = command_lint(
+            &parser,
+            &GlobalOptions::default(),
+            &[
+                "--disable",
+                "ellipsis,capitalization",
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Warning [punctuation]: Final punctuation differs: "." expected, but "" found.
+msgid "Done."
+msgstr "готово"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_rule_and_language() {
+        assert!(Rule::parse_list("whitespace,bogus").is_err());
+        assert!(language_rules("Ukrainian").is_ok());
+        assert!(language_rules("klingon").is_err());
+    }
+}
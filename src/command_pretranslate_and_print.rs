@@ -0,0 +1,265 @@
+//! Command to pre-translate messages using translation memory only, without AI.
+//!
+//! Exact matches by msgctxt and msgid are copied as is, while fuzzy matches
+//! are inserted as fuzzy translations for review.
+
+use crate::parser::{Parser, PoMessage};
+use crate::translation_memory::{
+    TranslationMemory, TranslationMemoryOptions, help_translation_memory_options,
+};
+use crate::util::IoContext;
+use anyhow::{Result, bail};
+use std::io::Write;
+
+/// Implementation of the `pretranslate` command.
+pub fn command_pretranslate_and_print(
+    parser: &Parser,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut tm_options = TranslationMemoryOptions::default();
+
+    // Parse "pretranslate" command options
+    let mut cmdline = cmdline;
+    loop {
+        if let Some(rest) = tm_options.parse_option(cmdline)? {
+            cmdline = rest;
+            continue;
+        }
+
+        match cmdline[..] {
+            ["-h", ..] | ["--help", ..] => {
+                help_pretranslate(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if tm_options.files.is_empty() {
+        bail!(tr!(
+            "Expected at least one translation memory file. Use --tm FILE option."
+        ));
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!(
+            "Expected at least one argument: the name of the file to pre-translate."
+        ));
+    }
+
+    let translation_memory = tm_options.load(parser, ctx)?;
+
+    for file in cmdline {
+        let messages = parser.parse_messages_from_file(file)?;
+        let stats = pretranslate_and_print(ctx, &translation_memory, &messages)?;
+
+        writeln!(
+            ctx.err,
+            "{}: {}",
+            tr!("INFO"),
+            tr!("Pre-translated {exact} exact and {fuzzy} fuzzy matches for {total} untranslated messages in file {file}.")
+                .replace("{exact}", &stats.exact.to_string())
+                .replace("{fuzzy}", &stats.fuzzy.to_string())
+                .replace("{total}", &stats.untranslated.to_string())
+                .replace("{file}", file)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Number of pre-translated messages.
+#[derive(Debug, Default, PartialEq)]
+struct PretranslateStats {
+    untranslated: usize,
+    exact: usize,
+    fuzzy: usize,
+}
+
+fn pretranslate_and_print(
+    ctx: &mut IoContext,
+    translation_memory: &TranslationMemory,
+    messages: &[PoMessage],
+) -> Result<PretranslateStats> {
+    let mut stats = PretranslateStats::default();
+
+    for message in messages {
+        if message.is_header() || message.is_translated() {
+            writeln!(ctx.out, "{message}")?;
+            continue;
+        }
+        stats.untranslated += 1;
+
+        // Translation is usable only when plural forms match
+        let same_form = |m: &PoMessage| m.is_plural() == message.is_plural();
+
+        if let Some(m) = translation_memory
+            .find_exact(message)
+            .filter(|m| same_form(m.message))
+        {
+            stats.exact += 1;
+            writeln!(ctx.out, "{}", m.message.with_key(message))?;
+            continue;
+        }
+
+        let fuzzy_match = translation_memory
+            .find_matches(message)
+            .into_iter()
+            .find(|m| same_form(m.message));
+
+        match fuzzy_match {
+            Some(m) => {
+                stats.fuzzy += 1;
+                let mut new_message = m.message.with_key(message);
+                new_message.comments = vec![
+                    tr!("# Fuzzy match {percent}% from {file}: \"{msgid}\"")
+                        .replace("{percent}", &format!("{:.0}", m.score * 100.0))
+                        .replace("{file}", m.source)
+                        .replace("{msgid}", &m.message.msgid),
+                    "#, fuzzy".to_string(),
+                ];
+                new_message
+                    .comments
+                    .extend(message.comments.iter().cloned());
+                writeln!(ctx.out, "{new_message}")?;
+            }
+            None => writeln!(ctx.out, "{message}")?,
+        }
+    }
+
+    Ok(stats)
+}
+
+fn help_pretranslate(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] pretranslate --tm TM_FILE [OPTIONS] [--] FILE...
+
+Fill untranslated messages using translation memory only, without AI.
+
+Messages with same msgctxt and msgid are copied from translation memory as is.
+The best fuzzy match with similarity above --tm-min-score is inserted as fuzzy translation,
+with a comment naming the score and the file of the match.
+"#
+        )
+    )?;
+    writeln!(out, "{}", help_translation_memory_options())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_pretranslate_positive() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let tm = NamedTempFile::new()?;
+        fs::write(
+            tm.path(),
+            r#"
+msgid "Open file"
+msgstr "Відкрити файл"
+
+msgctxt "menu"
+msgid "Close"
+msgstr "Закрити"
+"#,
+        )?;
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+#: src/main.c:1
+msgid "Open file"
+msgstr ""
+
+#, c-format
+msgid "Open files"
+msgstr ""
+
+msgid "Close"
+msgstr ""
+
+msgid "Quit"
+msgstr "Вийти"
+"#,
+        )?;
+
+        command_pretranslate_and_print(
+            &parser,
+            &[
+                "--tm",
+                tm.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        )?;
+
+        let result = String::from_utf8(out)?;
+        let tm_name = tm.path().to_str().unwrap();
+        assert_eq!(
+            result,
+            format!(
+                r#"#: src/main.c:1
+msgid "Open file"
+msgstr "Відкрити файл"
+
+# Fuzzy match 90% from {tm_name}: "Open file"
+#, fuzzy
+#, c-format
+msgid "Open files"
+msgstr "Відкрити файл"
+
+# Fuzzy match 100% from {tm_name}: "Close"
+#, fuzzy
+msgid "Close"
+msgstr "Закрити"
+
+msgid "Quit"
+msgstr "Вийти"
+
+"#
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_tm() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result = command_pretranslate_and_print(&parser, &["file.po"], &mut ctx);
+        assert!(result.is_err());
+        Ok(())
+    }
+}
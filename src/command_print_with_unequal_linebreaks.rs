@@ -2,13 +2,15 @@
 //!
 //! This is often a sign of a formatting error in the translation.
 
+use crate::diagnostics::{Diagnostic, Reporter};
 use crate::parser::Parser;
-use crate::util::IoContext;
+use crate::util::{GlobalOptions, IoContext};
 use anyhow::{Result, bail};
 
 /// Implementation of the `with-unequal-linebreaks` command.
 pub fn command_print_with_unequal_linebreaks(
     parser: &Parser,
+    options: &GlobalOptions,
     cmdline: &[&str],
     ctx: &mut IoContext,
 ) -> Result<()> {
@@ -20,30 +22,40 @@ pub fn command_print_with_unequal_linebreaks(
         )?,
 
         files if !files.is_empty() => {
+            let mut reporter = Reporter::new(options.output_format, "with-unequal-linebreaks");
             for file in files {
-                let messages = parser.parse_messages_from_file(file)?;
+                let messages = parser.parse_numbered_messages_from_file(file)?;
 
-                for message in messages.iter() {
+                for (line, message) in messages.iter() {
                     if message.is_header() {
-                        writeln!(ctx.out, "{message}")?;
-                    } else if !message.is_plural() {
-                        let msgid_nl: u32 = message.msgid.matches('\n').map(|_| 1).sum();
-                        let msgstr_nl: u32 = message.msgstr_first().matches('\n').map(|_| 1).sum();
-                        if msgid_nl != msgstr_nl {
+                        if reporter.is_po() {
                             writeln!(ctx.out, "{message}")?;
                         }
-                    } else {
-                        let msgid_nl: u32 = message.msgid.matches('\n').map(|_| 1).sum();
-                        for msgstr in &message.msgstr {
-                            let msgstr_nl: u32 = msgstr.matches('\n').map(|_| 1).sum();
-                            if msgid_nl != msgstr_nl {
-                                writeln!(ctx.out, "{message}")?;
-                                break; // no need to print multiple times
-                            }
+                        continue;
+                    }
+
+                    let msgid_nl = message.msgid.matches('\n').count();
+                    // No need to report plural messages multiple times
+                    let unequal = message
+                        .msgstr
+                        .iter()
+                        .map(|msgstr| msgstr.matches('\n').count())
+                        .find(|msgstr_nl| *msgstr_nl != msgid_nl);
+                    if let Some(msgstr_nl) = unequal {
+                        if reporter.is_po() {
+                            writeln!(ctx.out, "{message}")?;
+                        } else {
+                            let warning = Diagnostic::warning(
+                                tr!("Number of linebreaks differs: {msgid_count} in msgid, but {msgstr_count} in msgstr.")
+                                    .replace("{msgid_count}", &msgid_nl.to_string())
+                                    .replace("{msgstr_count}", &msgstr_nl.to_string()),
+                            );
+                            reporter.report(ctx, file, *line, message, &[warning])?;
                         }
                     }
                 }
             }
+            reporter.finish(ctx)?;
         }
 
         _ => bail!(tr!("At least one file is expected.")),
@@ -74,7 +86,12 @@ mod tests {
             "msgid \"a\\n\"\nmsgstr \"b\"\n\nmsgid \"c\"\nmsgstr \"d\"\n",
         )?;
 
-        command_print_with_unequal_linebreaks(&parser, &[f.path().to_str().unwrap()], &mut ctx)?;
+        command_print_with_unequal_linebreaks(
+            &parser,
+            &GlobalOptions::default(),
+            &[f.path().to_str().unwrap()],
+            &mut ctx,
+        )?;
 
         let result = String::from_utf8(out)?;
         assert!(result.contains("msgid \"a\\n\""));
@@ -92,7 +109,12 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        command_print_with_unequal_linebreaks(&parser, &["--help"], &mut ctx)?;
+        command_print_with_unequal_linebreaks(
+            &parser,
+            &GlobalOptions::default(),
+            &["--help"],
+            &mut ctx,
+        )?;
 
         let result = String::from_utf8(out)?;
         assert!(result.contains("Usage:"));
@@ -109,7 +131,12 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let result = command_print_with_unequal_linebreaks(&parser, &[], &mut ctx);
+        let result = command_print_with_unequal_linebreaks(
+            &parser,
+            &GlobalOptions::default(),
+            &[],
+            &mut ctx,
+        );
         assert!(result.is_err());
         Ok(())
     }
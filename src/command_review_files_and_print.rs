@@ -3,38 +3,34 @@
 //! This module compares different versions of translations for the same PO messages
 //! and uses an AI model to pick or synthesize the best version.
 
+use crate::ai_backend::{AiBackend, AiBackendOptions, help_ai_backend_options};
+use crate::diagnostics::to_comments;
 use crate::parser::{Parser, PoMessage};
-use crate::util::{AiBackend, IoContext, validate_message};
+use crate::util::{GlobalOptions, IoContext, validate_message};
 use anyhow::{Result, bail};
 use std::io::Write;
 
 /// Implementation of the `review` command.
 pub fn command_review_files_and_print(
     parser: &Parser,
+    options: &GlobalOptions,
     cmdline: &[&str],
     ctx: &mut IoContext,
 ) -> Result<()> {
     let mut language = "Ukrainian";
-    let mut model = "ollama:translategemma:12b";
-    let mut role = "translate-po";
-    let mut ai_command_str: Option<&str> = None;
+    let mut backend_options = AiBackendOptions::default();
 
     // Parse "translate" command options
     let mut cmdline = cmdline;
 
     // Parse "review" command options
     loop {
-        match cmdline[..] {
-            ["-m", model_name, ref tail @ ..] | ["--model", model_name, ref tail @ ..] => {
-                model = model_name;
-                cmdline = tail;
-            }
-
-            ["-r", role_name, ref tail @ ..] | ["--role", role_name, ref tail @ ..] => {
-                role = role_name;
-                cmdline = tail;
-            }
+        if let Some(rest) = backend_options.parse_option(cmdline)? {
+            cmdline = rest;
+            continue;
+        }
 
+        match cmdline[..] {
             ["-l", lang_name, ref tail @ ..]
             | ["--lang", lang_name, ref tail @ ..]
             | ["--language", lang_name, ref tail @ ..] => {
@@ -42,11 +38,6 @@ pub fn command_review_files_and_print(
                 cmdline = tail;
             }
 
-            ["--ai-command", cmd, ref tail @ ..] => {
-                ai_command_str = Some(cmd);
-                cmdline = tail;
-            }
-
             ["-h", ..] | ["-help", ..] | ["--help", ..] => {
                 help_review(ctx.out)?;
                 return Ok(());
@@ -78,17 +69,14 @@ pub fn command_review_files_and_print(
         messages.push(file_messages);
     }
 
-    let backend = if let Some(cmd) = ai_command_str {
-        AiBackend::from_command_line(cmd)
-    } else {
-        AiBackend::with_aichat_defaults(model, role, None)
-    };
+    let backend = backend_options.build()?;
 
     review_files_and_print(
         ctx,
         backend,
         language,
         parser.number_of_plural_cases,
+        options.max_length_ratio,
         messages,
     )?;
 
@@ -100,6 +88,7 @@ fn review_files_and_print(
     backend: AiBackend,
     language: &str,
     number_of_plural_cases: Option<usize>,
+    max_length_ratio: Option<f64>,
     mut messages: Vec<Vec<PoMessage>>,
 ) -> Result<()> {
     let parser = Parser {
@@ -150,35 +139,17 @@ fn review_files_and_print(
         text += "\n";
 
         // Review messages
+        let instruction = review_instruction(language);
         let message_text = format!(
-            r#"
-<instruction>
-Act as technical translator for Gettext .po files.
-Review PO message translation variants in <message></message> tag to {language} Language. List cons for varians in <review></review> tag.
-Check for technical correctness, translation correctness, correct gender, correct plural form, correct line breaks.
-Choose the variant most pleasing for a native speaker in {language} language.
-Write review in <review></review> tag first, then write one correct PO message without flaws in <message></message> tag.
-Example:
-<review> the review </review>
-<message>
-msgid "text"
-msgstr "текст"
-</message>
-Resulting message must be correct Gettext PO Message, wrapped in <message></message> tag.
-IMPORTANT: Copy msgid field verbatim, put translation into msgstr field.
-In translated message, msgid field must be copied intact first, then msgstr field must be translation of msgid to {language} language.
-IMPORTANT: Start with "<message> msgid ".
-</instruction>
-<message>
+            r#"<message>
 {text}
 </message>
-"#,
-            language = language
+"#
         );
         //eprintln!("{message_text}");
 
         // Translate
-        let new_message_text = backend.execute(&message_text)?;
+        let new_message_text = backend.execute_chat(ctx.err, &instruction, &message_text)?;
         //eprintln!("# Review:\n{new_message_text}\n");
 
         // Extract text between <message> and </message>, if they are present
@@ -194,7 +165,7 @@ IMPORTANT: Start with "<message> msgid ".
 
         match parser.parse_message_from_str(new_message_text_slice) {
             Ok(new_message) => {
-                let errors = validate_message(&new_message);
+                let errors = to_comments(&validate_message(&new_message, max_length_ratio));
                 if message.to_key() == new_message.to_key() {
                     writeln!(
                         ctx.out,
@@ -209,7 +180,7 @@ IMPORTANT: Start with "<message> msgid ".
                         tr!("Review")
                     )?;
                     let fixed_message = new_message.with_key(&message.to_key());
-                    let errors = validate_message(&fixed_message);
+                    let errors = to_comments(&validate_message(&fixed_message, max_length_ratio));
                     writeln!(
                         ctx.out,
                         "{}:\n{errors}#, fuzzy\n{fixed_message}",
@@ -238,6 +209,29 @@ IMPORTANT: Start with "<message> msgid ".
     Ok(())
 }
 
+/// Instruction for AI model to choose or synthesize the best translation among variants.
+///
+/// Variants are expected in `<message></message>` tag, each titled `# Variant N:`.
+pub fn review_instruction(language: &str) -> String {
+    format!(
+        r#"Act as technical translator for Gettext .po files.
+Review PO message translation variants in <message></message> tag to {language} Language. List cons for varians in <review></review> tag.
+Check for technical correctness, translation correctness, correct gender, correct plural form, correct line breaks.
+Choose the variant most pleasing for a native speaker in {language} language.
+Write review in <review></review> tag first, then write one correct PO message without flaws in <message></message> tag.
+Example:
+<review> the review </review>
+<message>
+msgid "text"
+msgstr "текст"
+</message>
+Resulting message must be correct Gettext PO Message, wrapped in <message></message> tag.
+IMPORTANT: Copy msgid field verbatim, put translation into msgstr field.
+In translated message, msgid field must be copied intact first, then msgstr field must be translation of msgid to {language} language.
+IMPORTANT: Start with "<message> msgid "."#
+    )
+}
+
 fn help_review(out: &mut dyn Write) -> Result<()> {
     writeln!(
         out,
@@ -253,17 +247,10 @@ OPTIONS:
 
   -l | --language LANG  Language to use. Default value: "Ukrainian".
 
-  -m | --model MODEL    AI model to use with aichat. Default value: "ollama:phi4:14b-q8_0".
-                        Additional models: "aya-expanse:32b-q3_K_S", "codestral:22b-v0.1-q5_K_S".
-
-  -r | --role ROLE      AI role to use with aichat.  Default value: "translate-po".
-                        For better reproducibility, set temperature and top_p to 0, to remove randomness.
-
-  --ai-command COMMAND  Custom command to use for review instead of aichat.
-                        Example: --ai-command "ollama run gemma3"
 "#
         )
     )?;
+    writeln!(out, "{}", help_ai_backend_options())?;
     Ok(())
 }
 
@@ -291,6 +278,7 @@ mod tests {
             AiBackend::mock("<message>msgid \"a\"\nmsgstr \"reviewed_a\"</message>"),
             "Ukrainian",
             None,
+            None,
             messages,
         )?;
 
@@ -310,7 +298,7 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        command_review_files_and_print(&parser, &["--help"], &mut ctx)?;
+        command_review_files_and_print(&parser, &GlobalOptions::default(), &["--help"], &mut ctx)?;
 
         let result = String::from_utf8(out)?;
         assert!(result.contains("Usage:"));
@@ -327,7 +315,8 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let result = command_review_files_and_print(&parser, &[], &mut ctx);
+        let result =
+            command_review_files_and_print(&parser, &GlobalOptions::default(), &[], &mut ctx);
         assert!(result.is_err());
         Ok(())
     }
@@ -0,0 +1,580 @@
+//! Command to estimate quality of translations using AI.
+//!
+//! The AI model rates each translated message from 0 to 100 with a short rationale.
+//! Messages with low scores are marked as fuzzy, so humans review only suspicious
+//! translations.
+
+use crate::ai_backend::{AiBackend, AiBackendOptions, help_ai_backend_options};
+use crate::command_translate_and_print::strip_thinking;
+use crate::parser::{Parser, PoMessage};
+use crate::util::IoContext;
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::io::Write;
+
+/// Default minimal score of a message, which is not marked as fuzzy.
+const DEFAULT_THRESHOLD: u32 = 70;
+
+/// Options of the `score` command.
+struct ScoreConfig<'a> {
+    backend: AiBackend,
+    language: &'a str,
+    /// Messages with lower score are marked as fuzzy.
+    threshold: u32,
+    /// Percent of messages with the lowest scores to mark as fuzzy, regardless of threshold.
+    worst_percent: Option<f64>,
+}
+
+/// Score of a single message, given by the AI model.
+#[derive(Debug, Clone, PartialEq)]
+struct Score {
+    score: u32,
+    rationale: String,
+}
+
+/// Implementation of the `score` command.
+pub fn command_score_with_ai_and_print(
+    parser: &Parser,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut language = "Ukrainian";
+    let mut threshold = DEFAULT_THRESHOLD;
+    let mut worst_percent = None;
+    let mut report_file = None;
+    let mut backend_options = AiBackendOptions::default();
+
+    // Parse "score" command options
+    let mut cmdline = cmdline;
+    loop {
+        if let Some(rest) = backend_options.parse_option(cmdline)? {
+            cmdline = rest;
+            continue;
+        }
+
+        match cmdline[..] {
+            ["-l", lang_name, ref tail @ ..]
+            | ["--lang", lang_name, ref tail @ ..]
+            | ["--language", lang_name, ref tail @ ..] => {
+                language = lang_name;
+                cmdline = tail;
+            }
+            ["-t", value, ref tail @ ..] | ["--threshold", value, ref tail @ ..] => {
+                match value.parse::<u32>() {
+                    Ok(n) if n <= 100 => threshold = n,
+                    _ => bail!(tr!("Invalid argument for --threshold option. Expected: score between 0 and 100. Actual value: \"{value}\".")
+                        .replace("{value}", value)),
+                }
+                cmdline = tail;
+            }
+            ["--worst", value, ref tail @ ..] => {
+                match value.trim_end_matches('%').parse::<f64>() {
+                    Ok(n) if (0.0..=100.0).contains(&n) => worst_percent = Some(n),
+                    _ => bail!(tr!("Invalid argument for --worst option. Expected: percent between 0 and 100. Actual value: \"{value}\".")
+                        .replace("{value}", value)),
+                }
+                cmdline = tail;
+            }
+            ["--report", file, ref tail @ ..] => {
+                report_file = Some(file);
+                cmdline = tail;
+            }
+
+            ["-h", ..] | ["-help", ..] | ["--help", ..] => {
+                help_score(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!(
+            "Expected at least one argument: the name of the file to score."
+        ));
+    }
+
+    let config = ScoreConfig {
+        backend: backend_options.build()?,
+        language,
+        threshold,
+        worst_percent,
+    };
+
+    // All files are scored first, because --worst ranks messages of the whole run
+    let mut files = Vec::new();
+    let mut scores = Vec::new();
+    for file in cmdline {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        scores.extend(score_messages(ctx, &config, file, &messages)?);
+        files.push((file, messages));
+    }
+    let worst = worst_messages(&scores, config.worst_percent);
+
+    let mut report = Vec::new();
+    let mut offset = 0;
+    for (file, messages) in &files {
+        let range = offset..offset + messages.len();
+        report.extend(print_scored(
+            ctx,
+            &config,
+            file,
+            messages,
+            &scores[range.clone()],
+            &worst[range],
+        )?);
+        offset += messages.len();
+    }
+
+    if let Some(file) = report_file {
+        let report = json!({
+            "threshold": config.threshold,
+            "messages": report,
+        });
+        let report = serde_json::to_string_pretty(&report)?;
+        std::fs::write(file, report + "\n").with_context(|| {
+            tr!("Cannot write report to file \"{file}\".").replace("{file}", file)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Scores translated messages of a file. Messages, which are not scored, have no score.
+fn score_messages(
+    ctx: &mut IoContext,
+    config: &ScoreConfig,
+    file: &str,
+    messages: &[(usize, PoMessage)],
+) -> Result<Vec<Option<Score>>> {
+    let mut scores: Vec<Option<Score>> = Vec::with_capacity(messages.len());
+
+    for (line, message) in messages {
+        // Untranslated and fuzzy messages need human review anyway
+        if message.is_header() || !message.is_translated() || message.is_fuzzy() {
+            scores.push(None);
+            continue;
+        }
+
+        match score_message(ctx.err, config, message) {
+            Ok(score) => scores.push(Some(score)),
+            Err(e) => {
+                writeln!(
+                    ctx.err,
+                    "{}: {e:#}:\n{message}",
+                    tr!("# WARNING: Cannot score message at {file}:{line}")
+                        .replace("{file}", file)
+                        .replace("{line}", &line.to_string())
+                )?;
+                scores.push(None);
+            }
+        }
+    }
+
+    Ok(scores)
+}
+
+/// Prints all messages of a file, with low-scored and worst ones marked as fuzzy.
+///
+/// Returns entries of the JSON report.
+fn print_scored(
+    ctx: &mut IoContext,
+    config: &ScoreConfig,
+    file: &str,
+    messages: &[(usize, PoMessage)],
+    scores: &[Option<Score>],
+    worst: &[bool],
+) -> Result<Vec<Value>> {
+    let mut report = Vec::new();
+    for (((line, message), score), &worst) in messages.iter().zip(scores).zip(worst) {
+        let Some(score) = score else {
+            writeln!(ctx.out, "{message}")?;
+            continue;
+        };
+
+        let fuzzy = score.score < config.threshold || worst;
+        if fuzzy {
+            writeln!(ctx.out, "{}", mark_fuzzy(message, score))?;
+        } else {
+            writeln!(ctx.out, "{message}")?;
+        }
+
+        report.push(json!({
+            "file": file,
+            "line": line,
+            "msgctxt": message.msgctxt,
+            "msgid": message.msgid,
+            "msgstr": message.msgstr,
+            "score": score.score,
+            "rationale": score.rationale,
+            "fuzzy": fuzzy,
+        }));
+    }
+
+    Ok(report)
+}
+
+/// Selects the given percent of scored messages with the lowest scores.
+///
+/// Exactly `ceil(n * percent / 100)` of `n` scored messages are selected. Of messages
+/// with equal scores, earlier ones are selected first.
+fn worst_messages(scores: &[Option<Score>], worst_percent: Option<f64>) -> Vec<bool> {
+    let mut worst = vec![false; scores.len()];
+    let Some(worst_percent) = worst_percent else {
+        return worst;
+    };
+
+    let mut ranked: Vec<(usize, u32)> = scores
+        .iter()
+        .enumerate()
+        .filter_map(|(i, score)| Some((i, score.as_ref()?.score)))
+        .collect();
+    let count = (ranked.len() as f64 * worst_percent / 100.0).ceil() as usize;
+    // Stable sort keeps order of messages with equal scores
+    ranked.sort_by_key(|&(_, score)| score);
+    for (i, _) in ranked.into_iter().take(count) {
+        worst[i] = true;
+    }
+    worst
+}
+
+/// Asks the AI model to score the translation of the message.
+fn score_message(err: &mut dyn Write, config: &ScoreConfig, message: &PoMessage) -> Result<Score> {
+    let language = config.language;
+    let instruction = format!(
+        r#"Act as a reviewer of translations in Gettext .po files.
+Rate the translation of the PO message in <message></message> tag to {language} language with a score from 0 to 100,
+where 100 is a perfect translation and 0 is a wrong or missing translation.
+Check for meaning, terminology, grammar, correct gender, correct plural forms, placeholders, markup, and line breaks.
+Write the score in <score></score> tag, then a short rationale in one sentence in <rationale></rationale> tag.
+Example:
+<score>85</score>
+<rationale>Correct meaning, but "файл" is in the wrong case.</rationale>"#
+    );
+    let message_text = format!("<message>\n{message}</message>\n");
+
+    let reply = config
+        .backend
+        .execute_chat(err, &instruction, &message_text)?;
+    parse_score(strip_thinking(&reply))
+}
+
+/// Extracts text between `<tag>` and `</tag>`.
+fn extract_tag<'a>(reply: &'a str, tag: &str) -> Option<&'a str> {
+    let start = reply.rfind(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = reply[start..].find(&format!("</{tag}>"))? + start;
+    Some(reply[start..end].trim())
+}
+
+/// Parses score and rationale from the reply of the AI model.
+fn parse_score(reply: &str) -> Result<Score> {
+    let Some(score) = extract_tag(reply, "score") else {
+        bail!(tr!("Score is not found in AI reply."));
+    };
+    let score = match score.parse::<u32>() {
+        Ok(score) if score <= 100 => score,
+        _ => bail!(
+            "{}",
+            tr!("Invalid score in AI reply. Expected: number between 0 and 100. Actual value: \"{value}\".")
+                .replace("{value}", score)
+        ),
+    };
+
+    // Rationale is placed into a single-line comment
+    let rationale = extract_tag(reply, "rationale")
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(Score { score, rationale })
+}
+
+/// Returns copy of the message, marked as fuzzy, with score and rationale as translator comment.
+fn mark_fuzzy(message: &PoMessage, score: &Score) -> PoMessage {
+    let mut new_message = message.clone();
+
+    let comment = tr!("# AI score {score}/100: {rationale}")
+        .replace("{score}", &score.score.to_string())
+        .replace("{rationale}", &score.rationale);
+    new_message
+        .comments
+        .insert(0, comment.trim_end().to_string());
+
+    // Add fuzzy flag to existing flags, or add new flags line before previous msgid ("#|")
+    if let Some(flags) = new_message
+        .comments
+        .iter_mut()
+        .find(|c| c.starts_with("#,"))
+    {
+        *flags = format!("#, fuzzy,{}", &flags[2..]);
+    } else {
+        let position = new_message
+            .comments
+            .iter()
+            .position(|c| c.starts_with("#|"))
+            .unwrap_or(new_message.comments.len());
+        new_message
+            .comments
+            .insert(position, "#, fuzzy".to_string());
+    }
+
+    new_message
+}
+
+fn help_score(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] score [OPTIONS] [--] FILE...
+
+Estimate quality of translations using AI tools (aichat, ollama, or HTTP API).
+
+The AI model rates each translated message from 0 to 100, with a short rationale.
+Messages with scores below the threshold are marked as fuzzy, with the score and the
+rationale in a translator comment, so only suspicious translations need a human review.
+Untranslated and fuzzy messages are not scored.
+
+All messages are printed to standard output. A JSON report with the score and the
+rationale of each scored message is written to the file given by --report.
+
+OPTIONS:
+
+  -l | --language LANG  Language of translations. Default value: "Ukrainian".
+
+  -t | --threshold N    Mark messages with score below N as fuzzy. Default value: 70.
+
+  --worst PERCENT       Also mark PERCENT of scored messages with the lowest scores as fuzzy,
+                        e.g. --worst 10 for the worst 10%. Messages of all files are ranked
+                        together; of messages with equal scores, earlier ones are marked first.
+
+  --report FILE         Write JSON report to FILE.
+
+"#
+        )
+    )?;
+    writeln!(out, "{}", help_ai_backend_options())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    fn score(score: u32) -> Option<Score> {
+        Some(Score {
+            score,
+            rationale: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_parse_score() -> Result<()> {
+        assert_eq!(
+            parse_score("<score> 40 </score>\n<rationale>Wrong\n  term.</rationale>")?,
+            Score {
+                score: 40,
+                rationale: "Wrong term.".to_string()
+            }
+        );
+        assert!(parse_score("<score>high</score>").is_err());
+        assert!(parse_score("<score>101</score>").is_err());
+        assert!(parse_score("Looks good.").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_worst_messages() {
+        let scores = vec![score(90), None, score(20), score(80), score(95), score(60)];
+        let selected = |percent| -> Vec<usize> {
+            worst_messages(&scores, percent)
+                .iter()
+                .enumerate()
+                .filter_map(|(i, &worst)| worst.then_some(i))
+                .collect()
+        };
+        assert_eq!(selected(None), Vec::<usize>::new());
+        assert_eq!(selected(Some(0.0)), Vec::<usize>::new());
+        assert_eq!(selected(Some(10.0)), vec![2]);
+        assert_eq!(selected(Some(40.0)), vec![2, 5]);
+
+        // Ties don't increase the number of selected messages
+        let scores = vec![score(95), score(90), score(90), score(90), score(95)];
+        let worst = worst_messages(&scores, Some(10.0));
+        assert_eq!(worst, vec![false, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_mark_fuzzy() -> Result<()> {
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str(
+            "#: src/main.c:10\n#, c-format\nmsgid \"%d files\"\nmsgstr \"%d файлів\"\n",
+        )?;
+        let score = Score {
+            score: 40,
+            rationale: "Wrong plural form.".to_string(),
+        };
+        assert_eq!(
+            mark_fuzzy(&message, &score).to_string(),
+            "# AI score 40/100: Wrong plural form.\n#: src/main.c:10\n#, fuzzy, c-format\nmsgid \"%d files\"\nmsgstr \"%d файлів\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_score() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+
+        let config = ScoreConfig {
+            backend: AiBackend::mock("<score>35</score><rationale>Meaning is lost.</rationale>"),
+            language: "Ukrainian",
+            threshold: DEFAULT_THRESHOLD,
+            worst_percent: None,
+        };
+
+        let parser = Parser::new(None);
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            "msgid \"Open\"\nmsgstr \"Відкрити\"\n\nmsgid \"Close\"\nmsgstr \"\"\n",
+        )?;
+        let file = f.path().to_str().unwrap();
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+
+        let scores = score_messages(&mut ctx, &config, file, &messages)?;
+        let report = print_scored(&mut ctx, &config, file, &messages, &scores, &[false; 2])?;
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            "# AI score 35/100: Meaning is lost.\n#, fuzzy\nmsgid \"Open\"\nmsgstr \"Відкрити\"\n\nmsgid \"Close\"\nmsgstr \"\"\n\n"
+        );
+        assert_eq!(
+            report,
+            vec![json!({
+                "file": file,
+                "line": 1,
+                "msgctxt": null,
+                "msgid": "Open",
+                "msgstr": ["Відкрити"],
+                "score": 35,
+                "rationale": "Meaning is lost.",
+                "fuzzy": true,
+            })]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_score_report_file() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f = NamedTempFile::new()?;
+        fs::write(f.path(), "msgid \"Open\"\nmsgstr \"Відкрити\"\n")?;
+        let report = NamedTempFile::new()?;
+
+        command_score_with_ai_and_print(
+            &parser,
+            &[
+                "--ai-command",
+                "echo <score>90</score>",
+                "--no-cache",
+                "--report",
+                report.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        )?;
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            "msgid \"Open\"\nmsgstr \"Відкрити\"\n\n"
+        );
+        let report: Value = serde_json::from_str(&fs::read_to_string(report.path())?)?;
+        assert_eq!(report["threshold"], 70);
+        assert_eq!(report["messages"][0]["score"], 90);
+        assert_eq!(report["messages"][0]["fuzzy"], false);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_worst_of_all_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let f1 = NamedTempFile::new()?;
+        let f2 = NamedTempFile::new()?;
+        fs::write(f1.path(), "msgid \"Open\"\nmsgstr \"Відкрити\"\n")?;
+        fs::write(f2.path(), "msgid \"Close\"\nmsgstr \"Закрити\"\n")?;
+
+        command_score_with_ai_and_print(
+            &parser,
+            &[
+                "--ai-command",
+                "echo <score>90</score>",
+                "--no-cache",
+                "--worst",
+                "50",
+                f1.path().to_str().unwrap(),
+                f2.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        )?;
+
+        // One of two messages with equal scores is marked, and no report is printed
+        assert_eq!(
+            String::from_utf8(out)?,
+            "# AI score 90/100:\n#, fuzzy\nmsgid \"Open\"\nmsgstr \"Відкрити\"\n\nmsgid \"Close\"\nmsgstr \"Закрити\"\n\n"
+        );
+        assert!(err.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_help() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        command_score_with_ai_and_print(&parser, &["--help"], &mut ctx)?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("Usage:"));
+        Ok(())
+    }
+}
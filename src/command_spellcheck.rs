@@ -0,0 +1,433 @@
+//! Command to check spelling of translations with Hunspell dictionaries.
+//!
+//! Placeholders, markup, and accelerator markers are removed from msgstr before
+//! it's split into words, so only human-readable text is checked.
+
+use crate::diagnostics::{Diagnostic, Reporter};
+use crate::format_string::{message_format, parse_format};
+use crate::hunspell::{Hunspell, find_dictionary};
+use crate::parser::{Parser, PoMessage};
+use crate::util::{GlobalOptions, IoContext};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::fs;
+use std::io::Write;
+use std::sync::LazyLock;
+
+/// Matches tags, entities, URLs, and e-mail addresses, which are not checked.
+static NOT_TEXT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[^<>]*>|&[A-Za-z0-9#]+;|\b\w+://\S+|\S+@\S+\.\w+").unwrap());
+
+/// Matches words, including apostrophes and hyphens inside them.
+static WORD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\p{L}\p{M}\p{N}_]+(?:['’ʼ-][\p{L}\p{M}\p{N}_]+)*").unwrap());
+
+/// Spell checker, which uses one or more dictionaries.
+pub struct SpellChecker {
+    dictionaries: Vec<Hunspell>,
+    marker: char,
+}
+
+impl SpellChecker {
+    /// Creates spell checker. Words from `ignored` are considered correct.
+    pub fn new(mut dictionaries: Vec<Hunspell>, ignored: &[String], marker: char) -> Result<Self> {
+        for dict in &mut dictionaries {
+            for word in ignored {
+                dict.add_word(word)?;
+            }
+        }
+        Ok(SpellChecker {
+            dictionaries,
+            marker,
+        })
+    }
+
+    fn check_word(&self, word: &str) -> bool {
+        self.dictionaries.iter().any(|dict| dict.check(word))
+    }
+
+    /// Returns words of msgstr to check: without placeholders, markup, accelerators,
+    /// identifiers, and numbers.
+    pub fn words(&self, message: &PoMessage, msgstr: &str) -> Vec<String> {
+        let mut text = msgstr.to_string();
+
+        if let Some(kind) = message_format(message)
+            && let Ok(placeholders) = parse_format(kind, msgstr)
+        {
+            for placeholder in placeholders {
+                text = text.replacen(&placeholder.text, " ", 1);
+            }
+        }
+
+        let text = NOT_TEXT_RE.replace_all(&text, " ");
+        let text = remove_accelerators(&text, self.marker);
+
+        WORD_RE
+            .find_iter(&text)
+            .map(|m| m.as_str())
+            .filter(|word| {
+                word.chars().count() > 1
+                    && !word.chars().any(|c| c.is_numeric() || c == '_')
+                    && !is_identifier(word)
+            })
+            .map(String::from)
+            .collect()
+    }
+
+    /// Returns misspelled words of the text. Hyphenated words are correct, when all their parts are.
+    pub fn misspelled(&self, message: &PoMessage, msgstr: &str) -> Vec<String> {
+        let mut misspelled: Vec<String> = Vec::new();
+        for word in self.words(message, msgstr) {
+            let correct = self.check_word(&word)
+                || (word.contains('-') && word.split('-').all(|part| self.check_word(part)));
+            if !correct && !misspelled.contains(&word) {
+                misspelled.push(word);
+            }
+        }
+        misspelled
+    }
+
+    /// Returns suggestions for the misspelled word from all dictionaries.
+    pub fn suggest(&self, word: &str, max: usize) -> Vec<String> {
+        let mut suggestions: Vec<String> = Vec::new();
+        for dict in &self.dictionaries {
+            for suggestion in dict.suggest(word, max) {
+                if suggestions.len() < max && !suggestions.contains(&suggestion) {
+                    suggestions.push(suggestion);
+                }
+            }
+        }
+        suggestions
+    }
+}
+
+/// Removes accelerator markers: "_Open" -> "Open". A doubled marker is kept as is.
+fn remove_accelerators(text: &str, marker: char) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == marker {
+            match chars.peek() {
+                Some(&next) if next == marker => {
+                    result.push(c);
+                    chars.next();
+                }
+                Some(next) if next.is_alphanumeric() => {}
+                _ => result.push(c),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Returns true for identifiers in camel case, e.g. "fileName" or "GtkWindow".
+fn is_identifier(word: &str) -> bool {
+    let is_upper = word.chars().all(|c| !c.is_lowercase());
+    !is_upper && word.chars().skip(1).any(char::is_uppercase)
+}
+
+/// Reads ignore list: one word per line, "#" starts a comment.
+fn read_ignore_list(file: &str) -> Result<Vec<String>> {
+    let text = fs::read_to_string(file)
+        .with_context(|| tr!("Cannot read ignore list \"{file}\".").replace("{file}", file))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Implementation of the `spellcheck` command.
+pub fn command_spellcheck(
+    parser: &Parser,
+    options: &GlobalOptions,
+    cmdline: &[&str],
+    ctx: &mut IoContext,
+) -> Result<()> {
+    let mut dict_names: Vec<&str> = Vec::new();
+    let mut dict_dirs: Vec<&str> = Vec::new();
+    let mut ignore_files: Vec<&str> = Vec::new();
+    let mut marker = '&';
+    let mut max_suggestions = 5;
+
+    // Parse "spellcheck" command options
+    let mut cmdline = cmdline;
+    loop {
+        match cmdline[..] {
+            ["-d", name, ref tail @ ..] | ["--dict", name, ref tail @ ..] => {
+                dict_names.push(name);
+                cmdline = tail;
+            }
+            ["--dict-dir", dir, ref tail @ ..] => {
+                dict_dirs.push(dir);
+                cmdline = tail;
+            }
+            ["--ignore", file, ref tail @ ..] => {
+                ignore_files.push(file);
+                cmdline = tail;
+            }
+            ["-m", value, ref tail @ ..] | ["--marker", value, ref tail @ ..] => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_alphanumeric() => marker = c,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --marker option. Expected: single non-alphanumeric character, e.g. \"&\", \"_\", or \"~\". Actual value: \"{value}\".")
+                            .replace("{value}", value)
+                    ),
+                }
+                cmdline = tail;
+            }
+            ["--suggestions", n, ref tail @ ..] => {
+                max_suggestions = match n.parse::<usize>() {
+                    Ok(n) => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --suggestions option. Expected: number of suggestions. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
+                cmdline = tail;
+            }
+            ["-h", ..] | ["--help", ..] => {
+                help(ctx.out)?;
+                return Ok(());
+            }
+            ["--", ref tail @ ..] => {
+                cmdline = tail;
+                break;
+            }
+            [arg, ..] if arg.starts_with('-') => {
+                bail!(
+                    "{}",
+                    tr!("Unknown option: \"{}\". Use --help for list of options.")
+                        .replace("{}", arg)
+                )
+            }
+            _ => break,
+        }
+    }
+
+    if dict_names.is_empty() {
+        bail!(tr!(
+            "Expected at least one Hunspell dictionary. Use --dict NAME option, e.g. --dict uk_UA."
+        ));
+    }
+
+    if cmdline.is_empty() {
+        bail!(tr!("At least one file is expected."));
+    }
+
+    let mut dictionaries = Vec::new();
+    for name in dict_names {
+        let (aff, dic) = find_dictionary(name, &dict_dirs)?;
+        dictionaries.push(Hunspell::from_files(&aff, &dic)?);
+    }
+
+    let mut ignored = Vec::new();
+    for file in ignore_files {
+        ignored.extend(read_ignore_list(file)?);
+    }
+
+    let checker = SpellChecker::new(dictionaries, &ignored, marker)?;
+
+    let mut reporter = Reporter::new(options.output_format, "spellcheck");
+    let mut violations = 0;
+    for file in cmdline {
+        let messages = parser.parse_numbered_messages_from_file(file)?;
+        for (line, message) in &messages {
+            if message.is_header() || !message.is_translated() {
+                continue;
+            }
+
+            let mut warnings = Vec::new();
+            let mut reported: Vec<String> = Vec::new();
+            for msgstr in &message.msgstr {
+                for word in checker.misspelled(message, msgstr) {
+                    if reported.contains(&word) {
+                        continue;
+                    }
+                    let suggestions = checker.suggest(&word, max_suggestions);
+                    if suggestions.is_empty() {
+                        warnings.push(Diagnostic::warning(
+                            tr!("Misspelled word \"{word}\". No suggestions.")
+                                .replace("{word}", &word),
+                        ));
+                    } else {
+                        warnings.push(Diagnostic::warning(
+                            tr!("Misspelled word \"{word}\". Suggestions: \"{suggestions}\".")
+                                .replace("{word}", &word)
+                                .replace("{suggestions}", &suggestions.join("\", \"")),
+                        ));
+                    }
+                    reported.push(word);
+                }
+            }
+
+            if !warnings.is_empty() {
+                violations += 1;
+                reporter.report(ctx, file, *line, message, &warnings)?;
+            }
+        }
+    }
+    reporter.finish(ctx)?;
+
+    if violations > 0 {
+        bail!(
+            "{}",
+            tr!("Found {count} messages with misspelled words.")
+                .replace("{count}", &violations.to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn help(out: &mut dyn Write) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            r#"Usage: po-tools [GLOBAL_OPTIONS] spellcheck --dict DICT [OPTIONS] [--] FILE...
+
+Check spelling of translations using locally installed Hunspell dictionaries
+(`.aff` and `.dic` files). Works offline.
+
+Placeholders (e.g. "%s", "{name}"), tags, entities, URLs, accelerator markers,
+identifiers in camel case, and words with digits are skipped. Misspelled words
+are printed with suggestions. Exit status is non-zero when misspelled words are found.
+
+OPTIONS:
+
+  -d | --dict DICT      Name of the dictionary, e.g. "uk_UA", or path to the dictionary
+                        without extension. Dictionaries are searched in --dict-dir directories,
+                        in directories from DICPATH environment variable, and in standard
+                        locations, e.g. /usr/share/hunspell. Can be used multiple times:
+                        a word is correct when any dictionary accepts it.
+
+  --dict-dir DIR        Additional directory to search for dictionaries. Can be used multiple times.
+
+  --ignore FILE         Project-level list of words to accept, one per line. Lines starting
+                        with `#` are comments. Can be used multiple times.
+
+  -m | --marker CHAR    Accelerator marker to remove from words, e.g. "&" or "_". Default value: "&".
+
+  --suggestions N       Maximum number of suggestions per word. Default value: 5.
+"#
+        )
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    const AFF: &str = "SET UTF-8\nTRY оаеиіфлйнт\nSFX A Y 1\nSFX A 0 и .\n";
+    const DIC: &str = "4\nвідкрити\nфайл/A\nнемає\nпапка\n";
+
+    #[test]
+    fn test_words() -> Result<()> {
+        let checker = SpellChecker::new(vec![Hunspell::from_str(AFF, DIC)?], &[], '_')?;
+        let message = PoMessage {
+            comments: vec!["#, c-format".to_string()],
+            msgid: "_Open %s".to_string(),
+            msgstr: vec![String::new()],
+            ..Default::default()
+        };
+        assert_eq!(
+            checker.words(
+                &message,
+                "_Відкрити <b>%s</b> з https://example.com, fileName та 3D у  п'ять"
+            ),
+            vec!["Відкрити", "та", "п'ять"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_spellcheck_command() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("xx_XX.aff"), AFF)?;
+        fs::write(dir.path().join("xx_XX.dic"), DIC)?;
+
+        let ignore = NamedTempFile::new()?;
+        fs::write(ignore.path(), "# Project words\nGNOME\n")?;
+
+        let f = NamedTempFile::new()?;
+        fs::write(
+            f.path(),
+            r#"
+msgid "Open files"
+msgstr "&Відкрити файли"
+
+msgid "No GNOME folder"
+msgstr "Немає GNOME папкі"
+"#,
+        )?;
+
+        let result = command_spellcheck(
+            &parser,
+            &GlobalOptions::default(),
+            &[
+                "--dict",
+                "xx_XX",
+                "--dict-dir",
+                dir.path().to_str().unwrap(),
+                "--ignore",
+                ignore.path().to_str().unwrap(),
+                f.path().to_str().unwrap(),
+            ],
+            &mut ctx,
+        );
+        assert!(result.is_err());
+
+        let result = String::from_utf8(out)?;
+        assert_eq!(
+            result,
+            r#"# Warning: Misspelled word "папкі". Suggestions: "папка".
+msgid "No GNOME folder"
+msgstr "Немає GNOME папкі"
+
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_dictionary() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        assert!(
+            command_spellcheck(&parser, &GlobalOptions::default(), &["file.po"], &mut ctx).is_err()
+        );
+        assert!(
+            command_spellcheck(
+                &parser,
+                &GlobalOptions::default(),
+                &["--dict", "/nonexistent/xx_XX", "file.po"],
+                &mut ctx
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+}
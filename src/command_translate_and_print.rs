@@ -3,83 +3,219 @@
 //! This is the most complex command, involving fuzzy matching from TM,
 //! dictionary lookups, and interaction with an AI model.
 
+use crate::ai_backend::{AiBackend, AiBackendOptions, help_ai_backend_options};
+use crate::back_translation::{BackTranslator, DEFAULT_MIN_SIMILARITY};
+use crate::command_review_files_and_print::review_instruction;
+use crate::diagnostics::{Diagnostic, to_comments};
 use crate::dictionary::Dictionary;
+use crate::ensemble::{Arbiter, Candidate, Ensemble, alternatives_comments, vote};
 use crate::parser::{Parser, PoMessage};
-use crate::util::{AiBackend, IoContext, validate_message};
+use crate::source_context::SourceContext;
+use crate::stemmer::stemmer_by_name;
+use crate::translation_memory::{
+    TranslationMemory, TranslationMemoryOptions, help_translation_memory_options,
+};
+use crate::util::{GlobalOptions, IoContext, validate_message};
 use anyhow::{Context, Result, bail};
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
 use std::io::Write;
-use strsim::normalized_levenshtein;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+
+/// Translated message with its id in a reply to a batch request.
+static BATCH_MESSAGE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?s)<message id="(\d+)">(.*?)</message>"#).unwrap());
 
 /// Implementation of the `translate` command.
 pub fn command_translate_and_print(
     parser: &Parser,
+    options: &GlobalOptions,
     cmdline: &[&str],
     ctx: &mut IoContext,
 ) -> Result<()> {
     let mut language = "Ukrainian";
-    let mut model = "ollama:translategemma:12b";
-    let mut role = "translate-po";
-    let mut rag = "";
-    let mut tm_file = "";
+    let mut backend_options = AiBackendOptions::default();
+    let mut tm_options = TranslationMemoryOptions::default();
     let mut dictionary_files: Vec<&str> = Vec::new();
+    let mut stemmer_name = "english";
     let mut debug = false;
-    let mut ai_command_str: Option<&str> = None;
     let mut force_keyword: Option<String> = None;
     let mut prompt: Option<String> = None;
+    let mut jobs: usize = 1;
+    let mut batch: usize = 1;
+    let mut checkpoint_file: Option<&str> = None;
+    let mut on_error = OnError::KeepUntranslated;
+    let mut max_fix_attempts = 0;
+    let mut source_root: Option<&str> = None;
+    let mut context_lines: usize = 3;
+    let mut source_max_tokens: usize = 1000;
+    let mut back_translate = false;
+    let mut back_translate_model: Option<&str> = None;
+    let mut back_translate_min_similarity = DEFAULT_MIN_SIMILARITY;
+    let mut models: Vec<&str> = Vec::new();
+    let mut arbiter = Arbiter::Vote;
 
     // Parse "translate" command options
     let mut cmdline = cmdline;
     loop {
+        if let Some(rest) = backend_options.parse_option(cmdline)? {
+            cmdline = rest;
+            continue;
+        }
+        if let Some(rest) = tm_options.parse_option(cmdline)? {
+            cmdline = rest;
+            continue;
+        }
+
         match cmdline[..] {
-            ["-m", model_name, ..] | ["--model", model_name, ..] => {
-                model = model_name;
+            ["-d", dict_file, ..] | ["--dictionary", dict_file, ..] => {
+                dictionary_files.push(dict_file);
                 cmdline = &cmdline[2..];
             }
 
-            ["-R", rag_name, ..] | ["--rag", rag_name, ..] => {
-                rag = rag_name;
+            ["--stemmer", name, ..] => {
+                stemmer_name = name;
                 cmdline = &cmdline[2..];
             }
 
-            ["-M", tm_file_name, ..]
-            | ["--tm", tm_file_name, ..]
-            | ["--translation-memory", tm_file_name, ..] => {
-                tm_file = tm_file_name;
+            ["-k", k, ..] | ["--force-by-keyword", k, ..] => {
+                force_keyword = Some(k.to_string());
                 cmdline = &cmdline[2..];
             }
 
-            ["-d", dict_file, ..] | ["--dictionary", dict_file, ..] => {
-                dictionary_files.push(dict_file);
+            ["-p", p, ..] | ["--prompt", p, ..] => {
+                prompt = Some(p.to_string());
                 cmdline = &cmdline[2..];
             }
 
-            ["-k", k, ..] | ["--force-by-keyword", k, ..] => {
-                force_keyword = Some(k.to_string());
+            ["-j", n, ..] | ["--jobs", n, ..] => {
+                jobs = match n.parse::<usize>() {
+                    Ok(n) if n > 0 => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for -j | --jobs option. Expected: positive number of parallel requests. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
                 cmdline = &cmdline[2..];
             }
 
-            ["-p", p, ..] | ["--prompt", p, ..] => {
-                prompt = Some(p.to_string());
+            ["-b", n, ..] | ["--batch", n, ..] => {
+                batch = match n.parse::<usize>() {
+                    Ok(n) if n > 0 => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for -b | --batch option. Expected: positive number of messages per AI request. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
                 cmdline = &cmdline[2..];
             }
 
-            ["--debug", ..] => {
-                debug = true;
+            ["--max-fix-attempts", n, ..] => {
+                max_fix_attempts = match n.parse::<usize>() {
+                    Ok(n) => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --max-fix-attempts option. Expected: number of attempts. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
+                cmdline = &cmdline[2..];
+            }
+
+            ["--on-error", policy, ..] => {
+                on_error = match policy {
+                    "fail" => OnError::Fail,
+                    "skip" => OnError::Skip,
+                    "keep-untranslated" => OnError::KeepUntranslated,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --on-error option. Expected: skip, fail, or keep-untranslated. Actual value: \"{value}\".")
+                            .replace("{value}", policy)
+                    ),
+                };
+                cmdline = &cmdline[2..];
+            }
+
+            ["--source-root", dir, ..] => {
+                source_root = Some(dir);
+                cmdline = &cmdline[2..];
+            }
+
+            ["--context-lines", n, ..] => {
+                context_lines = match n.parse::<usize>() {
+                    Ok(n) => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --context-lines option. Expected: number of lines. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
+                cmdline = &cmdline[2..];
+            }
+
+            ["--source-max-tokens", n, ..] => {
+                source_max_tokens = match n.parse::<usize>() {
+                    Ok(n) => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --source-max-tokens option. Expected: number of tokens. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
+                cmdline = &cmdline[2..];
+            }
+
+            ["--models", list, ..] => {
+                models = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty())
+                    .collect();
+                cmdline = &cmdline[2..];
+            }
+
+            ["--arbiter", name, ..] => {
+                arbiter = Arbiter::from_name(name)?;
+                cmdline = &cmdline[2..];
+            }
+
+            ["--back-translate", ..] => {
+                back_translate = true;
                 cmdline = &cmdline[1..];
             }
 
-            ["-c", cmd, ..] | ["--ai-command", cmd, ..] => {
-                ai_command_str = Some(cmd);
+            ["--back-translate-model", model, ..] => {
+                back_translate = true;
+                back_translate_model = Some(model);
+                cmdline = &cmdline[2..];
+            }
+
+            ["--back-translate-threshold", n, ..] => {
+                back_translate_min_similarity = match n.trim_end_matches('%').parse::<f64>() {
+                    Ok(n) if (0.0..=100.0).contains(&n) => n / 100.0,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --back-translate-threshold option. Expected: percent between 0 and 100. Actual value: \"{value}\".")
+                            .replace("{value}", n)
+                    ),
+                };
                 cmdline = &cmdline[2..];
             }
 
-            ["-r", role_name, ..] | ["--role", role_name, ..] => {
-                role = role_name;
+            ["--checkpoint", file, ..] => {
+                checkpoint_file = Some(file);
                 cmdline = &cmdline[2..];
             }
 
+            ["--debug", ..] => {
+                debug = true;
+                cmdline = &cmdline[1..];
+            }
+
             ["-l", lang_name, ..] | ["--lang", lang_name, ..] | ["--language", lang_name, ..] => {
                 language = lang_name;
                 cmdline = &cmdline[2..];
@@ -110,34 +246,76 @@ pub fn command_translate_and_print(
         ));
     }
 
-    let backend = if let Some(cmd) = ai_command_str {
-        AiBackend::from_command_line(cmd)
+    if batch > 1 && !models.is_empty() {
+        bail!(tr!(
+            "Options --batch and --models cannot be used together, because each model translates messages one by one."
+        ));
+    }
+
+    let backend = backend_options.build()?;
+
+    // Back-translation uses a different model, when given
+    let back_translator = if back_translate {
+        let mut options = backend_options.clone();
+        if let Some(model) = back_translate_model {
+            options.model = Some(model);
+        }
+        Some(BackTranslator::new(
+            options.build()?.with_shared_rate_limit(&backend),
+            back_translate_min_similarity,
+        ))
     } else {
-        AiBackend::with_aichat_defaults(model, role, if rag.is_empty() { None } else { Some(rag) })
+        None
     };
 
-    let tm_messages = if !tm_file.is_empty() {
-        let msgs = parser.parse_messages_from_file(tm_file).with_context(|| {
-            tr!("Cannot open file \"{file}\" with translation memory.").replace("{file}", tm_file)
-        })?;
-        writeln!(
-            ctx.err,
-            "{}: {}",
-            tr!("INFO"),
-            tr!("Loaded {count} messages from \"{file}\" file with translation memory.")
-                .replace("{count}", &msgs.len().to_string())
-                .replace("{file}", tm_file)
-        )?;
-        msgs
+    // Each model of the ensemble gets its own backend with the same options,
+    // but the rate limit applies to all requests of the run
+    let ensemble = if models.is_empty() {
+        None
     } else {
-        Vec::new()
+        let backends = models
+            .iter()
+            .map(|model| {
+                let mut options = backend_options.clone();
+                options.model = Some(model);
+                Ok((
+                    model.to_string(),
+                    options.build()?.with_shared_rate_limit(&backend),
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Some(Ensemble::new(backends, arbiter))
+    };
+
+    let source_context =
+        source_root.map(|dir| SourceContext::new(dir, context_lines, source_max_tokens));
+
+    let checkpoint = match checkpoint_file {
+        Some(file) => {
+            let checkpoint = Checkpoint::open(parser, file, ctx.err)?;
+            writeln!(
+                ctx.err,
+                "{}: {}",
+                tr!("INFO"),
+                tr!("Loaded {count} translated messages from \"{file}\" checkpoint file.")
+                    .replace("{count}", &checkpoint.done.len().to_string())
+                    .replace("{file}", file)
+            )?;
+            Some(checkpoint)
+        }
+        None => None,
     };
 
+    let translation_memory = tm_options.load(parser, ctx)?;
+
+    let stemmer = stemmer_by_name(stemmer_name)?;
     let mut dictionaries = Vec::new();
     for dict_file in dictionary_files {
-        let dict = Dictionary::from_file(dict_file).with_context(|| {
-            tr!("Cannot open dictionary file \"{file}\".").replace("{file}", dict_file)
-        })?;
+        let dict = Dictionary::from_file(dict_file)
+            .with_context(|| {
+                tr!("Cannot open dictionary file \"{file}\".").replace("{file}", dict_file)
+            })?
+            .with_stemmer(stemmer.clone());
         writeln!(
             ctx.err,
             "{}: {}",
@@ -175,49 +353,82 @@ pub fn command_translate_and_print(
             backend: backend.clone(),
             language,
             number_of_plural_cases: parser.number_of_plural_cases,
-            tm_messages: &tm_messages,
+            max_length_ratio: options.max_length_ratio,
+            translation_memory: &translation_memory,
             dictionaries: &dictionaries,
             debug,
             copy_comments: true,
             keyword_matcher: force_matcher,
             prompt: prompt.clone(),
+            jobs,
+            batch,
+            checkpoint: checkpoint.as_ref(),
+            source_context: source_context.as_ref(),
+            on_error,
+            max_fix_attempts,
+            back_translator: back_translator.as_ref(),
+            ensemble: ensemble.as_ref(),
+            failures: Mutex::new(Vec::new()),
         };
         translate_and_print(ctx, &config, &messages)?;
-    }
-
-    Ok(())
-}
 
-fn find_fuzzy_matches<'a>(message: &PoMessage, tm_messages: &'a [PoMessage]) -> Vec<&'a PoMessage> {
-    if message.is_header() {
-        return Vec::new();
+        let failures = config
+            .failures
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner());
+        if !failures.is_empty() {
+            writeln!(
+                ctx.err,
+                "{}: {}",
+                tr!("WARNING"),
+                tr!("Failed to translate {count} messages in file {file}:")
+                    .replace("{count}", &failures.len().to_string())
+                    .replace("{file}", file)
+            )?;
+            for failure in failures {
+                writeln!(ctx.err, "{failure}")?;
+            }
+        }
     }
-    let msgid = &message.msgid;
-
-    let mut matches: Vec<(f64, &PoMessage)> = tm_messages
-        .iter()
-        .filter(|tm_msg| !tm_msg.is_header())
-        .map(|tm_msg| (normalized_levenshtein(msgid, &tm_msg.msgid), tm_msg))
-        .collect();
-
-    // Sort by score descending
-    matches.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
 
-    // Take top 5
-    // TODO: make number of matches configurable
-    matches.into_iter().take(5).map(|(_, msg)| msg).collect()
+    Ok(())
 }
 
 struct TranslateConfig<'a> {
     backend: AiBackend,
     language: &'a str,
     number_of_plural_cases: Option<usize>,
-    tm_messages: &'a [PoMessage],
+    max_length_ratio: Option<f64>,
+    translation_memory: &'a TranslationMemory,
     dictionaries: &'a [Dictionary],
     debug: bool,
     copy_comments: bool,
     keyword_matcher: Option<Regex>,
     prompt: Option<String>,
+    jobs: usize,
+    batch: usize,
+    checkpoint: Option<&'a Checkpoint>,
+    source_context: Option<&'a SourceContext>,
+    on_error: OnError,
+    /// How many times to ask AI model to fix errors in translation.
+    max_fix_attempts: usize,
+    /// Checks translations by translating them back to English.
+    back_translator: Option<&'a BackTranslator>,
+    /// Translates each message with several models and chooses the best translation.
+    ensemble: Option<&'a Ensemble>,
+    /// Messages which failed to translate, for the summary at the end.
+    failures: Mutex<Vec<String>>,
+}
+
+/// What to do with a message when AI request fails even after retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnError {
+    /// Stop translation with error.
+    Fail,
+    /// Omit message from output.
+    Skip,
+    /// Print message as is, without translation.
+    KeepUntranslated,
 }
 
 fn translate_and_print(
@@ -225,64 +436,365 @@ fn translate_and_print(
     config: &TranslateConfig,
     messages: &[PoMessage],
 ) -> Result<()> {
-    for message in messages {
-        let should_force = config
-            .keyword_matcher
-            .as_ref()
-            .map(|re| re.is_match(&message.msgid))
-            .unwrap_or(false);
+    let tasks = split_into_tasks(config, messages);
 
-        if message.is_header() || (message.is_translated() && !message.is_fuzzy() && !should_force)
-        {
-            // Just copy headers and translated messages
-            writeln!(ctx.out, "{message}")?;
+    if config.jobs > 1 {
+        return translate_and_print_in_parallel(ctx, config, &tasks);
+    }
+
+    for task in tasks {
+        translate_task(ctx, config, task)?;
+    }
+
+    Ok(())
+}
+
+/// Returns true if message must be translated, i.e. it's untranslated, fuzzy, or forced by keyword.
+fn wants_translation(config: &TranslateConfig, message: &PoMessage) -> bool {
+    let should_force = config
+        .keyword_matcher
+        .as_ref()
+        .map(|re| re.is_match(&message.msgid))
+        .unwrap_or(false);
+
+    !message.is_header() && (!message.is_translated() || message.is_fuzzy() || should_force)
+}
+
+/// Returns translation of the message finished in a previous run, if any.
+fn checkpointed<'c>(config: &TranslateConfig<'c>, message: &PoMessage) -> Option<&'c PoMessage> {
+    if wants_translation(config, message) {
+        config.checkpoint?.get(message)
+    } else {
+        None
+    }
+}
+
+/// Returns true if message must be sent to the AI backend.
+fn needs_translation(config: &TranslateConfig, message: &PoMessage) -> bool {
+    wants_translation(config, message) && checkpointed(config, message).is_none()
+}
+
+/// Prints message, which is not sent to the AI backend.
+fn print_without_translation(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    message: &PoMessage,
+) -> Result<()> {
+    match checkpointed(config, message) {
+        // Message was translated in a previous run
+        Some(translated) => writeln!(ctx.out, "{translated}")?,
+        // Just copy headers and translated messages
+        None => writeln!(ctx.out, "{message}")?,
+    }
+    Ok(())
+}
+
+/// Translations finished so far, stored in a checkpoint file.
+///
+/// Each translation is appended to the file as soon as it's produced, so
+/// an interrupted run can be resumed without translating same messages again.
+struct Checkpoint {
+    done: HashMap<PoMessage, PoMessage>,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    /// Opens checkpoint file, loading translations from it, if file exists.
+    ///
+    /// Each entry ends with an empty line, so an entry without it was torn by an
+    /// interrupted run. Such entry is dropped with a warning and cut from the file.
+    fn open(parser: &Parser, path: &str, err: &mut dyn Write) -> Result<Self> {
+        let mut done = HashMap::new();
+        let mut valid_length = None;
+        if Path::new(path).exists() {
+            let mut data = std::fs::read(path).with_context(|| {
+                tr!("Cannot read checkpoint file \"{file}\".").replace("{file}", path)
+            })?;
+            if !data.is_empty() && !data.ends_with(b"\n\n") {
+                let length = data
+                    .windows(2)
+                    .rposition(|w| w == b"\n\n")
+                    .map_or(0, |i| i + 2);
+                writeln!(
+                    err,
+                    "{}: {}",
+                    tr!("WARNING"),
+                    tr!("Last entry of \"{file}\" checkpoint file is incomplete, dropping it.")
+                        .replace("{file}", path)
+                )?;
+                data.truncate(length);
+                valid_length = Some(length);
+            }
+
+            let messages = parser
+                .parse_messages_from_stream(&data[..])
+                .with_context(|| {
+                    tr!("Cannot read checkpoint file \"{file}\".").replace("{file}", path)
+                })?;
+            for message in messages {
+                if !message.is_header() && !message.is_nothing() {
+                    done.insert(Self::key(&message), message);
+                }
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| {
+                tr!("Cannot open checkpoint file \"{file}\" for writing.").replace("{file}", path)
+            })?;
+        if let Some(length) = valid_length {
+            file.set_len(length as u64).with_context(|| {
+                tr!("Cannot open checkpoint file \"{file}\" for writing.").replace("{file}", path)
+            })?;
+        }
+
+        Ok(Self {
+            done,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Identity of message, without comments.
+    fn key(message: &PoMessage) -> PoMessage {
+        PoMessage {
+            comments: Vec::new(),
+            ..message.to_key()
+        }
+    }
+
+    fn get(&self, message: &PoMessage) -> Option<&PoMessage> {
+        self.done.get(&Self::key(message))
+    }
+
+    /// Appends translated message to the checkpoint file.
+    fn append(&self, text: &str) -> Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(file, "{text}")?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Splits messages into consecutive tasks, each containing at most `config.batch`
+/// messages to translate. Messages which are just copied stay in the task of the
+/// preceding message to translate, so output of tasks can be concatenated in order.
+fn split_into_tasks<'m>(
+    config: &TranslateConfig,
+    messages: &'m [PoMessage],
+) -> Vec<&'m [PoMessage]> {
+    let batch = config.batch.max(1);
+    let mut tasks = Vec::new();
+    let mut start = 0;
+    let mut count = 0;
+
+    for (i, message) in messages.iter().enumerate() {
+        if needs_translation(config, message) {
+            if (count == 0 || count == batch) && start < i {
+                tasks.push(&messages[start..i]);
+                start = i;
+                count = 0;
+            }
+            count += 1;
+        }
+    }
+
+    if start < messages.len() {
+        tasks.push(&messages[start..]);
+    }
+
+    tasks
+}
+
+/// Translates and prints all messages of a single task.
+fn translate_task(ctx: &mut IoContext, config: &TranslateConfig, task: &[PoMessage]) -> Result<()> {
+    let to_translate = task
+        .iter()
+        .filter(|message| needs_translation(config, message))
+        .count();
+
+    // Ensemble translates messages one by one, to compare translations of each message
+    if to_translate > 1 && config.ensemble.is_none() {
+        return translate_batch(ctx, config, task);
+    }
+
+    for message in task {
+        if needs_translation(config, message) {
+            match config.ensemble {
+                Some(ensemble) => translate_with_ensemble(ctx, config, ensemble, message)?,
+                None => translate_single_message(ctx, config, message)?,
+            }
         } else {
-            translate_single_message(ctx, config, message)?;
+            print_without_translation(ctx, config, message)?;
         }
     }
 
     Ok(())
 }
 
-fn translate_single_message(
+/// Output and diagnostics produced by translation of a task.
+struct TranslatedOutput {
+    out: Vec<u8>,
+    err: Vec<u8>,
+}
+
+/// Translates a task, capturing its output into buffers instead of printing it.
+fn translate_to_buffers(config: &TranslateConfig, task: &[PoMessage]) -> Result<TranslatedOutput> {
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+    let mut ctx = IoContext {
+        out: &mut out,
+        err: &mut err,
+    };
+    translate_task(&mut ctx, config, task)?;
+    Ok(TranslatedOutput { out, err })
+}
+
+/// Translates tasks using a pool of `config.jobs` workers.
+///
+/// Workers take tasks in order from a shared counter and send results back
+/// to this thread, which prints them in the original order. When a worker fails,
+/// remaining tasks are not dispatched, results of already finished tasks
+/// before the failed one are printed, and the first error is returned.
+fn translate_and_print_in_parallel(
     ctx: &mut IoContext,
     config: &TranslateConfig,
-    message: &PoMessage,
+    tasks: &[&[PoMessage]],
 ) -> Result<()> {
-    let fuzzy_matches = find_fuzzy_matches(message, config.tm_messages);
-    let fuzzy_match_text = if !fuzzy_matches.is_empty() {
-        let mut text = format!(
-            "<context>\n{}:\n",
-            tr!("# Fuzzy matches from translation memory")
-        );
-        for m in fuzzy_matches {
-            text.push_str(&format!("{}\n", m));
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    let next_index = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel::<(usize, Result<TranslatedOutput>)>();
+
+    std::thread::scope(|s| -> Result<()> {
+        for _ in 0..config.jobs.min(tasks.len()) {
+            let sender = sender.clone();
+            let next_index = &next_index;
+            let stop = &stop;
+            s.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next_index.fetch_add(1, Ordering::Relaxed);
+                    let Some(task) = tasks.get(i) else {
+                        break;
+                    };
+
+                    let result = translate_to_buffers(config, task);
+
+                    if result.is_err() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    if sender.send((i, result)).is_err() {
+                        break;
+                    }
+                }
+            });
         }
-        text.push_str("</context>");
+        // Receiver loop ends when all workers drop their senders
+        drop(sender);
+
+        let mut pending: BTreeMap<usize, TranslatedOutput> = BTreeMap::new();
+        let mut next_to_print = 0;
+        let mut first_error: Option<(usize, anyhow::Error)> = None;
+
+        for (i, result) in receiver {
+            match result {
+                Ok(output) => {
+                    pending.insert(i, output);
+                }
+                Err(e) => {
+                    stop.store(true, Ordering::Relaxed);
+                    if first_error.as_ref().is_none_or(|(j, _)| i < *j) {
+                        first_error = Some((i, e));
+                    }
+                }
+            }
+
+            while let Some(output) = pending.remove(&next_to_print) {
+                ctx.err.write_all(&output.err)?;
+                ctx.out.write_all(&output.out)?;
+                next_to_print += 1;
+            }
+        }
+
+        match first_error {
+            Some((i, e)) => {
+                // Number of the first message to translate in the failed task
+                let index = tasks[..i].iter().map(|task| task.len()).sum::<usize>()
+                    + tasks[i]
+                        .iter()
+                        .position(|message| needs_translation(config, message))
+                        .unwrap_or(0)
+                    + 1;
+                Err(e.context(
+                    tr!("Translation of message #{index} failed.")
+                        .replace("{index}", &index.to_string()),
+                ))
+            }
+            None => Ok(()),
+        }
+    })
+}
+
+/// Builds `<context>` block with fuzzy matches from translation memory for given messages.
+fn fuzzy_match_context(config: &TranslateConfig, messages: &[&PoMessage]) -> String {
+    let mut text = String::new();
+    for message in messages {
+        for m in config.translation_memory.find_matches(message) {
+            text.push_str(&format!(
+                "{}\n{}\n",
+                tr!("# Similarity: {percent}%")
+                    .replace("{percent}", &format!("{:.0}", m.score * 100.0)),
+                m.message
+            ));
+        }
+    }
+
+    if text.is_empty() {
         text
     } else {
-        "".to_string()
-    };
+        format!(
+            "<context>\n{}:\n{text}</context>",
+            tr!("# Fuzzy matches from translation memory")
+        )
+    }
+}
+
+/// Builds `<source>` block with source code around references of given messages.
+fn source_code_context(config: &TranslateConfig, messages: &[&PoMessage]) -> String {
+    match config.source_context {
+        Some(source_context) => source_context.context_for(messages),
+        None => String::new(),
+    }
+}
 
-    // Find dictionary matches
+/// Builds `<dictionary>` block with dictionary terms found in given messages.
+fn dictionary_context(config: &TranslateConfig, messages: &[&PoMessage]) -> String {
     let mut dict_context = String::new();
     let mut seen_keys = HashSet::new();
 
     for dict in config.dictionaries {
-        for entry in dict.find_matches(&message.msgid) {
-            if seen_keys.insert(&entry.key) {
-                dict_context.push_str(&format!("- {} - {}\n", entry.key, entry.translation));
+        for message in messages {
+            for entry in dict.find_matches(&message.msgid) {
+                if seen_keys.insert(&entry.key) {
+                    dict_context.push_str(&format!("- {}\n", entry.describe()));
+                }
             }
         }
     }
 
-    if !dict_context.is_empty() {
-        dict_context = format!("<dictionary>\n{dict_context}</dictionary>\n");
+    if dict_context.is_empty() {
+        dict_context
+    } else {
+        format!("<dictionary>\n{dict_context}</dictionary>\n")
     }
+}
 
-    let is_plural = message.is_plural();
-    let example = if is_plural {
-        r#"
+const PLURAL_EXAMPLE: &str = r#"
 <example>
 msgid "%s new patch,"
 msgid_plural "%s new patches,"
@@ -290,46 +802,26 @@ msgstr[0] "%s нова латка,"
 msgstr[1] "%s нові латки,"
 msgstr[2] "%s нових латок,"
 </example>
-"#
-    } else {
-        ""
-    };
-
-    let prompt_text = if let Some(p) = &config.prompt {
-        format!("IMPORTANT: {p}\n")
-    } else {
-        "".to_string()
-    };
-
-    // Translation template
-    let message_text = format!(
-        r#"{dict_context}
-{fuzzy_match_text}
-<instruction>
-IMPORTANT: Translate text in <message></message> tag only and _nothing else_.
-IMPORTANT: Answers must be VALID Gettext PO messages. Msgid field must be verbatim copy of original msgid, while msgstr must be {language} translation.
-IMPORTANT: Don't translate <context> and <dictionary>. They are just for reference.
-IMPORTANT: Prefer translations proposed by dictionary.
-You are a professional English (en_US) to {language} translator. Your goal is to accurately convey the meaning and nuances of the original English text while adhering to {language} grammar, vocabulary, and cultural sensitivities.
-Produce only the {language} translation, without any additional explanations or commentary. Please translate the following English text in <message></message> into {language}.
-{prompt_text}
-</instruction>
-
-<message>
-{message}
-</message>
-{example}
-"#,
-        language = config.language
-    );
+"#;
 
+/// Sends instruction and message to the AI backend, printing both prompt and reply in debug mode.
+fn execute_prompt(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    backend: &AiBackend,
+    instruction: &str,
+    message_text: &str,
+) -> Result<String> {
     if config.debug {
         writeln!(
             ctx.err,
             "----{}-----------------------------------------------------------",
             tr!("Message to translator")
         )?;
-        writeln!(ctx.err, "{message_text}")?;
+        writeln!(
+            ctx.err,
+            "<instruction>\n{instruction}\n</instruction>\n{message_text}"
+        )?;
         writeln!(
             ctx.err,
             "----{}--------------------------------------------------------------",
@@ -337,8 +829,7 @@ Produce only the {language} translation, without any additional explanations or
         )?;
     }
 
-    // Translate
-    let new_message_text = config.backend.execute(&message_text)?;
+    let reply = backend.execute_chat(ctx.err, instruction, message_text)?;
 
     if config.debug {
         writeln!(
@@ -346,7 +837,7 @@ Produce only the {language} translation, without any additional explanations or
             "----{}-----------------------------------------------------------",
             tr!("Reply from translator")
         )?;
-        writeln!(ctx.err, "{new_message_text}")?;
+        writeln!(ctx.err, "{reply}")?;
         writeln!(
             ctx.err,
             "----{}----------------------------------------------------------------",
@@ -354,32 +845,22 @@ Produce only the {language} translation, without any additional explanations or
         )?;
     }
 
-    let new_message_text_cleaned = if let Some(start) = new_message_text.rfind("</think>") {
-        // Skip thinking output from reasoning models
-        let tag_len = "</think>".len();
-        &new_message_text[(start + tag_len)..]
-    } else {
-        &new_message_text[..]
-    };
+    Ok(reply)
+}
 
-    let new_message_text_slice = if let Some(end) = new_message_text_cleaned.rfind("</message>") {
-        // Extract text between <message> and </message>, if they are present
-        let tag_open = "<message>";
-        if let Some(start) = new_message_text_cleaned[..end].rfind(tag_open) {
-            &new_message_text_cleaned[(start + tag_open.len())..end]
-        } else {
-            // Found </message> but no <message> before it. Fallback to whole string or msgid search.
-            new_message_text_cleaned
-        }
-    } else if let Some(start) = new_message_text_cleaned.rfind("msgid ") {
-        // Unwrapped message found
-        &new_message_text_cleaned[start..]
+/// Skips thinking output from reasoning models.
+pub fn strip_thinking(reply: &str) -> &str {
+    if let Some(start) = reply.rfind("</think>") {
+        let tag_len = "</think>".len();
+        &reply[(start + tag_len)..]
     } else {
-        // Message not found
-        new_message_text_cleaned
-    };
+        reply
+    }
+}
 
-    let parser = Parser {
+/// Creates parser for messages returned by the AI model.
+fn reply_parser(config: &TranslateConfig, is_plural: bool) -> Parser {
+    Parser {
         number_of_plural_cases: if is_plural {
             Some(config.number_of_plural_cases.unwrap_or(2))
         } else {
@@ -387,62 +868,576 @@ Produce only the {language} translation, without any additional explanations or
         },
         ignore_garbage_after_msgstr: true,
         strip_comments: true,
-    };
+    }
+}
 
-    match parser.parse_message_from_str(new_message_text_slice) {
-        Ok(mut new_message) => {
-            if config.copy_comments {
-                new_message.comments = message.comments.clone();
-            }
+/// Translates several messages with a single AI request.
+///
+/// Each message is sent with a numeric id and each translation is expected
+/// back in `<message id="N">` tag. Translations which are missing, cannot be
+/// parsed, or have a wrong key are retried individually.
+fn translate_batch(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    task: &[PoMessage],
+) -> Result<()> {
+    let to_translate: Vec<&PoMessage> = task
+        .iter()
+        .filter(|message| needs_translation(config, message))
+        .collect();
 
-            let actual_key = message.to_key();
-            let result_key = new_message.to_key();
+    let dict_context = dictionary_context(config, &to_translate);
+    let source_context = source_code_context(config, &to_translate);
+    let fuzzy_match_text = fuzzy_match_context(config, &to_translate);
 
-            if actual_key == result_key {
-                let errors = validate_message(&new_message);
-                writeln!(
-                    ctx.out,
-                    "{}:\n{errors}#, fuzzy\n{new_message}",
-                    tr!("# Translated message")
-                )?;
-            } else {
-                writeln!(
-                    ctx.err,
-                    "{}. {} = \"{}\"\n# {}:\n=====\n{new_message_text_slice}\n=====",
-                    tr!(
-                        "# WARNING: Wrong msgid field when trying to translate. Replacing wrong ID with correct id"
-                    ),
-                    tr!("Actual key"),
-                    actual_key,
-                    tr!("Raw translation text")
-                )?;
-                let fixed_message = new_message.with_key(&actual_key);
-                let errors = validate_message(&fixed_message);
-                writeln!(
-                    ctx.out,
-                    "{}:\n{errors}#, fuzzy\n{fixed_message}",
-                    tr!("# Translated message (WARNING: wrong id after translation)")
-                )?;
-            }
-        }
+    let example = if to_translate.iter().any(|m| m.is_plural()) {
+        PLURAL_EXAMPLE
+    } else {
+        ""
+    };
 
-        Err(e) => {
-            writeln!(
+    let prompt_text = if let Some(p) = &config.prompt {
+        format!("IMPORTANT: {p}\n")
+    } else {
+        "".to_string()
+    };
+
+    let mut messages_text = String::new();
+    for (id, message) in to_translate.iter().enumerate() {
+        messages_text.push_str(&format!(
+            "<message id=\"{}\">\n{message}</message>\n",
+            id + 1
+        ));
+    }
+
+    // Translation template
+    let instruction = format!(
+        r#"IMPORTANT: Translate text in <message id="N"></message> tags only and _nothing else_.
+IMPORTANT: Answers must be VALID Gettext PO messages. Msgid field must be verbatim copy of original msgid, while msgstr must be {language} translation.
+IMPORTANT: Wrap each translated message in <message id="N"></message> tag with the same id as the original message. Translate all messages.
+IMPORTANT: Don't translate <source>, <context> and <dictionary>. They are just for reference.
+IMPORTANT: Prefer translations proposed by dictionary.
+You are a professional English (en_US) to {language} translator. Your goal is to accurately convey the meaning and nuances of the original English text while adhering to {language} grammar, vocabulary, and cultural sensitivities.
+Produce only the {language} translations, without any additional explanations or commentary. Please translate the following English texts in <message id="N"></message> tags into {language}.
+{prompt_text}"#,
+        language = config.language
+    );
+    let message_text = format!(
+        r#"{source_context}{dict_context}
+{fuzzy_match_text}
+
+{messages_text}{example}
+"#
+    );
+
+    let reply = match execute_prompt(ctx, config, &config.backend, &instruction, &message_text) {
+        Ok(reply) => reply,
+        Err(e) => {
+            for message in task {
+                if needs_translation(config, message) {
+                    handle_failed_request(ctx, config, message, &e)?;
+                } else {
+                    print_without_translation(ctx, config, message)?;
+                }
+            }
+            return Ok(());
+        }
+    };
+    let reply = strip_thinking(&reply);
+
+    // Last reply for each id wins, because models sometimes repeat the input first
+    let mut replies: Vec<Option<&str>> = vec![None; to_translate.len()];
+    for cap in BATCH_MESSAGE_RE.captures_iter(reply) {
+        if let Ok(id) = cap[1].parse::<usize>()
+            && (1..=to_translate.len()).contains(&id)
+        {
+            replies[id - 1] = cap.get(2).map(|m| m.as_str());
+        }
+    }
+
+    let mut replies = replies.into_iter();
+    for message in task {
+        if !needs_translation(config, message) {
+            print_without_translation(ctx, config, message)?;
+            continue;
+        }
+
+        let parsed = replies.next().flatten().and_then(|text| {
+            reply_parser(config, message.is_plural())
+                .parse_message_from_str(text)
+                .ok()
+        });
+
+        match parsed {
+            Some(mut new_message) => {
+                if config.copy_comments {
+                    new_message.comments = message.comments.clone();
+                }
+
+                if new_message.to_key() == message.to_key() {
+                    if config.max_fix_attempts == 0
+                        || validate_message(&new_message, config.max_length_ratio).is_empty()
+                    {
+                        print_translated_message(
+                            ctx,
+                            config,
+                            tr!("# Translated message"),
+                            &new_message,
+                        )?;
+                        continue;
+                    }
+
+                    writeln!(
+                        ctx.err,
+                        "{}: \"{}\"",
+                        tr!(
+                            "# WARNING: Batch translation has errors, retrying message individually"
+                        ),
+                        message.msgid
+                    )?;
+                    translate_single_message(ctx, config, message)?;
+                    continue;
+                }
+
+                writeln!(
+                    ctx.err,
+                    "{}: \"{}\"",
+                    tr!(
+                        "# WARNING: Wrong msgid field in batch translation, retrying message individually"
+                    ),
+                    message.msgid
+                )?;
+            }
+            None => {
+                writeln!(
+                    ctx.err,
+                    "{}: \"{}\"",
+                    tr!(
+                        "# WARNING: Translation is missing in batch reply, retrying message individually"
+                    ),
+                    message.msgid
+                )?;
+            }
+        }
+
+        translate_single_message(ctx, config, message)?;
+    }
+
+    Ok(())
+}
+
+/// Handles failure of AI request for the message according to `--on-error` policy.
+fn handle_failed_request(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    message: &PoMessage,
+    error: &anyhow::Error,
+) -> Result<()> {
+    if config.on_error == OnError::Fail {
+        bail!("{error:#}");
+    }
+
+    writeln!(
+        ctx.err,
+        "{}: {error:#}\n{message}",
+        tr!("# ERROR: AI request failed for message")
+    )?;
+    config
+        .failures
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(format!("{}: {error:#}", message.to_key()));
+
+    if config.on_error == OnError::KeepUntranslated {
+        writeln!(
+            ctx.out,
+            "{}:\n{message}",
+            tr!("# UNTranslated message (AI request failed)")
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints translated message marked as fuzzy, along with validation errors,
+/// and stores it in the checkpoint file, if any.
+fn print_translated_message(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    title: &str,
+    new_message: &PoMessage,
+) -> Result<()> {
+    let mut errors = validate_message(new_message, config.max_length_ratio);
+    if let Some(back_translator) = config.back_translator {
+        match back_translator.check(ctx.err, config.language, new_message) {
+            Ok(Some(warning)) => errors.push(warning),
+            Ok(None) => {}
+            Err(e) => writeln!(
+                ctx.err,
+                "{}: {e:#}",
+                tr!("# WARNING: AI request failed when trying to back-translate message")
+            )?,
+        }
+    }
+    let text = format!("{title}:\n{}#, fuzzy\n{new_message}", to_comments(&errors));
+    writeln!(ctx.out, "{text}")?;
+
+    if let Some(checkpoint) = config.checkpoint {
+        checkpoint.append(&text)?;
+    }
+    Ok(())
+}
+
+/// Builds instruction and message text to translate a single message.
+fn single_message_prompt(config: &TranslateConfig, message: &PoMessage) -> (String, String) {
+    let fuzzy_match_text = fuzzy_match_context(config, &[message]);
+    let dict_context = dictionary_context(config, &[message]);
+    let source_context = source_code_context(config, &[message]);
+
+    let is_plural = message.is_plural();
+    let example = if is_plural { PLURAL_EXAMPLE } else { "" };
+
+    let prompt_text = if let Some(p) = &config.prompt {
+        format!("IMPORTANT: {p}\n")
+    } else {
+        "".to_string()
+    };
+
+    // Translation template
+    let instruction = format!(
+        r#"IMPORTANT: Translate text in <message></message> tag only and _nothing else_.
+IMPORTANT: Answers must be VALID Gettext PO messages. Msgid field must be verbatim copy of original msgid, while msgstr must be {language} translation.
+IMPORTANT: Don't translate <source>, <context> and <dictionary>. They are just for reference.
+IMPORTANT: Prefer translations proposed by dictionary.
+You are a professional English (en_US) to {language} translator. Your goal is to accurately convey the meaning and nuances of the original English text while adhering to {language} grammar, vocabulary, and cultural sensitivities.
+Produce only the {language} translation, without any additional explanations or commentary. Please translate the following English text in <message></message> into {language}.
+{prompt_text}"#,
+        language = config.language
+    );
+    let message_text = format!(
+        r#"{source_context}{dict_context}
+{fuzzy_match_text}
+
+<message>
+{message}
+</message>
+{example}
+"#
+    );
+
+    (instruction, message_text)
+}
+
+fn translate_single_message(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    message: &PoMessage,
+) -> Result<()> {
+    let (instruction, message_text) = single_message_prompt(config, message);
+    let parser = reply_parser(config, message.is_plural());
+    let actual_key = message.to_key();
+
+    let mut best: Option<(usize, PoMessage)> = None;
+    let mut request_text = message_text.clone();
+    for attempt in 0..=config.max_fix_attempts {
+        // Translate
+        let new_message_text =
+            match execute_prompt(ctx, config, &config.backend, &instruction, &request_text) {
+                Ok(text) => text,
+                Err(e) if best.is_none() => return handle_failed_request(ctx, config, message, &e),
+                Err(e) => {
+                    writeln!(
+                        ctx.err,
+                        "{}: {e:#}",
+                        tr!("# WARNING: AI request failed when trying to fix translation")
+                    )?;
+                    break;
+                }
+            };
+        let new_message_text_slice = extract_message_text(strip_thinking(&new_message_text));
+
+        let mut new_message = match parser.parse_message_from_str(new_message_text_slice) {
+            Ok(new_message) => new_message,
+            Err(e) => {
+                writeln!(
+                    ctx.err,
+                    "{}: {:#}:\n{message}\n# {}:\n=====\n{new_message_text_slice}\n=====",
+                    tr!("# ERROR: Cannot parse translation of message"),
+                    e,
+                    tr!("# Raw translation text")
+                )?;
+                if attempt < config.max_fix_attempts {
+                    request_text = fix_request_text(
+                        &message_text,
+                        new_message_text_slice,
+                        &tr!("# Error: Cannot parse translation: {error}\n")
+                            .replace("{error}", &format!("{e:#}")),
+                    );
+                }
+                continue;
+            }
+        };
+
+        if config.copy_comments {
+            new_message.comments = message.comments.clone();
+        }
+
+        let mut errors = Vec::new();
+        if new_message.to_key() != actual_key {
+            writeln!(
+                ctx.err,
+                "{}. {} = \"{}\"\n# {}:\n=====\n{new_message_text_slice}\n=====",
+                tr!("# WARNING: Wrong msgid field when trying to translate"),
+                tr!("Actual key"),
+                actual_key,
+                tr!("Raw translation text")
+            )?;
+            errors.push(Diagnostic::error(tr!(
+                "Msgid field is changed. Copy msgid field verbatim from the original message."
+            )));
+        }
+        errors.extend(validate_message(
+            &new_message.with_key(&actual_key),
+            config.max_length_ratio,
+        ));
+
+        if errors.is_empty() {
+            return print_translated_message(
+                ctx,
+                config,
+                tr!("# Translated message"),
+                &new_message,
+            );
+        }
+
+        // Keep the attempt with the least number of problems
+        if best.as_ref().is_none_or(|(p, _)| errors.len() < *p) {
+            best = Some((errors.len(), new_message.clone()));
+        }
+
+        if attempt < config.max_fix_attempts {
+            let errors = to_comments(&errors);
+            writeln!(
                 ctx.err,
-                "{}: {:#}:\n{message}\n# {}:\n=====\n{new_message_text_slice}\n=====",
-                tr!("# ERROR: Cannot parse translation of message"),
-                e,
-                tr!("# Raw translation text")
+                "{}:\n{errors}{new_message}",
+                tr!("# WARNING: Translation has errors, asking to fix them")
             )?;
+            request_text = fix_request_text(&message_text, &new_message.to_string(), &errors);
+        }
+    }
+
+    match best {
+        Some((_, new_message)) if new_message.to_key() == actual_key => {
+            print_translated_message(ctx, config, tr!("# Translated message"), &new_message)
+        }
+        Some((_, new_message)) => print_translated_message(
+            ctx,
+            config,
+            tr!("# Translated message (WARNING: wrong id after translation)"),
+            &new_message.with_key(&actual_key),
+        ),
+        None => {
             writeln!(
                 ctx.out,
                 "{}:\n#, fuzzy\n{message}",
                 tr!("# UNTranslated message (cannot parse translation)")
             )?;
+            Ok(())
         }
     }
+}
 
-    Ok(())
+/// Translates the message with each model of the ensemble, drops translations with
+/// validation errors, and prints the winner, with other translations as comments.
+///
+/// When no model produces a valid translation, the message is translated as usual.
+fn translate_with_ensemble(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    ensemble: &Ensemble,
+    message: &PoMessage,
+) -> Result<()> {
+    let (instruction, message_text) = single_message_prompt(config, message);
+    let parser = reply_parser(config, message.is_plural());
+    let actual_key = message.to_key();
+
+    let mut candidates = Vec::new();
+    for (model, backend) in &ensemble.backends {
+        let reply = match execute_prompt(ctx, config, backend, &instruction, &message_text) {
+            Ok(reply) => reply,
+            Err(e) => {
+                writeln!(
+                    ctx.err,
+                    "{}: {e:#}",
+                    tr!("# WARNING: AI request failed for model {model}").replace("{model}", model)
+                )?;
+                continue;
+            }
+        };
+        let text = extract_message_text(strip_thinking(&reply));
+
+        let mut new_message =
+            match parser.parse_message_from_str(text) {
+                Ok(new_message) if new_message.to_key() == actual_key => new_message,
+                _ => {
+                    writeln!(
+                    ctx.err,
+                    "{}:\n=====\n{text}\n=====",
+                    tr!("# WARNING: Cannot parse translation by model {model}, or msgid is changed")
+                        .replace("{model}", model)
+                )?;
+                    continue;
+                }
+            };
+        if config.copy_comments {
+            new_message.comments = message.comments.clone();
+        }
+
+        let errors = validate_message(&new_message, config.max_length_ratio);
+        if !errors.is_empty() {
+            writeln!(
+                ctx.err,
+                "{}:\n{}{new_message}",
+                tr!("# WARNING: Translation by model {model} has errors, dropping it")
+                    .replace("{model}", model),
+                to_comments(&errors)
+            )?;
+            continue;
+        }
+
+        candidates.push(Candidate {
+            model,
+            message: new_message,
+        });
+    }
+
+    if candidates.is_empty() {
+        writeln!(
+            ctx.err,
+            "{}: \"{}\"",
+            tr!("# WARNING: No valid translations by ensemble, translating message as usual"),
+            message.msgid
+        )?;
+        return translate_single_message(ctx, config, message);
+    }
+
+    let reviewed = match ensemble.arbiter {
+        Arbiter::Review if candidates.len() > 1 => {
+            review_candidates(ctx, config, message, &candidates)?
+        }
+        _ => None,
+    };
+    let (winner, title) = match reviewed {
+        Some(reviewed) => (
+            reviewed,
+            tr!("# Translated message (chosen by review)").to_string(),
+        ),
+        None => {
+            let winner = &candidates[vote(&candidates)];
+            (
+                winner.message.clone(),
+                tr!("# Translated message (chosen by vote, model {model})")
+                    .replace("{model}", winner.model),
+            )
+        }
+    };
+
+    let mut new_message = winner.clone();
+    let mut comments = alternatives_comments(&winner, &candidates);
+    comments.append(&mut new_message.comments);
+    new_message.comments = comments;
+
+    print_translated_message(ctx, config, &title, &new_message)
+}
+
+/// Asks AI model to choose or fix the best translation among candidates, using the
+/// prompt of the `review` command.
+///
+/// Returns `None` when the review is not a valid translation, so it falls back to voting.
+fn review_candidates(
+    ctx: &mut IoContext,
+    config: &TranslateConfig,
+    message: &PoMessage,
+    candidates: &[Candidate],
+) -> Result<Option<PoMessage>> {
+    let mut text = String::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        text.push_str(&format!(
+            "{}:\n{}",
+            tr!("# Variant {}").replace("{}", &(i + 1).to_string()),
+            candidate.message
+        ));
+    }
+    let message_text = format!("<message>\n{text}\n</message>\n");
+    let instruction = review_instruction(config.language);
+
+    let reply = match execute_prompt(ctx, config, &config.backend, &instruction, &message_text) {
+        Ok(reply) => reply,
+        Err(e) => {
+            writeln!(
+                ctx.err,
+                "{}: {e:#}",
+                tr!("# WARNING: AI request failed when trying to review translations, using vote")
+            )?;
+            return Ok(None);
+        }
+    };
+    let text = extract_message_text(strip_thinking(&reply));
+
+    let parser = reply_parser(config, message.is_plural());
+    match parser.parse_message_from_str(text) {
+        Ok(mut reviewed)
+            if reviewed.to_key() == message.to_key()
+                && validate_message(&reviewed, config.max_length_ratio).is_empty() =>
+        {
+            if config.copy_comments {
+                reviewed.comments = message.comments.clone();
+            }
+            Ok(Some(reviewed))
+        }
+        _ => {
+            writeln!(
+                ctx.err,
+                "{}:\n=====\n{text}\n=====",
+                tr!("# WARNING: Review of translations is not valid, using vote")
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+/// Extracts text of the PO message from the reply of AI model.
+fn extract_message_text(reply: &str) -> &str {
+    if let Some(end) = reply.rfind("</message>") {
+        // Extract text between <message> and </message>, if they are present
+        let tag_open = "<message>";
+        if let Some(start) = reply[..end].rfind(tag_open) {
+            &reply[(start + tag_open.len())..end]
+        } else {
+            // Found </message> but no <message> before it. Fallback to whole string.
+            reply
+        }
+    } else if let Some(start) = reply.rfind("msgid ") {
+        // Unwrapped message found
+        &reply[start..]
+    } else {
+        // Message not found
+        reply
+    }
+}
+
+/// Builds request to fix previous translation: original request, previous
+/// translation, and the list of errors found in it.
+fn fix_request_text(message_text: &str, previous_translation: &str, errors: &str) -> String {
+    format!(
+        r#"{message_text}
+Your previous translation of the message has errors. Fix them and answer with the corrected message in <message></message> tag.
+<previous_translation>
+{previous_translation}
+</previous_translation>
+<errors>
+{errors}</errors>
+"#
+    )
 }
 
 fn help_translate(out: &mut dyn Write) -> Result<()> {
@@ -460,36 +1455,100 @@ OPTIONS:
 
   -l | --language LANG  Language to use. Default value: "Ukrainian".
 
-  -m | --model MODEL    AI model to use with aichat. Default value: "ollama:phi4:14b-q8_0".
-                        Additional models: "aya-expanse:32b-q3_K_S", "codestral:22b-v0.1-q5_K_S".
+  -d | --dictionary FILE  Dictionary file (TSV, CSV, or TBX) to use for context. Can be used multiple times.
 
-  -r | --role ROLE      AI role to use with aichat.  Default value: "translate-po".
-                        For better reproducibility, set temperature and top_p to 0, to remove randomness.
+  --stemmer NAME        Stemmer to match inflected forms of dictionary terms, e.g. "entries" for "entry".
+                        Values: english, none. Default value: english.
 
-  -R | --rag RAG        aichat RAG to use.
+  -k | --force-by-keyword KEYWORD  Force translation of messages whose msgid contains KEYWORD.
 
-  -c | --ai-command COMMAND  Custom command to use for translation instead of aichat.
-                        Example: --ai-command "ollama run gemma4:latest"
-                        Options --model, --role, --rag will not work with this option.
+  -p | --prompt PROMPT  Additional instructions for AI models during translation.
 
-  --tm FILE             Local Translation Memory file (PO format) to use for fuzzy matching.
+  -j | --jobs N         Number of AI requests to run in parallel. Default value: 1.
+                        Output is printed in the original order of messages.
 
-  -d | --dictionary FILE  TSV dictionary file to use for context. Can be used multiple times.
+  -b | --batch N        Number of messages to translate with a single AI request. Default value: 1.
+                        Messages missing from the reply are retried individually.
 
-  -k | --force-by-keyword KEYWORD  Force translation of messages whose msgid contains KEYWORD.
+  --checkpoint FILE     Append each translated message to FILE as soon as it's translated.
+                        When FILE exists, messages already translated in it are not translated again,
+                        so an interrupted translation can be resumed.
 
-  -p | --prompt PROMPT  Additional instructions for AI models during translation.
+  --source-root DIR     Resolve `#: file:line` references relative to DIR and show source code
+                        around them to AI models, to help with ambiguous strings.
+
+  --context-lines N     Number of source lines to show before and after the reference. Default value: 3.
+
+  --source-max-tokens N Maximum size of source code in a single prompt, in tokens (roughly 4 characters).
+                        Default value: 1000.
+
+  --max-fix-attempts N  When translation fails validation (missing translation, mismatched
+                        symbols, changed msgid), send it back to AI model with the list of
+                        errors up to N times and keep the best attempt. Default value: 0.
+
+  --models M1,M2,...    Translate each message with several models, drop translations which fail
+                        validation, and choose the best one among the rest. Other translations are
+                        added as comments. Cannot be used with --batch. --max-rpm limits requests
+                        of all models together.
+
+  --arbiter vote|review How to choose the best translation of --models:
+                          vote - the translation most similar to others wins, so the majority wins (default),
+                          review - the model given by --model reviews translations, like the review command.
+
+  --back-translate      Ask AI model to translate each translation back to English, and compare
+                        it with msgid. When they differ too much, the back-translation is added
+                        as a warning comment to the fuzzy message, to catch hallucinations.
+
+  --back-translate-model MODEL  Model for back-translation, ideally different from the translating one.
+                        Implies --back-translate. Default value: same as --model.
+
+  --back-translate-threshold PERCENT  Minimal similarity of back-translation to msgid. Default value: 50.
+
+  --on-error POLICY     What to do when AI request fails even after retries:
+                          keep-untranslated - print message without translation (default),
+                          skip - omit message from output,
+                          fail - stop translation.
+                        Failed messages are listed at the end.
 
   --debug               Print inputs and outputs of AI models to stderr.
 "#
         )
     )?;
+    writeln!(out, "{}", help_translation_memory_options())?;
+    writeln!(out, "{}", help_ai_backend_options())?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::LazyLock;
+
+    static EMPTY_TM: LazyLock<TranslationMemory> = LazyLock::new(TranslationMemory::default);
+
+    fn test_config(backend: AiBackend) -> TranslateConfig<'static> {
+        TranslateConfig {
+            backend,
+            language: "Ukrainian",
+            number_of_plural_cases: None,
+            max_length_ratio: None,
+            translation_memory: &EMPTY_TM,
+            dictionaries: &[],
+            debug: false,
+            copy_comments: true,
+            keyword_matcher: None,
+            prompt: None,
+            jobs: 1,
+            batch: 1,
+            checkpoint: None,
+            source_context: None,
+            on_error: OnError::KeepUntranslated,
+            max_fix_attempts: 0,
+            back_translator: None,
+            ensemble: None,
+            failures: Mutex::new(Vec::new()),
+        }
+    }
 
     #[test]
     fn test_translate_positive() -> Result<()> {
@@ -501,17 +1560,7 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"a\"\nmsgstr \"translated_a\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
-        };
+        let config = test_config(AiBackend::mock("msgid \"a\"\nmsgstr \"translated_a\""));
 
         let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?;
         translate_and_print(&mut ctx, &config, &[message])?;
@@ -532,17 +1581,7 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"a\"\nmsgstr \"translated_a\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
-        };
+        let config = test_config(AiBackend::mock("msgid \"a\"\nmsgstr \"translated_a\""));
 
         let message = parser.parse_message_from_str("# comment\nmsgid \"a\"\nmsgstr \"\"\n")?;
         translate_and_print(&mut ctx, &config, &[message])?;
@@ -564,18 +1603,8 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let config = TranslateConfig {
-            // Backend should not be called
-            backend: AiBackend::mock("SHOULD NOT BE CALLED"),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
-        };
+        // Backend should not be called
+        let config = test_config(AiBackend::mock("SHOULD NOT BE CALLED"));
 
         // already translated message
         let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"existing_a\"\n")?;
@@ -599,32 +1628,414 @@ mod tests {
         };
         let parser = Parser::new(None);
 
-        let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"a\"\nmsgstr \"translated_fuzzy_a\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
-        };
+        let config = test_config(AiBackend::mock(
+            "msgid \"a\"\nmsgstr \"translated_fuzzy_a\"",
+        ));
 
         // fuzzy message
         let message =
             parser.parse_message_from_str("#, fuzzy\nmsgid \"a\"\nmsgstr \"old_fuzzy_a\"\n")?;
         translate_and_print(&mut ctx, &config, &[message])?;
 
-        let result = String::from_utf8(out)?;
-        assert!(result.contains("msgid \"a\""));
-        assert!(result.contains("msgstr \"translated_fuzzy_a\""));
-        assert!(result.contains("Translated message"));
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("msgid \"a\""));
+        assert!(result.contains("msgstr \"translated_fuzzy_a\""));
+        assert!(result.contains("Translated message"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_check_symbols() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        // AI "forgot" the %d symbol
+        let config = test_config(AiBackend::mock("msgid \"a %d\"\nmsgstr \"translated_a\""));
+
+        let message = parser.parse_message_from_str("msgid \"a %d\"\nmsgstr \"\"\n")?;
+        translate_and_print(&mut ctx, &config, &[message])?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("Warning: Incorrect symbols"));
+        assert!(result.contains("#, fuzzy"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_check_whitespace() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        // AI "forgot" the trailing space
+        let config = test_config(AiBackend::mock("msgid \"a \"\nmsgstr \"translated_a\""));
+
+        let message = parser.parse_message_from_str("msgid \"a \"\nmsgstr \"\"\n")?;
+        translate_and_print(&mut ctx, &config, &[message])?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("Warning: Whitespace mismatch"));
+        assert!(result.contains("#, fuzzy"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_force_keyword() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let config = TranslateConfig {
+            keyword_matcher: Some(Regex::new(r"(?i)\bkeywords?\b").unwrap()),
+            ..test_config(AiBackend::mock(
+                "msgid \"keyword message\"\nmsgstr \"forced_translation\"",
+            ))
+        };
+
+        // already translated message with keyword in msgid
+        let message = parser
+            .parse_message_from_str("msgid \"keyword message\"\nmsgstr \"old_translation\"\n")?;
+        translate_and_print(&mut ctx, &config, &[message])?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("msgid \"keyword message\""));
+        assert!(result.contains("msgstr \"forced_translation\""));
+        assert!(result.contains("Translated message"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_skip_translated_with_keyword_no_match() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        // Backend should not be called because the message is translated
+        // and the keyword "tag" is NOT in the msgid.
+        // Word "percenTAGe" contains "tag", but must not trigger the translation.
+        let config = TranslateConfig {
+            copy_comments: false,
+            keyword_matcher: Some(Regex::new(r"(?i)\btags?\b").unwrap()),
+            ..test_config(AiBackend::mock(
+                "msgid \"percentage\"\nmsgstr \"у відсотках\"\n",
+            ))
+        };
+
+        let message =
+            parser.parse_message_from_str("msgid \"percentage\"\nmsgstr \"відсоток\"\n")?;
+
+        translate_and_print(&mut ctx, &config, &[message])?;
+
+        let result = String::from_utf8(out)?;
+        assert!(!result.contains("у відсотках"));
+        assert!(result.contains("msgid \"percentage\""));
+        assert!(result.contains("msgstr \"відсоток\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_custom_prompt() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let config = TranslateConfig {
+            // Use debug mode to see message sent to AI
+            debug: true,
+            prompt: Some("USE VERY FORMAL STYLE".to_string()),
+            ..test_config(AiBackend::mock("msgid \"a\"\nmsgstr \"b\""))
+        };
+
+        let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?;
+        translate_single_message(&mut ctx, &config, &message)?;
+
+        let result = String::from_utf8(err)?;
+        assert!(result.contains("USE VERY FORMAL STYLE"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_help() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        command_translate_and_print(&parser, &GlobalOptions::default(), &["--help"], &mut ctx)?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("Usage:"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_files() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result = command_translate_and_print(&parser, &GlobalOptions::default(), &[], &mut ctx);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_with_models() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let result = command_translate_and_print(
+            &parser,
+            &GlobalOptions::default(),
+            &["--batch", "5", "--models", "a,b", "file.po"],
+            &mut ctx,
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .starts_with("Options --batch and --models cannot be used together")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_force_keyword_comprehensive() -> Result<()> {
+        let parser = Parser::new(None);
+
+        // Test matches for "tag"
+        // Expected: tag, tags, Tag match; percentage, tagging do NOT match.
+        let test_cases = vec![
+            ("tag", true),
+            ("tags", true),
+            ("Tag", true),
+            ("Two tags in a row", true),
+            ("percentage", false),
+            ("tagging", false),
+        ];
+
+        let tag_regex = Regex::new(r"(?i)\btags?\b").unwrap();
+
+        for (msgid, should_match) in test_cases {
+            let mut out = Vec::new();
+            let mut err = Vec::new();
+            let mut ctx = IoContext {
+                out: &mut out,
+                err: &mut err,
+            };
+            let config = TranslateConfig {
+                copy_comments: false,
+                keyword_matcher: Some(tag_regex.clone()),
+                ..test_config(AiBackend::mock(
+                    "msgid \"...\"\nmsgstr \"forced_translation\"",
+                ))
+            };
+
+            let message =
+                parser.parse_message_from_str(&format!("msgid \"{msgid}\"\nmsgstr \"old\"\n"))?;
+            translate_and_print(&mut ctx, &config, &[message])?;
+            let result = String::from_utf8(out)?;
+            if should_match {
+                assert!(
+                    result.contains("forced_translation"),
+                    "Should have matched '{}'",
+                    msgid
+                );
+            } else {
+                assert!(
+                    result.contains("old"),
+                    "Should NOT have matched '{}'",
+                    msgid
+                );
+                assert!(
+                    !result.contains("forced_translation"),
+                    "Should NOT have matched '{}'",
+                    msgid
+                );
+            }
+        }
+
+        // Test multi-word keyword
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let config_big_endian = TranslateConfig {
+            copy_comments: false,
+            keyword_matcher: Some(Regex::new(r"(?i)\bbig endians?\b").unwrap()),
+            ..test_config(AiBackend::mock(
+                "msgid \"...\"\nmsgstr \"forced_translation\"",
+            ))
+        };
+
+        let message =
+            parser.parse_message_from_str("msgid \"This is big endian\"\nmsgstr \"old\"\n")?;
+        translate_and_print(&mut ctx, &config_big_endian, &[message])?;
+        let result = String::from_utf8(out)?;
+        assert!(
+            result.contains("forced_translation"),
+            "Should match 'big endian'"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_broken_tags() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let broken_output = "<message>msgid \"a\"\nmsgstr \"translated_a\"</message>\n\
+                             Some extra text mentioning <message> tag but not closing it.";
+
+        let config = test_config(AiBackend::mock(broken_output));
+
+        let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?;
+        // This should NOT panic
+        translate_and_print(&mut ctx, &config, &[message])?;
+
+        let result = String::from_utf8(out)?;
+        assert!(result.contains("msgid \"a\""));
+        assert!(result.contains("msgstr \"translated_a\""));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_translate_parallel_keeps_order() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        // sed replaces empty msgstr in the prompt, so each reply contains its own msgid
+        let config = TranslateConfig {
+            jobs: 4,
+            ..test_config(AiBackend::new(
+                "sed".to_string(),
+                vec![r#"s/^msgstr ""$/msgstr "done"/"#.to_string()],
+            ))
+        };
+
+        let messages = (0..20)
+            .map(|i| parser.parse_message_from_str(&format!("msgid \"m{i}\"\nmsgstr \"\"\n")))
+            .collect::<Result<Vec<_>>>()?;
+        translate_and_print(&mut ctx, &config, &messages)?;
+
+        let result = String::from_utf8(out)?;
+        let positions = (0..20)
+            .map(|i| result.find(&format!("msgid \"m{i}\"\n")).unwrap())
+            .collect::<Vec<_>>();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(result.matches("msgstr \"done\"").count(), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_parallel_worker_failure() -> Result<()> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let parser = Parser::new(None);
+
+        let config = TranslateConfig {
+            jobs: 2,
+            on_error: OnError::Fail,
+            ..test_config(AiBackend::new(
+                "non-existent-command-123".to_string(),
+                Vec::new(),
+            ))
+        };
+
+        let messages = vec![
+            parser.parse_message_from_str("msgid \"a\"\nmsgstr \"b\"\n")?,
+            parser.parse_message_from_str("msgid \"c\"\nmsgstr \"\"\n")?,
+        ];
+        let result = translate_and_print(&mut ctx, &config, &messages);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Translation of message #2 failed."
+        );
+
+        // Already translated message before the failed one is still printed
+        let output = String::from_utf8(out)?;
+        assert!(output.contains("msgstr \"b\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_into_tasks() -> Result<()> {
+        let parser = Parser::new(None);
+        let config = TranslateConfig {
+            batch: 2,
+            ..test_config(AiBackend::mock(""))
+        };
+
+        let messages = [
+            "msgid \"\"\nmsgstr \"Header\"\n",
+            "msgid \"a\"\nmsgstr \"\"\n",
+            "msgid \"b\"\nmsgstr \"done\"\n",
+            "msgid \"c\"\nmsgstr \"\"\n",
+            "msgid \"d\"\nmsgstr \"\"\n",
+        ]
+        .iter()
+        .map(|text| parser.parse_message_from_str(text))
+        .collect::<Result<Vec<_>>>()?;
+
+        let tasks = split_into_tasks(&config, &messages);
+        let lengths = tasks.iter().map(|task| task.len()).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![1, 3, 1]);
         Ok(())
     }
 
     #[test]
-    fn test_translate_check_symbols() -> Result<()> {
+    fn test_translate_batch() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -634,29 +2045,30 @@ mod tests {
         let parser = Parser::new(None);
 
         let config = TranslateConfig {
-            // AI "forgot" the %d symbol
-            backend: AiBackend::mock("msgid \"a %d\"\nmsgstr \"translated_a\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
+            batch: 10,
+            ..test_config(AiBackend::mock(
+                "<message id=\"2\">\nmsgid \"b\"\nmsgstr \"translated_b\"\n</message>\n\
+                 <message id=\"1\">\nmsgid \"a\"\nmsgstr \"translated_a\"\n</message>\n",
+            ))
         };
 
-        let message = parser.parse_message_from_str("msgid \"a %d\"\nmsgstr \"\"\n")?;
-        translate_and_print(&mut ctx, &config, &[message])?;
+        let messages = vec![
+            parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?,
+            parser.parse_message_from_str("msgid \"x\"\nmsgstr \"existing_x\"\n")?,
+            parser.parse_message_from_str("msgid \"b\"\nmsgstr \"\"\n")?,
+        ];
+        translate_and_print(&mut ctx, &config, &messages)?;
 
         let result = String::from_utf8(out)?;
-        assert!(result.contains("Warning: Incorrect symbols"));
-        assert!(result.contains("#, fuzzy"));
+        let a = result.find("msgstr \"translated_a\"").unwrap();
+        let x = result.find("msgstr \"existing_x\"").unwrap();
+        let b = result.find("msgstr \"translated_b\"").unwrap();
+        assert!(a < x && x < b);
         Ok(())
     }
 
     #[test]
-    fn test_translate_check_whitespace() -> Result<()> {
+    fn test_translate_batch_retries_missing_and_wrong_messages() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -665,101 +2077,197 @@ mod tests {
         };
         let parser = Parser::new(None);
 
+        // Reply to batch contains wrong msgid for "a" and nothing for "b",
+        // so both are retried individually with the same mock reply.
         let config = TranslateConfig {
-            // AI "forgot" the trailing space
-            backend: AiBackend::mock("msgid \"a \"\nmsgstr \"translated_a\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
+            batch: 2,
+            ..test_config(AiBackend::mock(
+                "<message id=\"1\">\nmsgid \"wrong\"\nmsgstr \"translated\"\n</message>\n",
+            ))
         };
 
-        let message = parser.parse_message_from_str("msgid \"a \"\nmsgstr \"\"\n")?;
-        translate_and_print(&mut ctx, &config, &[message])?;
+        let messages = vec![
+            parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?,
+            parser.parse_message_from_str("msgid \"b\"\nmsgstr \"\"\n")?,
+        ];
+        translate_and_print(&mut ctx, &config, &messages)?;
+
+        let errors = String::from_utf8(err)?;
+        assert!(errors.contains("Wrong msgid field in batch translation"));
+        assert!(errors.contains("Translation is missing in batch reply"));
 
         let result = String::from_utf8(out)?;
-        assert!(result.contains("Warning: Whitespace mismatch"));
-        assert!(result.contains("#, fuzzy"));
+        assert!(result.contains("msgid \"a\""));
+        assert!(result.contains("msgid \"b\""));
         Ok(())
     }
 
     #[test]
-    fn test_translate_force_keyword() -> Result<()> {
+    fn test_translate_checkpoint_resume() -> Result<()> {
+        let parser = Parser::new(None);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("checkpoint.po");
+        let path = path.to_str().unwrap();
+
+        let messages = vec![
+            parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?,
+            parser.parse_message_from_str("msgid \"b\"\nmsgstr \"\"\n")?,
+        ];
+
+        // First run is interrupted after first message
+        {
+            let mut out = Vec::new();
+            let mut err = Vec::new();
+            let mut ctx = IoContext {
+                out: &mut out,
+                err: &mut err,
+            };
+            let checkpoint = Checkpoint::open(&parser, path, ctx.err)?;
+            let config = TranslateConfig {
+                checkpoint: Some(&checkpoint),
+                ..test_config(AiBackend::mock("msgid \"a\"\nmsgstr \"translated_a\""))
+            };
+            translate_and_print(&mut ctx, &config, &messages[..1])?;
+        }
+
+        // Second run translates the second message only
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
             out: &mut out,
             err: &mut err,
         };
-        let parser = Parser::new(None);
-
+        let checkpoint = Checkpoint::open(&parser, path, ctx.err)?;
         let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"keyword message\"\nmsgstr \"forced_translation\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: Some(Regex::new(r"(?i)\bkeywords?\b").unwrap()),
-            prompt: None,
+            checkpoint: Some(&checkpoint),
+            ..test_config(AiBackend::mock("msgid \"b\"\nmsgstr \"translated_b\""))
         };
-
-        // already translated message with keyword in msgid
-        let message = parser
-            .parse_message_from_str("msgid \"keyword message\"\nmsgstr \"old_translation\"\n")?;
-        translate_and_print(&mut ctx, &config, &[message])?;
+        translate_and_print(&mut ctx, &config, &messages)?;
 
         let result = String::from_utf8(out)?;
-        assert!(result.contains("msgid \"keyword message\""));
-        assert!(result.contains("msgstr \"forced_translation\""));
-        assert!(result.contains("Translated message"));
+        let a = result.find("msgstr \"translated_a\"").unwrap();
+        let b = result.find("msgstr \"translated_b\"").unwrap();
+        assert!(a < b);
+        assert_eq!(result.matches("translated_b").count(), 1);
+
+        // Both translations are in the checkpoint file now
+        let saved = std::fs::read_to_string(path)?;
+        assert!(saved.contains("translated_a"));
+        assert!(saved.contains("translated_b"));
         Ok(())
     }
 
     #[test]
-    fn test_translate_skip_translated_with_keyword_no_match() -> Result<()> {
-        let mut out = Vec::new();
+    fn test_checkpoint_torn_entry() -> Result<()> {
+        let parser = Parser::new(None);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("checkpoint.po");
+        let path = path.to_str().unwrap();
+
+        // Run was killed while the second entry was written
+        std::fs::write(
+            path,
+            "# Translated message:\n#, fuzzy\nmsgid \"a\"\nmsgstr \"translated_a\"\n\n\
+             # Translated message:\n#, fuzzy\nmsgid \"b\"\nmsgstr \"transl",
+        )?;
+
         let mut err = Vec::new();
-        let mut ctx = IoContext {
-            out: &mut out,
-            err: &mut err,
-        };
+        let checkpoint = Checkpoint::open(&parser, path, &mut err)?;
+        assert_eq!(checkpoint.done.len(), 1);
+        assert!(String::from_utf8(err)?.starts_with("WARNING: Last entry of"));
+
+        // Torn entry is cut, so new entries are appended after the valid ones
+        checkpoint.append("msgid \"c\"\nmsgstr \"translated_c\"\n")?;
+        assert_eq!(
+            std::fs::read_to_string(path)?,
+            "# Translated message:\n#, fuzzy\nmsgid \"a\"\nmsgstr \"translated_a\"\n\n\
+             msgid \"c\"\nmsgstr \"translated_c\"\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate_on_error_policies() -> Result<()> {
         let parser = Parser::new(None);
+        let messages = vec![
+            parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?,
+            parser.parse_message_from_str("msgid \"b\"\nmsgstr \"existing_b\"\n")?,
+        ];
 
-        // Backend should not be called because the message is translated
-        // and the keyword "tag" is NOT in the msgid.
-        // Word "percenTAGe" contains "tag", but must not trigger the translation.
-        let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"percentage\"\nmsgstr \"у відсотках\"\n"),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: false,
-            keyword_matcher: Some(Regex::new(r"(?i)\btags?\b").unwrap()),
-            prompt: None,
-        };
+        for on_error in [OnError::KeepUntranslated, OnError::Skip, OnError::Fail] {
+            let mut out = Vec::new();
+            let mut err = Vec::new();
+            let mut ctx = IoContext {
+                out: &mut out,
+                err: &mut err,
+            };
+            let config = TranslateConfig {
+                on_error,
+                ..test_config(AiBackend::new(
+                    "non-existent-command-123".to_string(),
+                    Vec::new(),
+                ))
+            };
 
-        let message =
-            parser.parse_message_from_str("msgid \"percentage\"\nmsgstr \"відсоток\"\n")?;
+            let result = translate_and_print(&mut ctx, &config, &messages);
+            let output = String::from_utf8(out)?;
+            let failures = config.failures.into_inner().unwrap();
+
+            match on_error {
+                OnError::KeepUntranslated => {
+                    assert!(result.is_ok());
+                    assert!(output.contains("msgid \"a\""));
+                    assert!(output.contains("msgstr \"existing_b\""));
+                    assert_eq!(failures.len(), 1);
+                }
+                OnError::Skip => {
+                    assert!(result.is_ok());
+                    assert!(!output.contains("msgid \"a\""));
+                    assert!(output.contains("msgstr \"existing_b\""));
+                    assert_eq!(failures.len(), 1);
+                }
+                OnError::Fail => assert!(result.is_err()),
+            }
+        }
+        Ok(())
+    }
 
-        translate_and_print(&mut ctx, &config, &[message])?;
+    #[test]
+    #[cfg(not(windows))]
+    fn test_translate_fix_attempts() -> Result<()> {
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Hello, %s!\"\nmsgstr \"\"\n")?;
 
-        let result = String::from_utf8(out)?;
-        assert!(!result.contains("у відсотках"));
-        assert!(result.contains("msgid \"percentage\""));
-        assert!(result.contains("msgstr \"відсоток\""));
+        // Model answers with broken translation unless it is asked to fix errors
+        let script = r#"if grep -q '<errors>'; then printf '<message>msgid "Hello, %%s!"\nmsgstr "Привіт, %%s!"\n</message>'; else printf '<message>msgid "Hello!"\nmsgstr "Привіт"\n</message>'; fi"#;
+        let backend = AiBackend::new("sh".to_string(), vec!["-c".to_string(), script.to_string()]);
+
+        for (max_fix_attempts, expected) in
+            [(0, "msgstr \"Привіт\""), (1, "msgstr \"Привіт, %s!\"")]
+        {
+            let mut out = Vec::new();
+            let mut err = Vec::new();
+            let mut ctx = IoContext {
+                out: &mut out,
+                err: &mut err,
+            };
+            let config = TranslateConfig {
+                max_fix_attempts,
+                ..test_config(backend.clone())
+            };
+
+            translate_single_message(&mut ctx, &config, &message)?;
+
+            let output = String::from_utf8(out)?;
+            assert!(output.contains("msgid \"Hello, %s!\""), "{output}");
+            assert!(output.contains(expected), "{output}");
+        }
         Ok(())
     }
 
     #[test]
-    fn test_translate_custom_prompt() -> Result<()> {
+    #[cfg(not(windows))]
+    fn test_translate_best_attempt() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -767,30 +2275,35 @@ mod tests {
             err: &mut err,
         };
         let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Hello, %s!\"\nmsgstr \"\"\n")?;
 
+        // First attempt has one problem with details, second one has two problems
+        let script = r#"if grep -q '<errors>'; then printf '<message>msgid "Hi, %%s!"\nmsgstr "Привіт, мій друже %%s!"\n</message>'; else printf '<message>msgid "Hello, %%s!"\nmsgstr "Привіт!"\n</message>'; fi"#;
         let config = TranslateConfig {
-            backend: AiBackend::mock("msgid \"a\"\nmsgstr \"b\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            // Use debug mode to see message sent to AI
-            debug: true,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: Some("USE VERY FORMAL STYLE".to_string()),
+            max_fix_attempts: 1,
+            max_length_ratio: Some(1.5),
+            ..test_config(AiBackend::new(
+                "sh".to_string(),
+                vec!["-c".to_string(), script.to_string()],
+            ))
         };
 
-        let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?;
         translate_single_message(&mut ctx, &config, &message)?;
 
-        let result = String::from_utf8(err)?;
-        assert!(result.contains("USE VERY FORMAL STYLE"));
+        let output = String::from_utf8(out)?;
+        assert!(output.contains("msgstr \"Привіт!\""), "{output}");
         Ok(())
     }
 
     #[test]
-    fn test_help() -> Result<()> {
+    fn test_translate_with_source_context() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(
+            dir.path().join("menu.c"),
+            "menu_add(_(\"Open\"), open_file);\n",
+        )?;
+        let source_context = SourceContext::new(dir.path(), 3, 1000);
+
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -798,16 +2311,28 @@ mod tests {
             err: &mut err,
         };
         let parser = Parser::new(None);
+        let message =
+            parser.parse_message_from_str("#: menu.c:1\nmsgid \"Open\"\nmsgstr \"\"\n")?;
 
-        command_translate_and_print(&parser, &["--help"], &mut ctx)?;
+        let config = TranslateConfig {
+            debug: true,
+            source_context: Some(&source_context),
+            ..test_config(AiBackend::mock(
+                "<message>msgid \"Open\"\nmsgstr \"Відкрити\"</message>",
+            ))
+        };
+        translate_single_message(&mut ctx, &config, &message)?;
 
-        let result = String::from_utf8(out)?;
-        assert!(result.contains("Usage:"));
+        let prompt = String::from_utf8(err)?;
+        assert!(prompt.contains(
+            "<source>\n# menu.c:1\n>    1 | menu_add(_(\"Open\"), open_file);\n</source>"
+        ));
+        assert!(String::from_utf8(out)?.contains("msgstr \"Відкрити\""));
         Ok(())
     }
 
     #[test]
-    fn test_no_files() -> Result<()> {
+    fn test_translate_back_translate() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -815,105 +2340,74 @@ mod tests {
             err: &mut err,
         };
         let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Save changes\"\nmsgstr \"\"\n")?;
 
-        let result = command_translate_and_print(&parser, &[], &mut ctx);
-        assert!(result.is_err());
+        // Back-translation is far from msgid, so it's reported
+        let back_translator = BackTranslator::new(
+            AiBackend::mock("<translation>Delete everything</translation>"),
+            DEFAULT_MIN_SIMILARITY,
+        );
+        let config = TranslateConfig {
+            back_translator: Some(&back_translator),
+            ..test_config(AiBackend::mock(
+                "<message>msgid \"Save changes\"\nmsgstr \"Видалити все\"</message>",
+            ))
+        };
+        translate_single_message(&mut ctx, &config, &message)?;
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            "# Translated message:\n# Warning [back-translation]: Back-translation differs from msgid, similarity is 18%, but minimum is 50%.\n# Back-translation: \"Delete everything\"\n#, fuzzy\nmsgid \"Save changes\"\nmsgstr \"Видалити все\"\n\n"
+        );
         Ok(())
     }
 
-    #[test]
-    fn test_translate_force_keyword_comprehensive() -> Result<()> {
-        let parser = Parser::new(None);
-
-        // Test matches for "tag"
-        // Expected: tag, tags, Tag match; percentage, tagging do NOT match.
-        let test_cases = vec![
-            ("tag", true),
-            ("tags", true),
-            ("Tag", true),
-            ("Two tags in a row", true),
-            ("percentage", false),
-            ("tagging", false),
-        ];
-
-        let tag_regex = Regex::new(r"(?i)\btags?\b").unwrap();
-
-        for (msgid, should_match) in test_cases {
-            let mut out = Vec::new();
-            let mut err = Vec::new();
-            let mut ctx = IoContext {
-                out: &mut out,
-                err: &mut err,
-            };
-            let config = TranslateConfig {
-                backend: AiBackend::mock("msgid \"...\"\nmsgstr \"forced_translation\""),
-                language: "Ukrainian",
-                number_of_plural_cases: None,
-                tm_messages: &[],
-                dictionaries: &[],
-                debug: false,
-                copy_comments: false,
-                keyword_matcher: Some(tag_regex.clone()),
-                prompt: None,
-            };
-
-            let message =
-                parser.parse_message_from_str(&format!("msgid \"{msgid}\"\nmsgstr \"old\"\n"))?;
-            translate_and_print(&mut ctx, &config, &[message])?;
-            let result = String::from_utf8(out)?;
-            if should_match {
-                assert!(
-                    result.contains("forced_translation"),
-                    "Should have matched '{}'",
-                    msgid
-                );
-            } else {
-                assert!(
-                    result.contains("old"),
-                    "Should NOT have matched '{}'",
-                    msgid
-                );
-                assert!(
-                    !result.contains("forced_translation"),
-                    "Should NOT have matched '{}'",
-                    msgid
-                );
-            }
-        }
+    fn ensemble_of(replies: &[&str], arbiter: Arbiter) -> Ensemble {
+        let backends = replies
+            .iter()
+            .enumerate()
+            .map(|(i, reply)| (format!("m{}", i + 1), AiBackend::mock(reply)))
+            .collect();
+        Ensemble::new(backends, arbiter)
+    }
 
-        // Test multi-word keyword
+    #[test]
+    fn test_translate_ensemble_vote() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
             out: &mut out,
             err: &mut err,
         };
-        let config_big_endian = TranslateConfig {
-            backend: AiBackend::mock("msgid \"...\"\nmsgstr \"forced_translation\""),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: false,
-            keyword_matcher: Some(Regex::new(r"(?i)\bbig endians?\b").unwrap()),
-            prompt: None,
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Open %s\"\nmsgstr \"\"\n")?;
+
+        let ensemble = ensemble_of(
+            &[
+                "msgid \"Open %s\"\nmsgstr \"Відкрити %s\"",
+                "msgid \"Open %s\"\nmsgstr \"Відкрити\"",
+                "msgid \"Open %s\"\nmsgstr \"Відкрийте %s\"",
+                "msgid \"Open %s\"\nmsgstr \"Відкрити %s\"",
+            ],
+            Arbiter::Vote,
+        );
+        let config = TranslateConfig {
+            ensemble: Some(&ensemble),
+            ..test_config(AiBackend::mock("unused"))
         };
+        translate_and_print(&mut ctx, &config, &[message])?;
 
-        let message =
-            parser.parse_message_from_str("msgid \"This is big endian\"\nmsgstr \"old\"\n")?;
-        translate_and_print(&mut ctx, &config_big_endian, &[message])?;
-        let result = String::from_utf8(out)?;
-        assert!(
-            result.contains("forced_translation"),
-            "Should match 'big endian'"
+        // Translation without placeholder is dropped, and the majority wins
+        assert_eq!(
+            String::from_utf8(out)?,
+            "# Translated message (chosen by vote, model m1):\n#, fuzzy\n# Alternative by m3: \"Відкрийте %s\"\nmsgid \"Open %s\"\nmsgstr \"Відкрити %s\"\n\n"
         );
-
+        assert!(String::from_utf8(err)?.contains("Translation by model m2 has errors"));
         Ok(())
     }
 
     #[test]
-    fn test_translate_broken_tags() -> Result<()> {
+    fn test_translate_ensemble_review() -> Result<()> {
         let mut out = Vec::new();
         let mut err = Vec::new();
         let mut ctx = IoContext {
@@ -921,29 +2415,27 @@ mod tests {
             err: &mut err,
         };
         let parser = Parser::new(None);
-
-        let broken_output = "<message>msgid \"a\"\nmsgstr \"translated_a\"</message>\n\
-                             Some extra text mentioning <message> tag but not closing it.";
-
+        let message = parser.parse_message_from_str("msgid \"Open\"\nmsgstr \"\"\n")?;
+
+        let ensemble = ensemble_of(
+            &[
+                "msgid \"Open\"\nmsgstr \"Відкрити\"",
+                "msgid \"Open\"\nmsgstr \"Відкрийте\"",
+            ],
+            Arbiter::Review,
+        );
         let config = TranslateConfig {
-            backend: AiBackend::mock(broken_output),
-            language: "Ukrainian",
-            number_of_plural_cases: None,
-            tm_messages: &[],
-            dictionaries: &[],
-            debug: false,
-            copy_comments: true,
-            keyword_matcher: None,
-            prompt: None,
+            ensemble: Some(&ensemble),
+            ..test_config(AiBackend::mock(
+                "<review>Infinitive is better.</review><message>msgid \"Open\"\nmsgstr \"Відкрити\"</message>",
+            ))
         };
-
-        let message = parser.parse_message_from_str("msgid \"a\"\nmsgstr \"\"\n")?;
-        // This should NOT panic
         translate_and_print(&mut ctx, &config, &[message])?;
 
-        let result = String::from_utf8(out)?;
-        assert!(result.contains("msgid \"a\""));
-        assert!(result.contains("msgstr \"translated_a\""));
+        assert_eq!(
+            String::from_utf8(out)?,
+            "# Translated message (chosen by review):\n#, fuzzy\n# Alternative by m2: \"Відкрийте\"\nmsgid \"Open\"\nmsgstr \"Відкрити\"\n\n"
+        );
         Ok(())
     }
 }
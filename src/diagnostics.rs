@@ -0,0 +1,404 @@
+//! Structured diagnostics, reported by check commands.
+//!
+//! Checks describe problems as diagnostics with rule id, severity, and text. In PO
+//! format, diagnostics are printed as `# Error: ...` and `# Warning: ...` comments
+//! before the message. In other formats, they are printed with file, line, and
+//! message key as JSON, SARIF, or GCC-style lines.
+
+use crate::parser::PoMessage;
+use crate::util::IoContext;
+use anyhow::{Result, bail};
+use serde_json::{Value, json};
+
+/// Output format of diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Comments before messages in PO file.
+    #[default]
+    Po,
+    /// JSON array of diagnostics.
+    Json,
+    /// SARIF 2.1.0 log, for code scanning tools.
+    Sarif,
+    /// GCC-style lines: `file:line: severity: text [rule]`, for editors.
+    Gcc,
+}
+
+impl OutputFormat {
+    /// Returns output format by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "po" => Ok(OutputFormat::Po),
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "gcc" => Ok(OutputFormat::Gcc),
+            _ => bail!(
+                "{}",
+                tr!("Invalid argument for --format option. Expected: po, json, sarif, or gcc. Actual value: \"{value}\".")
+                    .replace("{value}", name)
+            ),
+        }
+    }
+}
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    /// Returns localized name of the severity for PO comments.
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Error => tr!("Error"),
+            Severity::Warning => tr!("Warning"),
+        }
+    }
+}
+
+/// A single problem found by a check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Id of the sub-rule, e.g. "punctuation" for `lint/punctuation`,
+    /// or `None` for the rule of the check itself.
+    pub rule: Option<String>,
+    pub severity: Severity,
+    /// Description of the problem, in a single line.
+    pub text: String,
+    /// Additional lines of the description, e.g. actual symbols of msgid and msgstr.
+    pub details: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, text: impl Into<String>) -> Self {
+        Diagnostic {
+            rule: None,
+            severity,
+            text: text.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self::new(Severity::Error, text)
+    }
+
+    pub fn warning(text: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, text)
+    }
+
+    /// Sets id of the sub-rule.
+    pub fn with_rule(mut self, rule: &str) -> Self {
+        self.rule = Some(rule.to_string());
+        self
+    }
+
+    /// Adds a line of details.
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.details.push(detail.into());
+        self
+    }
+
+    /// Formats the diagnostic as PO comments, e.g. `# Warning [quotes]: text`.
+    pub fn to_comment(&self) -> String {
+        let mut comment = match &self.rule {
+            Some(rule) => format!("# {} [{rule}]: {}\n", self.severity.label(), self.text),
+            None => format!("# {}: {}\n", self.severity.label(), self.text),
+        };
+        for detail in &self.details {
+            comment.push_str(&format!("# {detail}\n"));
+        }
+        comment
+    }
+}
+
+/// Formats diagnostics as PO comments, to print them before the message.
+pub fn to_comments(diagnostics: &[Diagnostic]) -> String {
+    diagnostics.iter().map(Diagnostic::to_comment).collect()
+}
+
+/// A diagnostic with its location in a PO file and the key of the message.
+struct Record {
+    file: String,
+    /// Number of the first line of the message, starting from 1.
+    line: usize,
+    /// Id of the rule, e.g. "check-format" or "lint/punctuation".
+    rule: String,
+    /// Context of the message.
+    msgctxt: Option<String>,
+    /// Id of the message. Empty for problems, which are not related to a single message.
+    msgid: String,
+    diagnostic: Diagnostic,
+}
+
+impl Record {
+    fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "line": self.line,
+            "rule": self.rule,
+            "severity": self.diagnostic.severity.name(),
+            "message_key": { "msgctxt": self.msgctxt, "msgid": self.msgid },
+            "message": self.diagnostic.text,
+            "details": self.diagnostic.details,
+        })
+    }
+
+    fn to_sarif(&self) -> Value {
+        json!({
+            "ruleId": self.rule,
+            "level": self.diagnostic.severity.name(),
+            "message": { "text": self.diagnostic.text },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": self.file },
+                    "region": { "startLine": self.line }
+                }
+            }],
+            "properties": {
+                "msgctxt": self.msgctxt,
+                "msgid": self.msgid,
+                "details": self.diagnostic.details,
+            }
+        })
+    }
+
+    fn to_gcc(&self) -> String {
+        let mut text = format!(
+            "{}:{}: {}: {} [{}]\n",
+            self.file,
+            self.line,
+            self.diagnostic.severity.name(),
+            self.diagnostic.text,
+            self.rule
+        );
+        for detail in &self.diagnostic.details {
+            text.push_str(&format!("{}:{}: note: {}\n", self.file, self.line, detail));
+        }
+        text
+    }
+}
+
+/// Reports problems found by a check, in the selected output format.
+///
+/// In PO format, problems are printed immediately as comments before the message.
+/// In GCC format, they are printed immediately as lines. JSON and SARIF documents
+/// are printed by `finish()`.
+pub struct Reporter {
+    format: OutputFormat,
+    rule: &'static str,
+    records: Vec<Record>,
+}
+
+impl Reporter {
+    /// Creates reporter for the check with the given rule id, usually the name of the command.
+    pub fn new(format: OutputFormat, rule: &'static str) -> Self {
+        Reporter {
+            format,
+            rule,
+            records: Vec::new(),
+        }
+    }
+
+    /// Returns true, when problems are printed as PO comments.
+    pub fn is_po(&self) -> bool {
+        self.format == OutputFormat::Po
+    }
+
+    /// Reports problems of the message.
+    pub fn report(
+        &mut self,
+        ctx: &mut IoContext,
+        file: &str,
+        line: usize,
+        message: &PoMessage,
+        diagnostics: &[Diagnostic],
+    ) -> Result<()> {
+        if self.is_po() {
+            writeln!(ctx.out, "{}{message}", to_comments(diagnostics))?;
+            return Ok(());
+        }
+        self.add(ctx, file, line, Some(message), diagnostics)
+    }
+
+    /// Reports problems, which are not related to a single message, e.g. collisions between messages.
+    pub fn report_group(
+        &mut self,
+        ctx: &mut IoContext,
+        file: &str,
+        line: usize,
+        diagnostics: &[Diagnostic],
+    ) -> Result<()> {
+        if self.is_po() {
+            writeln!(ctx.out, "{}", to_comments(diagnostics))?;
+            return Ok(());
+        }
+        self.add(ctx, file, line, None, diagnostics)
+    }
+
+    fn add(
+        &mut self,
+        ctx: &mut IoContext,
+        file: &str,
+        line: usize,
+        message: Option<&PoMessage>,
+        diagnostics: &[Diagnostic],
+    ) -> Result<()> {
+        for diagnostic in diagnostics {
+            let record = Record {
+                file: file.to_string(),
+                line,
+                rule: match &diagnostic.rule {
+                    Some(rule) => format!("{}/{rule}", self.rule),
+                    None => self.rule.to_string(),
+                },
+                msgctxt: message.and_then(|m| m.msgctxt.clone()),
+                msgid: message.map(|m| m.msgid.clone()).unwrap_or_default(),
+                diagnostic: diagnostic.clone(),
+            };
+            if self.format == OutputFormat::Gcc {
+                write!(ctx.out, "{}", record.to_gcc())?;
+            } else {
+                self.records.push(record);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints collected diagnostics as JSON or SARIF document.
+    pub fn finish(self, ctx: &mut IoContext) -> Result<()> {
+        let document = match self.format {
+            OutputFormat::Po | OutputFormat::Gcc => return Ok(()),
+            OutputFormat::Json => Value::Array(self.records.iter().map(Record::to_json).collect()),
+            OutputFormat::Sarif => {
+                let mut rules: Vec<&str> = self.records.iter().map(|r| r.rule.as_str()).collect();
+                rules.sort();
+                rules.dedup();
+                json!({
+                    "version": "2.1.0",
+                    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+                    "runs": [{
+                        "tool": {
+                            "driver": {
+                                "name": "po-tools",
+                                "version": env!("CARGO_PKG_VERSION"),
+                                "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                            }
+                        },
+                        "results": self.records.iter().map(Record::to_sarif).collect::<Vec<_>>(),
+                    }]
+                })
+            }
+        };
+        writeln!(ctx.out, "{}", serde_json::to_string_pretty(&document)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_comments() {
+        let diagnostics = [
+            Diagnostic::warning("Incorrect symbols:")
+                .with_detail("msgid:  %")
+                .with_detail("msgstr: "),
+            Diagnostic::warning("Use «» quotes instead of \".").with_rule("quotes"),
+            Diagnostic::error("Message is not translated."),
+        ];
+        assert_eq!(
+            to_comments(&diagnostics),
+            "# Warning: Incorrect symbols:\n# msgid:  %\n# msgstr: \n# Warning [quotes]: Use «» quotes instead of \".\n# Error: Message is not translated.\n"
+        );
+    }
+
+    fn report(format: OutputFormat) -> Result<String> {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut ctx = IoContext {
+            out: &mut out,
+            err: &mut err,
+        };
+        let message = PoMessage {
+            msgctxt: Some("Menu".to_string()),
+            msgid: "Open".to_string(),
+            msgstr: vec!["відкрити".to_string()],
+            ..Default::default()
+        };
+
+        let mut reporter = Reporter::new(format, "lint");
+        reporter.report(
+            &mut ctx,
+            "uk.po",
+            12,
+            &message,
+            &[
+                Diagnostic::warning("msgstr must start with a capital letter, like msgid.")
+                    .with_rule("capitalization"),
+            ],
+        )?;
+        reporter.finish(&mut ctx)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_output_formats() -> Result<()> {
+        assert_eq!(
+            report(OutputFormat::Po)?,
+            "# Warning [capitalization]: msgstr must start with a capital letter, like msgid.\nmsgctxt \"Menu\"\nmsgid \"Open\"\nmsgstr \"відкрити\"\n\n"
+        );
+
+        assert_eq!(
+            report(OutputFormat::Gcc)?,
+            "uk.po:12: warning: msgstr must start with a capital letter, like msgid. [lint/capitalization]\n"
+        );
+
+        let json: Value = serde_json::from_str(&report(OutputFormat::Json)?)?;
+        assert_eq!(
+            json,
+            json!([{
+                "file": "uk.po",
+                "line": 12,
+                "rule": "lint/capitalization",
+                "severity": "warning",
+                "message_key": { "msgctxt": "Menu", "msgid": "Open" },
+                "message": "msgstr must start with a capital letter, like msgid.",
+                "details": [],
+            }])
+        );
+
+        let sarif: Value = serde_json::from_str(&report(OutputFormat::Sarif)?)?;
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"][0]["id"],
+            "lint/capitalization"
+        );
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["level"], "warning");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            12
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            OutputFormat::from_name("sarif").unwrap(),
+            OutputFormat::Sarif
+        );
+        assert!(OutputFormat::from_name("xml").is_err());
+    }
+}
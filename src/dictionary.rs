@@ -1,23 +1,193 @@
 //! Dictionary support for translation terminology.
 //!
 //! This module provides the `Dictionary` struct for loading terminology
-//! from TSV files and searching for matches in text.
+//! from TSV, CSV, or TBX files and searching for matches in text.
 
-use anyhow::{Context, Result};
+use crate::stemmer::{Stemmer, stem_words};
+use anyhow::{Context, Result, bail};
 use regex::Regex;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::Arc;
 
 /// A single entry in the dictionary.
 #[derive(Debug, Clone)]
 pub struct DictionaryEntry {
     /// The source term (usually in English).
     pub key: String,
-    /// The translated term in the target language.
+    /// The translated term in the target language. Variants are separated by `|`.
     pub translation: String,
     /// Pre-compiled regex for searching this term.
     pub regex: Regex,
+    /// Part of speech of the term, e.g. "noun" or "verb".
+    pub part_of_speech: Option<String>,
+    /// Free-form note about usage of the term.
+    pub note: Option<String>,
+    /// Context or subject domain, where the term is used.
+    pub domain: Option<String>,
+    /// The term must be kept as is, without translation.
+    pub do_not_translate: bool,
+    /// Translations of the term, which must not be used.
+    pub forbidden: Vec<String>,
+    /// Whether the term is searched by user-defined regex pattern instead of the default one.
+    pub custom_pattern: bool,
+    /// Stems of words of the term, when the dictionary uses a stemmer.
+    pub stems: Vec<String>,
+}
+
+impl DictionaryEntry {
+    /// Creates a new entry without optional fields.
+    ///
+    /// When `pattern` is not given, the term is searched as a whole word, case-insensitively,
+    /// with an optional plural "s" suffix.
+    pub fn new(key: &str, translation: &str, pattern: Option<&str>) -> Result<Self> {
+        let custom_pattern = pattern.is_some();
+        let pattern = match pattern {
+            Some(pattern) => pattern.to_string(),
+            None => format!(r"(?i)\b{}(s)?\b", regex::escape(key)),
+        };
+        let regex = Regex::new(&pattern)
+            .with_context(|| format!("Failed to compile regex for dictionary key: {key}"))?;
+
+        Ok(DictionaryEntry {
+            key: key.to_string(),
+            translation: translation.to_string(),
+            regex,
+            part_of_speech: None,
+            note: None,
+            domain: None,
+            do_not_translate: false,
+            forbidden: Vec::new(),
+            custom_pattern,
+            stems: Vec::new(),
+        })
+    }
+
+    /// Returns allowed translations of the term.
+    ///
+    /// Variants, e.g. inflected forms, are separated by `|` in the translation.
+    /// For terms which must not be translated, the term itself is returned.
+    pub fn translations(&self) -> impl Iterator<Item = &str> {
+        let translation = if self.do_not_translate {
+            self.key.as_str()
+        } else {
+            self.translation.as_str()
+        };
+        translation
+            .split('|')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Checks whether text contains one of allowed translations of the term, as whole words.
+    ///
+    /// Comparison is case-insensitive.
+    pub fn is_translated_in(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.translations()
+            .any(|translation| contains_words(&text, &translation.to_lowercase()))
+    }
+
+    /// Returns forbidden translations of the term, which are present in the text.
+    pub fn forbidden_in(&self, text: &str) -> Vec<&str> {
+        let text = text.to_lowercase();
+        self.forbidden
+            .iter()
+            .map(|f| f.as_str())
+            .filter(|f| contains_words(&text, &f.to_lowercase()))
+            .collect()
+    }
+
+    /// Describes the entry for AI models: term, translation, and additional information.
+    pub fn describe(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(pos) = &self.part_of_speech {
+            details.push(pos.clone());
+        }
+        if let Some(domain) = &self.domain {
+            details.push(format!("domain: {domain}"));
+        }
+        if let Some(note) = &self.note {
+            details.push(format!("note: {note}"));
+        }
+        if !self.forbidden.is_empty() {
+            details.push(format!("never use: {}", self.forbidden.join(", ")));
+        }
+
+        let translation = if self.do_not_translate {
+            format!("{} (do not translate)", self.key)
+        } else {
+            self.translations().collect::<Vec<_>>().join(", ")
+        };
+
+        if details.is_empty() {
+            format!("{} - {translation}", self.key)
+        } else {
+            format!("{} - {translation} ({})", self.key, details.join("; "))
+        }
+    }
+}
+
+/// Checks whether `text` contains `words` with word boundaries on both sides.
+fn contains_words(text: &str, words: &str) -> bool {
+    text.match_indices(words).any(|(start, _)| {
+        let end = start + words.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// Returns true, when the row is a header, i.e. it names both term and translation columns.
+///
+/// A single column name is not enough, because "key" or "source" may be a real term.
+fn is_header(record: &[String]) -> bool {
+    let columns: Vec<Column> = record.iter().map(|name| Column::from_name(name)).collect();
+    columns.contains(&Column::Term) && columns.contains(&Column::Translation)
+}
+
+/// Column of a TSV or CSV dictionary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Term,
+    Translation,
+    PartOfSpeech,
+    Note,
+    Domain,
+    DoNotTranslate,
+    Forbidden,
+    Pattern,
+    Unknown,
+}
+
+/// Order of columns in TSV or CSV dictionary without header.
+const DEFAULT_COLUMNS: [Column; 8] = [
+    Column::Term,
+    Column::Translation,
+    Column::PartOfSpeech,
+    Column::Note,
+    Column::Domain,
+    Column::DoNotTranslate,
+    Column::Forbidden,
+    Column::Pattern,
+];
+
+impl Column {
+    /// Recognizes name of the column in the header.
+    fn from_name(name: &str) -> Self {
+        match name.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "term" | "source" | "key" => Column::Term,
+            "translation" | "target" => Column::Translation,
+            "pos" | "part_of_speech" => Column::PartOfSpeech,
+            "note" | "notes" | "comment" => Column::Note,
+            "domain" | "context" => Column::Domain,
+            "dnt" | "do_not_translate" => Column::DoNotTranslate,
+            "forbidden" | "deprecated" => Column::Forbidden,
+            "pattern" | "regex" => Column::Pattern,
+            _ => Column::Unknown,
+        }
+    }
 }
 
 /// A collection of dictionary entries.
@@ -25,13 +195,47 @@ pub struct DictionaryEntry {
 pub struct Dictionary {
     /// List of entries in the dictionary.
     pub entries: Vec<DictionaryEntry>,
+    /// Stemmer to match inflected forms of terms.
+    pub stemmer: Option<Arc<dyn Stemmer>>,
 }
 
 impl Dictionary {
-    /// Loads a dictionary from a TSV file.
+    /// Loads a dictionary from a file. Format is selected by extension:
+    /// `.tbx` for TBX, `.csv` for CSV, and TSV otherwise.
     ///
-    /// Each line should contain a term and its translation separated by a tab.
+    /// In TSV files without header, each line contains a term and its translation,
+    /// separated by the first tab. In CSV files, columns are: term, translation,
+    /// part of speech, note, domain, do not translate flag, forbidden translations,
+    /// and regex pattern. Only the first two columns are required. A header line,
+    /// which names both term and translation columns, selects columns in any order,
+    /// for TSV files too.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("tbx") => {
+                let text = std::fs::read_to_string(&path).with_context(|| {
+                    format!(
+                        "{}: {:?}",
+                        tr!("Failed to open dictionary file"),
+                        path.as_ref()
+                    )
+                })?;
+                Self::from_tbx_str(&text)
+            }
+            Some("csv") => Self::from_csv_file(path),
+            _ => Self::from_tsv_file(path),
+        }
+    }
+
+    /// Loads a dictionary from a TSV file.
+    ///
+    /// Each line should contain a term and its translation separated by a tab,
+    /// unless a header line selects other columns.
+    fn from_tsv_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(&path).with_context(|| {
             format!(
                 "{}: {:?}",
@@ -40,7 +244,7 @@ impl Dictionary {
             )
         })?;
         let reader = BufReader::new(file);
-        let mut entries = Vec::new();
+        let mut records: Vec<Vec<String>> = Vec::new();
 
         for (line_no, line) in reader.lines().enumerate() {
             let line = line?;
@@ -50,46 +254,211 @@ impl Dictionary {
             }
 
             // Split by tab char
-            match line.split_once('\t') {
-                Some((key, translation)) => {
-                    let key = key.trim();
-                    if key.is_empty() {
-                        continue;
-                    }
-                    let escaped_key = regex::escape(key);
-                    let pattern = format!(r"(?i)\b{}(s)?\b", escaped_key);
-                    let regex = Regex::new(&pattern).with_context(|| {
-                        format!("Failed to compile regex for dictionary key: {key}")
-                    })?;
-
-                    entries.push(DictionaryEntry {
-                        key: key.to_string(),
-                        translation: translation.trim().to_string(),
-                        regex,
-                    });
-                }
-                None => {
-                    eprintln!(
-                        "{}: \"{}\"",
-                        tr!("WARNING: Invalid dictionary entry at line {line}: missing tab separator. Line:").replace("{line}", &(line_no + 1).to_string()),
-                        line
-                    );
+            if line.contains('\t') {
+                records.push(line.split('\t').map(String::from).collect());
+            } else {
+                eprintln!(
+                    "{}: \"{}\"",
+                    tr!("WARNING: Invalid dictionary entry at line {line}: missing tab separator. Line:").replace("{line}", &(line_no + 1).to_string()),
+                    line
+                );
+            }
+        }
+
+        // Without header, everything after the first tab is translation
+        if !records.first().is_some_and(|record| is_header(record)) {
+            for record in &mut records {
+                let translation = record[1..].join("\t");
+                record.truncate(1);
+                record.push(translation);
+            }
+        }
+
+        Self::from_records(records)
+    }
+
+    /// Loads a dictionary from a CSV file.
+    fn from_csv_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .from_path(&path)
+            .with_context(|| {
+                format!(
+                    "{}: {:?}",
+                    tr!("Failed to open dictionary file"),
+                    path.as_ref()
+                )
+            })?;
+
+        let mut records = Vec::new();
+        for record in reader.records() {
+            let record = record.with_context(|| {
+                format!(
+                    "{}: {:?}",
+                    tr!("Failed to parse dictionary file"),
+                    path.as_ref()
+                )
+            })?;
+            records.push(record.iter().map(String::from).collect());
+        }
+
+        Self::from_records(records)
+    }
+
+    /// Creates dictionary from rows of TSV or CSV file.
+    fn from_records(records: Vec<Vec<String>>) -> Result<Self> {
+        let mut records = records.into_iter().peekable();
+
+        let columns: Vec<Column> = match records.peek() {
+            Some(header) if is_header(header) => {
+                let header = records.next().unwrap_or_default();
+                header.iter().map(|name| Column::from_name(name)).collect()
+            }
+            _ => DEFAULT_COLUMNS.to_vec(),
+        };
+
+        let mut entries = Vec::new();
+        for record in records {
+            let field = |column: Column| {
+                columns
+                    .iter()
+                    .zip(&record)
+                    .find(|(c, _)| **c == column)
+                    .map(|(_, value)| value.trim())
+                    .filter(|value| !value.is_empty())
+            };
+
+            let Some(key) = field(Column::Term) else {
+                continue;
+            };
+            let mut entry = DictionaryEntry::new(
+                key,
+                field(Column::Translation).unwrap_or_default(),
+                field(Column::Pattern),
+            )?;
+            entry.part_of_speech = field(Column::PartOfSpeech).map(String::from);
+            entry.note = field(Column::Note).map(String::from);
+            entry.domain = field(Column::Domain).map(String::from);
+            entry.do_not_translate = field(Column::DoNotTranslate).is_some_and(|value| {
+                matches!(
+                    value.to_lowercase().as_str(),
+                    "1" | "yes" | "y" | "true" | "x" | "dnt"
+                )
+            });
+            entry.forbidden = field(Column::Forbidden)
+                .map(|value| {
+                    value
+                        .split('|')
+                        .map(|f| f.trim().to_string())
+                        .filter(|f| !f.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            entries,
+            stemmer: None,
+        })
+    }
+
+    /// Parses a dictionary in TBX (TermBase eXchange) format, versions 2 and 3.
+    ///
+    /// English language set is used as source, the first other language set as target.
+    /// Target terms with deprecated or superseded status become forbidden translations.
+    pub fn from_tbx_str(text: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(text)
+            .with_context(|| tr!("Failed to parse TBX dictionary"))?;
+
+        let mut entries = Vec::new();
+        for concept in doc
+            .descendants()
+            .filter(|n| n.has_tag_name("termEntry") || n.has_tag_name("conceptEntry"))
+        {
+            let lang_sets: Vec<_> = concept
+                .children()
+                .filter(|n| n.has_tag_name("langSet") || n.has_tag_name("langSec"))
+                .collect();
+
+            let lang = |node: &roxmltree::Node| {
+                node.attribute(("http://www.w3.org/XML/1998/namespace", "lang"))
+                    .unwrap_or_default()
+                    .to_lowercase()
+            };
+            let Some(source) = lang_sets.iter().find(|n| lang(n).starts_with("en")) else {
+                continue;
+            };
+            let target = lang_sets.iter().find(|n| !lang(n).starts_with("en"));
+
+            let source_terms = tbx_terms(source);
+            let Some(source_term) = source_terms.first() else {
+                continue;
+            };
+
+            let mut translations = Vec::new();
+            let mut forbidden = Vec::new();
+            for term in target.map(tbx_terms).unwrap_or_default() {
+                if term.forbidden {
+                    forbidden.push(term.text);
+                } else {
+                    translations.push(term.text);
                 }
             }
+
+            let mut entry = DictionaryEntry::new(&source_term.text, &translations.join("|"), None)?;
+            entry.part_of_speech = source_term.part_of_speech.clone();
+            entry.note = tbx_descrip(&concept, &["definition", "note"])
+                .or_else(|| tbx_descrip(source, &["definition", "note"]));
+            entry.domain = tbx_descrip(&concept, &["subjectField", "domain"]);
+            entry.do_not_translate = translations.len() == 1 && translations[0] == entry.key;
+            entry.forbidden = forbidden;
+            entries.push(entry);
+        }
+
+        if entries.is_empty() && !doc.descendants().any(|n| n.has_tag_name("body")) {
+            bail!(tr!("Not a TBX dictionary: <body> element is not found."));
         }
 
-        Ok(Self { entries })
+        Ok(Self {
+            entries,
+            stemmer: None,
+        })
+    }
+
+    /// Sets stemmer to match inflected forms of terms, which have no own pattern.
+    pub fn with_stemmer(mut self, stemmer: Option<Arc<dyn Stemmer>>) -> Self {
+        for entry in &mut self.entries {
+            entry.stems = match &stemmer {
+                Some(stemmer) if !entry.custom_pattern => stem_words(stemmer.as_ref(), &entry.key),
+                _ => Vec::new(),
+            };
+        }
+        self.stemmer = stemmer;
+        self
     }
 
     /// Finds all dictionary terms that appear in the given text.
     ///
     /// Search is case-insensitive and respects word boundaries. It also
-    /// handles simple plurals (id + 's').
+    /// handles simple plurals (id + 's'), unless the entry has its own pattern.
+    /// With stemmer, terms are also found by stems of words, e.g. "entries" for "entry".
     pub fn find_matches<'a>(&'a self, text: &str) -> Vec<&'a DictionaryEntry> {
         let mut matches = Vec::new();
+        let text_stems = match &self.stemmer {
+            Some(stemmer) => stem_words(stemmer.as_ref(), text),
+            None => Vec::new(),
+        };
 
         for entry in &self.entries {
-            if entry.regex.is_match(text) {
+            let stems_match = !entry.stems.is_empty()
+                && text_stems
+                    .windows(entry.stems.len())
+                    .any(|window| window == entry.stems.as_slice());
+            if stems_match || entry.regex.is_match(text) {
                 matches.push(entry);
             }
         }
@@ -98,6 +467,52 @@ impl Dictionary {
     }
 }
 
+/// A term from TBX language set.
+struct TbxTerm {
+    text: String,
+    part_of_speech: Option<String>,
+    forbidden: bool,
+}
+
+/// Returns terms of TBX language set.
+fn tbx_terms(lang_set: &roxmltree::Node) -> Vec<TbxTerm> {
+    lang_set
+        .descendants()
+        .filter(|n| n.has_tag_name("tig") || n.has_tag_name("ntig") || n.has_tag_name("termSec"))
+        .filter_map(|tig| {
+            let text = tig
+                .descendants()
+                .find(|n| n.has_tag_name("term"))?
+                .text()?
+                .trim()
+                .to_string();
+            let term_note = |kind: &str| {
+                tig.descendants()
+                    .find(|n| n.has_tag_name("termNote") && n.attribute("type") == Some(kind))
+                    .and_then(|n| n.text())
+                    .map(|t| t.trim().to_string())
+            };
+            let status = term_note("administrativeStatus").unwrap_or_default();
+            Some(TbxTerm {
+                text,
+                part_of_speech: term_note("partOfSpeech"),
+                forbidden: status.starts_with("deprecated") || status.starts_with("superseded"),
+            })
+        })
+        .collect()
+}
+
+/// Returns text of the first `descrip` or `note` child element of given types.
+fn tbx_descrip(node: &roxmltree::Node, kinds: &[&str]) -> Option<String> {
+    node.children()
+        .find(|n| {
+            (n.has_tag_name("descrip") && n.attribute("type").is_some_and(|t| kinds.contains(&t)))
+                || (n.has_tag_name("note") && kinds.contains(&"note"))
+        })
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,22 +524,12 @@ mod tests {
         // Since we can't easily mock file system here without extra crates,
         // let's test `find_matches` with manually created dict.
 
-        let bug_re = Regex::new(r"(?i)\bbug(s)?\b").unwrap();
-        let feat_re = Regex::new(r"(?i)\bfeature(s)?\b").unwrap();
-
         let dict = Dictionary {
             entries: vec![
-                DictionaryEntry {
-                    key: "bug".to_string(),
-                    translation: "латка".to_string(),
-                    regex: bug_re,
-                },
-                DictionaryEntry {
-                    key: "feature".to_string(),
-                    translation: "можливість".to_string(),
-                    regex: feat_re,
-                },
+                DictionaryEntry::new("bug", "латка", None).unwrap(),
+                DictionaryEntry::new("feature", "можливість", None).unwrap(),
             ],
+            stemmer: None,
         };
 
         let matches = dict.find_matches("Fixing bugs in the system.");
@@ -138,4 +543,141 @@ mod tests {
         let matches = dict.find_matches("No match here.");
         assert_eq!(matches.len(), 0);
     }
+
+    #[test]
+    fn test_is_translated_in() {
+        let entry = DictionaryEntry::new("bug", "помилка | помилки|помилок", None).unwrap();
+
+        assert_eq!(
+            entry.translations().collect::<Vec<_>>(),
+            vec!["помилка", "помилки", "помилок"]
+        );
+        assert!(entry.is_translated_in("Помилка в системі"));
+        assert!(entry.is_translated_in("Виправлено 5 помилок."));
+        assert!(!entry.is_translated_in("Виправлено 5 помилоккк."));
+        assert!(!entry.is_translated_in("Виправлено 5 вад."));
+    }
+
+    #[test]
+    fn test_tsv_with_extra_columns() -> Result<()> {
+        let f = tempfile::Builder::new().suffix(".tsv").tempfile()?;
+        std::fs::write(
+            f.path(),
+            "term\ttranslation\tpos\tnote\tdomain\tdnt\tforbidden\tpattern\n\
+             bug\tпомилка\tnoun\t\tsoftware\t\tбаг\n\
+             Linux\t\t\t\t\tyes\n\
+             mouse\tмиша\t\t\t\t\t\t(?i)\\bmice\\b|\\bmouse\\b\n",
+        )?;
+        let dict = Dictionary::from_file(f.path())?;
+        assert_eq!(dict.entries.len(), 3);
+
+        let bug = &dict.entries[0];
+        assert_eq!(bug.part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(bug.domain.as_deref(), Some("software"));
+        assert_eq!(bug.forbidden, vec!["баг"]);
+        assert_eq!(bug.forbidden_in("Виправлено баг"), vec!["баг"]);
+        assert_eq!(
+            bug.describe(),
+            "bug - помилка (noun; domain: software; never use: баг)"
+        );
+
+        let linux = &dict.entries[1];
+        assert!(linux.do_not_translate);
+        assert!(linux.is_translated_in("Ядро Linux"));
+        assert_eq!(linux.describe(), "Linux - Linux (do not translate)");
+
+        assert_eq!(dict.find_matches("Three mice")[0].key, "mouse");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tsv_without_header() -> Result<()> {
+        let f = tempfile::Builder::new().suffix(".tsv").tempfile()?;
+        // "key" is a real term here, and third column is a part of translation, like before
+        std::fs::write(f.path(), "key\tключ\nfile\tфайл\tnot a folder\n")?;
+        let dict = Dictionary::from_file(f.path())?;
+        assert_eq!(dict.entries.len(), 2);
+        assert_eq!(dict.entries[0].key, "key");
+        assert_eq!(dict.entries[0].translation, "ключ");
+        assert_eq!(dict.entries[1].key, "file");
+        assert_eq!(dict.entries[1].translation, "файл\tnot a folder");
+        assert_eq!(dict.entries[1].part_of_speech, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_with_header() -> Result<()> {
+        let f = tempfile::Builder::new().suffix(".csv").tempfile()?;
+        std::fs::write(
+            f.path(),
+            "Term,Notes,Translation,Forbidden\n\
+             # comment\n\
+             file,\"Document, not a folder\",файл|файли,\n",
+        )?;
+        let dict = Dictionary::from_file(f.path())?;
+        assert_eq!(dict.entries.len(), 1);
+        assert_eq!(dict.entries[0].key, "file");
+        assert_eq!(dict.entries[0].translation, "файл|файли");
+        assert_eq!(
+            dict.entries[0].note.as_deref(),
+            Some("Document, not a folder")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tbx() -> Result<()> {
+        let dict = Dictionary::from_tbx_str(
+            r#"<?xml version="1.0"?>
+<martif type="TBX" xml:lang="en">
+  <text><body>
+    <termEntry id="1">
+      <descrip type="subjectField">software</descrip>
+      <langSet xml:lang="en">
+        <tig><term>bug</term><termNote type="partOfSpeech">noun</termNote></tig>
+      </langSet>
+      <langSet xml:lang="uk">
+        <tig><term>помилка</term><termNote type="administrativeStatus">preferredTerm-admn-sts</termNote></tig>
+        <tig><term>баг</term><termNote type="administrativeStatus">deprecatedTerm-admn-sts</termNote></tig>
+      </langSet>
+    </termEntry>
+  </body></text>
+</martif>"#,
+        )?;
+
+        assert_eq!(dict.entries.len(), 1);
+        let bug = &dict.entries[0];
+        assert_eq!(bug.key, "bug");
+        assert_eq!(bug.translation, "помилка");
+        assert_eq!(bug.forbidden, vec!["баг"]);
+        assert_eq!(bug.part_of_speech.as_deref(), Some("noun"));
+        assert_eq!(bug.domain.as_deref(), Some("software"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matches_with_stemmer() -> Result<()> {
+        let dict = Dictionary {
+            entries: vec![
+                DictionaryEntry::new("entry", "запис", None)?,
+                DictionaryEntry::new("index", "індекс", None)?,
+                DictionaryEntry::new("copy file", "копіювати файл", None)?,
+                DictionaryEntry::new("mouse", "миша", Some(r"\bmouse\b"))?,
+            ],
+            stemmer: None,
+        };
+
+        let text = "Copied files with indices of entries, and mice.";
+        assert!(dict.find_matches(text).is_empty());
+
+        let dict = dict.with_stemmer(crate::stemmer::stemmer_by_name("english")?);
+        let keys: Vec<&str> = dict
+            .find_matches(text)
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        // Entries with own pattern are not stemmed
+        assert_eq!(keys, vec!["entry", "index", "copy file"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,170 @@
+//! Translation by an ensemble of AI models.
+//!
+//! Each message is translated by several models. Candidates, which fail validation,
+//! are dropped, and the winner among the rest is chosen by voting or by an AI review.
+
+use crate::ai_backend::AiBackend;
+use crate::parser::{PoMessage, escape_comment};
+use anyhow::{Result, bail};
+use strsim::normalized_levenshtein;
+
+/// How to choose the winner among candidate translations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arbiter {
+    /// Majority and similarity voting: the candidate most similar to others wins.
+    Vote,
+    /// AI model reviews candidates and picks or fixes the best one, like the `review` command.
+    Review,
+}
+
+impl Arbiter {
+    /// Returns arbiter by its name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "vote" => Ok(Arbiter::Vote),
+            "review" => Ok(Arbiter::Review),
+            _ => bail!(
+                "{}",
+                tr!("Invalid argument for --arbiter option. Expected: vote or review. Actual value: \"{value}\".")
+                    .replace("{value}", name)
+            ),
+        }
+    }
+}
+
+/// AI models, which translate each message, and the way to choose the best translation.
+pub struct Ensemble {
+    /// Names of models with their backends.
+    pub backends: Vec<(String, AiBackend)>,
+    pub arbiter: Arbiter,
+}
+
+impl Ensemble {
+    pub fn new(backends: Vec<(String, AiBackend)>, arbiter: Arbiter) -> Self {
+        Self { backends, arbiter }
+    }
+}
+
+/// Candidate translation and the name of the model, which produced it.
+pub struct Candidate<'a> {
+    pub model: &'a str,
+    pub message: PoMessage,
+}
+
+/// Returns index of the candidate with the highest total similarity to other candidates.
+///
+/// Identical translations have similarity 1.0, so the majority wins, while similarity
+/// breaks ties between unique translations. On equal votes, the first candidate wins.
+pub fn vote(candidates: &[Candidate]) -> usize {
+    let texts: Vec<String> = candidates
+        .iter()
+        .map(|c| c.message.msgstr.join("\n"))
+        .collect();
+
+    let mut best = (0, f64::MIN);
+    for (i, text) in texts.iter().enumerate() {
+        let votes: f64 = texts
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, other)| normalized_levenshtein(text, other))
+            .sum();
+        if votes > best.1 {
+            best = (i, votes);
+        }
+    }
+    best.0
+}
+
+/// Formats candidates, which are different from the winner, as translator comments.
+pub fn alternatives_comments(winner: &PoMessage, candidates: &[Candidate]) -> Vec<String> {
+    let mut comments: Vec<String> = Vec::new();
+    for candidate in candidates {
+        if candidate.message.msgstr == winner.msgstr {
+            continue;
+        }
+        let msgstr = candidate
+            .message
+            .msgstr
+            .iter()
+            .map(|s| format!("\"{}\"", escape_comment(s)))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let comment = tr!("# Alternative by {model}: {msgstr}")
+            .replace("{model}", candidate.model)
+            .replace("{msgstr}", &msgstr);
+        if !comments.contains(&comment) {
+            comments.push(comment);
+        }
+    }
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(model: &'a str, msgstr: &str) -> Candidate<'a> {
+        Candidate {
+            model,
+            message: PoMessage {
+                msgid: "Open file".to_string(),
+                msgstr: vec![msgstr.to_string()],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_vote() {
+        // Majority wins
+        let candidates = [
+            candidate("m1", "Відкрити документ"),
+            candidate("m2", "Відкрити файл"),
+            candidate("m3", "Відкрити файл"),
+        ];
+        assert_eq!(vote(&candidates), 1);
+
+        // Translation most similar to others wins
+        let candidates = [
+            candidate("m1", "Закрити"),
+            candidate("m2", "Відкрити файл"),
+            candidate("m3", "Відкрити файли"),
+            candidate("m4", "Відкрити файлик"),
+        ];
+        assert_eq!(vote(&candidates), 2);
+
+        assert_eq!(vote(&[candidate("m1", "Відкрити файл")]), 0);
+    }
+
+    #[test]
+    fn test_alternatives_comments() {
+        let candidates = [
+            candidate("m1", "Відкрити файл"),
+            candidate("m2", "Відкрити \"файл\""),
+            candidate("m3", "Відкрити файл"),
+        ];
+        assert_eq!(
+            alternatives_comments(&candidates[0].message, &candidates),
+            vec!["# Alternative by m2: \"Відкрити \\\"файл\\\"\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_multiline_alternative() {
+        let candidates = [
+            candidate("m1", "Рядок один\nРядок два"),
+            candidate("m2", "Рядок 1\nРядок 2"),
+        ];
+        assert_eq!(
+            alternatives_comments(&candidates[0].message, &candidates),
+            vec!["# Alternative by m2: \"Рядок 1\\nРядок 2\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_arbiter_from_name() {
+        assert_eq!(Arbiter::from_name("review").unwrap(), Arbiter::Review);
+        assert!(Arbiter::from_name("random").is_err());
+    }
+}
@@ -0,0 +1,589 @@
+//! Parsers and checker for format strings in messages.
+//!
+//! Supports C printf (`%s`, `%1$d`), Python (`%(name)s`, `%s`), Qt (`%1`) and
+//! brace formats (`{}`, `{0}`, `{name}`) used by Python, Rust, and C#. Format of a message
+//! is selected by its `#, xxx-format` flag, or detected automatically.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::parser::PoMessage;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Kind of format strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    /// C printf: `%s`, `%5.2f`, `%1$d`.
+    C,
+    /// Python percent format: `%s`, `%(name)s`.
+    Python,
+    /// Qt format: `%1`, `%L2`.
+    Qt,
+    /// Brace format of Python, Rust, and C#: `{}`, `{0}`, `{name:>10}`.
+    Brace,
+}
+
+impl FormatKind {
+    /// Returns format kind for the gettext format flag, e.g. "c-format".
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "c-format" | "objc-format" | "sh-printf-format" => Some(FormatKind::C),
+            "python-format" => Some(FormatKind::Python),
+            "qt-format" | "qt-plural-format" | "kde-format" => Some(FormatKind::Qt),
+            "python-brace-format" | "rust-format" | "csharp-format" => Some(FormatKind::Brace),
+            _ => None,
+        }
+    }
+
+    /// Name of the format, as in gettext flags.
+    fn name(self) -> &'static str {
+        match self {
+            FormatKind::C => "c-format",
+            FormatKind::Python => "python-format",
+            FormatKind::Qt => "qt-format",
+            FormatKind::Brace => "brace-format",
+        }
+    }
+}
+
+/// Argument, referenced by a placeholder.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Argument {
+    /// Positional argument, starting from 1.
+    Position(usize),
+    /// Named argument.
+    Name(String),
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Argument::Position(n) => write!(f, "{n}"),
+            Argument::Name(name) => write!(f, "\"{name}\""),
+        }
+    }
+}
+
+/// A placeholder in format string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    /// Text of the placeholder, as written in the string.
+    pub text: String,
+    /// Referenced argument.
+    pub argument: Argument,
+    /// Type of the argument, normalized so compatible conversions are equal.
+    /// Empty, when the format has no types.
+    pub kind: String,
+}
+
+/// Returns the format of the message: from its flag, or detected by msgid.
+///
+/// Returns `None` when the message is not a format string, or is marked with `no-xxx-format` flag.
+pub fn message_format(message: &PoMessage) -> Option<FormatKind> {
+    let flags: Vec<&str> = message
+        .comments
+        .iter()
+        .filter_map(|c| c.strip_prefix("#,"))
+        .flat_map(|flags| flags.split(','))
+        .map(|flag| flag.trim())
+        .collect();
+
+    if let Some(kind) = flags.iter().find_map(|flag| FormatKind::from_flag(flag)) {
+        return Some(kind);
+    }
+    if flags
+        .iter()
+        .any(|flag| flag.starts_with("no-") && flag.ends_with("-format"))
+    {
+        return None;
+    }
+
+    detect_format(&message.msgid)
+}
+
+/// Returns true, when message has explicit format flag.
+pub fn has_format_flag(message: &PoMessage) -> bool {
+    message
+        .comments
+        .iter()
+        .filter_map(|c| c.strip_prefix("#,"))
+        .flat_map(|flags| flags.split(','))
+        .any(|flag| FormatKind::from_flag(flag.trim()).is_some())
+}
+
+/// Detects format of the string by its placeholders.
+pub fn detect_format(text: &str) -> Option<FormatKind> {
+    let parses = |kind| {
+        parse_format(kind, text)
+            .map(|placeholders| !placeholders.is_empty())
+            .unwrap_or(false)
+    };
+
+    if text.contains("%(") && parses(FormatKind::Python) {
+        Some(FormatKind::Python)
+    } else if parse_format(FormatKind::C, text).is_ok_and(|p| {
+        // "100% done" is not a format string, so space flag is not allowed here
+        !p.is_empty() && p.iter().all(|p| !p.text.contains(' '))
+    }) {
+        Some(FormatKind::C)
+    } else if parses(FormatKind::Qt) {
+        Some(FormatKind::Qt)
+    } else if parses(FormatKind::Brace) {
+        Some(FormatKind::Brace)
+    } else {
+        None
+    }
+}
+
+/// Parses placeholders of the format string.
+pub fn parse_format(kind: FormatKind, text: &str) -> Result<Vec<Placeholder>, String> {
+    match kind {
+        FormatKind::C => parse_printf(text, false),
+        FormatKind::Python => parse_printf(text, true),
+        FormatKind::Qt => Ok(parse_qt(text)),
+        FormatKind::Brace => parse_brace(text),
+    }
+}
+
+/// Parses C printf or Python percent format.
+fn parse_printf(text: &str, python: bool) -> Result<Vec<Placeholder>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut placeholders = Vec::new();
+    let mut next_position = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != '%' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+
+        if chars.get(i) == Some(&'%') {
+            i += 1;
+            continue;
+        }
+
+        // Argument: %(name)s in Python, %1$s in C
+        let mut argument = None;
+        if python && chars.get(i) == Some(&'(') {
+            let end = chars[i..]
+                .iter()
+                .position(|c| *c == ')')
+                .ok_or_else(|| tr!("unterminated argument name").to_string())?;
+            argument = Some(Argument::Name(chars[i + 1..i + end].iter().collect()));
+            i += end + 1;
+        } else if !python {
+            let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 && chars.get(i + digits) == Some(&'$') {
+                let position: String = chars[i..i + digits].iter().collect();
+                argument = Some(Argument::Position(position.parse().unwrap_or(0)));
+                i += digits + 1;
+            }
+        }
+
+        // Flags, width, precision
+        while i < chars.len() && "-+ #0'".contains(chars[i]) {
+            i += 1;
+        }
+        while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '*') {
+            i += 1;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '*') {
+                i += 1;
+            }
+        }
+
+        // Length modifier
+        let length_start = i;
+        while i < chars.len() && "hlLqjzt".contains(chars[i]) {
+            i += 1;
+        }
+        let length: String = chars[length_start..i].iter().collect();
+
+        let Some(&conversion) = chars.get(i) else {
+            return Err(tr!("incomplete format specification at the end of string").to_string());
+        };
+        i += 1;
+
+        let class = match conversion {
+            'd' | 'i' => "int",
+            'o' | 'u' | 'x' | 'X' if !python => "unsigned int",
+            'o' | 'u' | 'x' | 'X' => "int",
+            'r' | 'a' if python => "string",
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'a' | 'A' => "double",
+            'c' => "char",
+            's' => "string",
+            'p' if !python => "pointer",
+            'n' if !python => "count",
+            _ => {
+                return Err(
+                    tr!("unknown conversion \"{conversion}\" in \"{placeholder}\"")
+                        .replace("{conversion}", &conversion.to_string())
+                        .replace("{placeholder}", &chars[start..i].iter().collect::<String>()),
+                );
+            }
+        };
+
+        let argument = argument.unwrap_or_else(|| {
+            next_position += 1;
+            Argument::Position(next_position - 1)
+        });
+
+        placeholders.push(Placeholder {
+            text: chars[start..i].iter().collect(),
+            argument,
+            kind: format!("{length}{class}").trim().to_string(),
+        });
+    }
+
+    Ok(placeholders)
+}
+
+/// Parses Qt format: `%1` to `%99`, optionally with `L` for localized numbers.
+fn parse_qt(text: &str) -> Vec<Placeholder> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut placeholders = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != '%' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        if chars.get(i) == Some(&'L') {
+            i += 1;
+        }
+        let digits = chars[i..]
+            .iter()
+            .take(2)
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        let number: String = chars[i..i + digits].iter().collect();
+        match number.parse::<usize>() {
+            Ok(n) if n > 0 => {
+                i += digits;
+                placeholders.push(Placeholder {
+                    text: chars[start..i].iter().collect(),
+                    argument: Argument::Position(n),
+                    kind: String::new(),
+                });
+            }
+            _ => i = start + 1,
+        }
+    }
+
+    placeholders
+}
+
+/// Parses brace format: `{}`, `{0}`, `{name}`, `{name:fmt}`, `{0,10:N2}`, with `{{` and `}}` escapes.
+fn parse_brace(text: &str) -> Result<Vec<Placeholder>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut placeholders = Vec::new();
+    let mut next_position = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' if chars.get(i + 1) == Some(&'{') => i += 2,
+            '}' if chars.get(i + 1) == Some(&'}') => i += 2,
+            '{' => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|c| *c == '}')
+                    .ok_or_else(|| tr!("unterminated \"{\" placeholder").to_string())?;
+                let text: String = chars[i..=i + end].iter().collect();
+                let field: String = chars[i + 1..i + end]
+                    .iter()
+                    .take_while(|c| !matches!(c, ':' | ',' | '!'))
+                    .collect();
+                let field = field.trim();
+
+                let argument = if field.is_empty() {
+                    next_position += 1;
+                    Argument::Position(next_position - 1)
+                } else if let Ok(n) = field.parse::<usize>() {
+                    // Explicit indexes start from 0
+                    Argument::Position(n + 1)
+                } else if field
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '[' || c == ']')
+                {
+                    Argument::Name(field.to_string())
+                } else {
+                    return Err(tr!("invalid placeholder \"{placeholder}\"")
+                        .replace("{placeholder}", &text));
+                };
+
+                placeholders.push(Placeholder {
+                    text,
+                    argument,
+                    kind: String::new(),
+                });
+                i += end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    Ok(placeholders)
+}
+
+/// Checks format strings of the message, like `msgfmt -c` does.
+///
+/// Returns problems, when placeholders of msgstr don't match placeholders of msgid.
+/// Problems are errors for formats selected by the `#, xxx-format` flag, and warnings
+/// for detected formats, because text like "{curly} braces" might not be a format string.
+pub fn check_format(message: &PoMessage) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return errors;
+    }
+    let Some(kind) = message_format(message) else {
+        return errors;
+    };
+    let severity = if has_format_flag(message) {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+
+    for (n, msgstr) in message.msgstr.iter().enumerate() {
+        let msgid = match &message.msgid_plural {
+            Some(msgid_plural) if n > 0 => msgid_plural,
+            _ => &message.msgid,
+        };
+        check_strings(
+            kind,
+            severity,
+            msgid,
+            msgstr,
+            message.is_plural(),
+            &mut errors,
+        );
+    }
+    errors
+}
+
+/// Compares placeholders of msgid and msgstr, appending problems with the given severity.
+fn check_strings(
+    kind: FormatKind,
+    severity: Severity,
+    msgid: &str,
+    msgstr: &str,
+    plural: bool,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let src = match parse_format(kind, msgid) {
+        Ok(placeholders) => placeholders,
+        Err(e) => {
+            errors.push(Diagnostic::new(
+                severity,
+                tr!("msgid is not a valid {format} string: {error}.")
+                    .replace("{format}", kind.name())
+                    .replace("{error}", &e),
+            ));
+            return;
+        }
+    };
+    let dst = match parse_format(kind, msgstr) {
+        Ok(placeholders) => placeholders,
+        Err(e) => {
+            errors.push(Diagnostic::new(
+                severity,
+                tr!("msgstr is not a valid {format} string: {error}.")
+                    .replace("{format}", kind.name())
+                    .replace("{error}", &e),
+            ));
+            return;
+        }
+    };
+
+    let src = arguments(&src, "msgid", severity, errors);
+    let dst = arguments(&dst, "msgstr", severity, errors);
+
+    for (argument, placeholder) in &src {
+        match dst.get(argument) {
+            None if plural => {
+                // Plural forms may omit the number, e.g. "one file" instead of "%d file"
+            }
+            None => errors.push(Diagnostic::new(
+                severity,
+                tr!("Format specification \"{placeholder}\" for argument {argument} from msgid is missing in msgstr.")
+                    .replace("{placeholder}", &placeholder.text)
+                    .replace("{argument}", &argument.to_string()),
+            )),
+            Some(other) if other.kind != placeholder.kind => errors.push(Diagnostic::new(
+                severity,
+                tr!("Format specifications for argument {argument} have different types: \"{msgid_placeholder}\" in msgid, but \"{msgstr_placeholder}\" in msgstr.")
+                    .replace("{argument}", &argument.to_string())
+                    .replace("{msgid_placeholder}", &placeholder.text)
+                    .replace("{msgstr_placeholder}", &other.text),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    for (argument, placeholder) in &dst {
+        if !src.contains_key(argument) {
+            errors.push(Diagnostic::new(
+                severity,
+                tr!("Format specification \"{placeholder}\" in msgstr refers to argument {argument}, which is not in msgid.")
+                    .replace("{placeholder}", &placeholder.text)
+                    .replace("{argument}", &argument.to_string()),
+            ));
+        }
+    }
+}
+
+/// Groups placeholders by argument. Reports mixed positional and sequential arguments.
+fn arguments<'a>(
+    placeholders: &'a [Placeholder],
+    field: &str,
+    severity: Severity,
+    errors: &mut Vec<Diagnostic>,
+) -> BTreeMap<Argument, &'a Placeholder> {
+    let explicit = placeholders.iter().filter(|p| p.text.contains('$')).count();
+    if explicit > 0 && explicit < placeholders.len() {
+        errors.push(Diagnostic::new(
+            severity,
+            tr!("{field} mixes numbered (\"%1$s\") and unnumbered (\"%s\") format specifications.")
+                .replace("{field}", field),
+        ));
+    }
+
+    let mut map = BTreeMap::new();
+    for placeholder in placeholders {
+        map.entry(placeholder.argument.clone())
+            .or_insert(placeholder);
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use crate::parser::Parser;
+    use anyhow::Result;
+
+    fn check(text: &str) -> Result<Option<String>> {
+        let parser = Parser::new(None);
+        let errors = check_format(&parser.parse_message_from_str(text)?);
+        Ok((!errors.is_empty()).then(|| to_comments(&errors)))
+    }
+
+    #[test]
+    fn test_parse_printf() {
+        let placeholders = parse_format(FormatKind::C, "%s: %5.2f%% of %lu, %2$s").unwrap();
+        let texts: Vec<&str> = placeholders.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, vec!["%s", "%5.2f", "%lu", "%2$s"]);
+        assert_eq!(placeholders[2].kind, "lunsigned int");
+        assert_eq!(placeholders[3].argument, Argument::Position(2));
+
+        assert!(parse_format(FormatKind::C, "100%").is_err());
+        assert!(parse_format(FormatKind::C, "%y").is_err());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format("%(count)d files"), Some(FormatKind::Python));
+        assert_eq!(detect_format("%d files"), Some(FormatKind::C));
+        assert_eq!(detect_format("%1 of %2"), Some(FormatKind::Qt));
+        assert_eq!(detect_format("{count} files"), Some(FormatKind::Brace));
+        assert_eq!(detect_format("100% done"), None);
+        assert_eq!(detect_format("Plain text"), None);
+    }
+
+    #[test]
+    fn test_check_c_format() -> Result<()> {
+        assert_eq!(
+            check("#, c-format\nmsgid \"%s has %d files\"\nmsgstr \"%2$d файлів у %1$s\"\n")?,
+            None
+        );
+        // Punctuation changes are fine
+        assert_eq!(
+            check("#, c-format\nmsgid \"%s: done.\"\nmsgstr \"%s — готово!\"\n")?,
+            None
+        );
+
+        let errors =
+            check("#, c-format\nmsgid \"%s has %d files\"\nmsgstr \"%s має %s файлів\"\n")?
+                .unwrap();
+        assert_eq!(
+            errors,
+            "# Error: Format specifications for argument 2 have different types: \"%d\" in msgid, but \"%s\" in msgstr.\n"
+        );
+
+        let errors = check("#, c-format\nmsgid \"%s\"\nmsgstr \"%s %3$d\"\n")?.unwrap();
+        assert!(errors.contains("mixes numbered"));
+        assert!(errors.contains("refers to argument 3, which is not in msgid"));
+
+        let errors = check("#, c-format\nmsgid \"%s and %s\"\nmsgstr \"%s\"\n")?.unwrap();
+        assert!(errors.contains("\"%s\" for argument 2 from msgid is missing in msgstr"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_other_formats() -> Result<()> {
+        assert_eq!(
+            check("msgid \"%(count)d of %(total)d\"\nmsgstr \"%(total)d з %(count)d\"\n")?,
+            None
+        );
+        assert!(check("#, python-format\nmsgid \"%(name)s\"\nmsgstr \"%(nam)s\"\n")?.is_some());
+
+        assert_eq!(
+            check("#, qt-format\nmsgid \"%1 of %2\"\nmsgstr \"%2: %1\"\n")?,
+            None
+        );
+        assert!(check("#, qt-format\nmsgid \"%1 of %2\"\nmsgstr \"%1 з %3\"\n")?.is_some());
+
+        assert_eq!(
+            check(
+                "#, rust-format\nmsgid \"{name} has {} files {{}}\"\nmsgstr \"{} файлів у {name}\"\n"
+            )?,
+            None
+        );
+        assert!(check("#, csharp-format\nmsgid \"{0} of {1}\"\nmsgstr \"{0} з {2}\"\n")?.is_some());
+
+        // Flag disables detection
+        assert_eq!(
+            check("#, no-c-format\nmsgid \"%d\"\nmsgstr \"%s\"\n")?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detected_format_warnings() -> Result<()> {
+        let parser = Parser::new(None);
+        let text =
+            "msgid \"Use {curly} braces to group\"\nmsgstr \"Використовуйте {фігурні} дужки\"\n";
+        let warnings = check_format(&parser.parse_message_from_str(text)?);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().all(|w| w.severity == Severity::Warning));
+
+        let errors = check_format(
+            &parser.parse_message_from_str(&format!("#, python-brace-format\n{text}"))?,
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.severity == Severity::Error));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_plural() -> Result<()> {
+        let text = "#, c-format\nmsgid \"%d file\"\nmsgid_plural \"%d files\"\nmsgstr[0] \"один файл\"\nmsgstr[1] \"%d файли\"\nmsgstr[2] \"%s файлів\"\n";
+        let parser = Parser::new(Some(3));
+        let errors = check_format(&parser.parse_message_from_str(text)?);
+        assert_eq!(
+            to_comments(&errors),
+            "# Error: Format specifications for argument 1 have different types: \"%d\" in msgid, but \"%s\" in msgstr.\n"
+        );
+        Ok(())
+    }
+}
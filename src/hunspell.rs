@@ -0,0 +1,296 @@
+//! Hunspell dictionaries (`.aff` and `.dic` files), for spell-checking.
+//!
+//! Dictionaries are found in standard locations and decoded according to their `SET`
+//! option. Checking and suggestions are done by the `spellbook` crate.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Hunspell dictionary.
+pub struct Hunspell {
+    dictionary: spellbook::Dictionary,
+}
+
+/// Standard locations of Hunspell dictionaries.
+const DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+/// Finds `.aff` and `.dic` files of the dictionary.
+///
+/// `name` is either a path to the dictionary without extension (or to its `.dic` file),
+/// or a name like "uk_UA", which is searched in `extra_dirs`, in directories from
+/// `DICPATH` environment variable, and in standard locations.
+pub fn find_dictionary(name: &str, extra_dirs: &[&str]) -> Result<(PathBuf, PathBuf)> {
+    let base = name
+        .strip_suffix(".dic")
+        .or_else(|| name.strip_suffix(".aff"))
+        .unwrap_or(name);
+
+    let mut candidates = vec![PathBuf::from(base)];
+    let env_dirs = std::env::var("DICPATH").unwrap_or_default();
+    let dirs = extra_dirs
+        .iter()
+        .copied()
+        .chain(env_dirs.split(':').filter(|d| !d.is_empty()))
+        .chain(DICTIONARY_DIRS.iter().copied());
+    if !base.contains('/') {
+        candidates.extend(dirs.map(|dir| Path::new(dir).join(base)));
+    }
+
+    for candidate in candidates {
+        let aff = candidate.with_extension("aff");
+        let dic = candidate.with_extension("dic");
+        if aff.is_file() && dic.is_file() {
+            return Ok((aff, dic));
+        }
+    }
+
+    bail!(
+        "{}",
+        tr!("Cannot find Hunspell dictionary \"{name}\" (\"{name}.aff\" and \"{name}.dic\"). Use --dict-dir DIR option or DICPATH environment variable.")
+            .replace("{name}", base)
+    )
+}
+
+/// Decodes text of a dictionary file, according to the `SET` option.
+fn decode(bytes: &[u8], encoding: &str) -> Result<String> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    // Hunspell names most encodings by their standard labels, e.g. "ISO8859-1" or "KOI8-U"
+    let label = match encoding.to_lowercase().as_str() {
+        "" => "utf-8".to_string(),
+        "microsoft-cp1251" => "windows-1251".to_string(),
+        label => label.to_string(),
+    };
+    match encoding_rs::Encoding::for_label(label.as_bytes()) {
+        Some(encoding) => Ok(encoding
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()),
+        None => bail!(
+            "{}",
+            tr!("Unsupported encoding of Hunspell dictionary: \"{encoding}\". Convert the dictionary to UTF-8.")
+                .replace("{encoding}", encoding)
+        ),
+    }
+}
+
+impl Hunspell {
+    /// Loads dictionary from `.aff` and `.dic` files.
+    pub fn from_files(aff: &Path, dic: &Path) -> Result<Self> {
+        let aff_bytes = fs::read(aff).with_context(|| {
+            tr!("Cannot read file \"{file}\".").replace("{file}", &aff.display().to_string())
+        })?;
+        let dic_bytes = fs::read(dic).with_context(|| {
+            tr!("Cannot read file \"{file}\".").replace("{file}", &dic.display().to_string())
+        })?;
+
+        // Encoding is specified in .aff file, which is ASCII compatible
+        let encoding = String::from_utf8_lossy(&aff_bytes)
+            .lines()
+            .find_map(|line| line.strip_prefix("SET ").map(|e| e.trim().to_string()))
+            .unwrap_or_default();
+
+        Self::from_str(
+            &decode(&aff_bytes, &encoding)?,
+            &decode(&dic_bytes, &encoding)?,
+        )
+        .with_context(|| {
+            tr!("Cannot load Hunspell dictionary \"{file}\".")
+                .replace("{file}", &aff.display().to_string())
+        })
+    }
+
+    /// Parses dictionary from the text of `.aff` and `.dic` files.
+    pub fn from_str(aff: &str, dic: &str) -> Result<Self> {
+        let dictionary = spellbook::Dictionary::new(aff, dic).map_err(|e| anyhow!("{e}"))?;
+        Ok(Self { dictionary })
+    }
+
+    /// Adds word to the dictionary at runtime, e.g. from ignore list.
+    pub fn add_word(&mut self, word: &str) -> Result<()> {
+        // Slash separates flags in .dic entries
+        self.dictionary
+            .add(&word.replace('/', "\\/"))
+            .map_err(|e| anyhow!("{e}"))
+            .with_context(|| {
+                tr!("Cannot add word \"{word}\" to Hunspell dictionary.").replace("{word}", word)
+            })
+    }
+
+    /// Checks spelling of the word.
+    pub fn check(&self, word: &str) -> bool {
+        self.dictionary.check(word)
+    }
+
+    /// Returns up to `max` suggestions for the misspelled word.
+    pub fn suggest(&self, word: &str, max: usize) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        self.dictionary.suggest(word, &mut suggestions);
+        suggestions.truncate(max);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_AFF: &str = "SET UTF-8
+TRY оаеиінтсрвлкмдпу
+FORBIDDENWORD !
+ICONV 1
+ICONV ’ '
+REP 1
+REP фаил файл
+SFX A Y 2
+SFX A 0 и [^ьй]
+SFX A ь і ь
+PFX B Y 1
+PFX B 0 пере .
+";
+
+    const TEST_DIC: &str = "5
+файл/A
+відкрити/B
+тінь/A
+п'ять
+дурниця/!
+";
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let dict = Hunspell::from_str(TEST_AFF, TEST_DIC)?;
+        for word in [
+            "файл",
+            "файли",
+            "Файли",
+            "ФАЙЛИ",
+            "тіні",
+            "перевідкрити",
+            "п’ять",
+        ] {
+            assert!(dict.check(word), "{word} must be correct");
+        }
+        for word in ["фаил", "тіньи", "перефайл", "дурниця", "відкритии"]
+        {
+            assert!(!dict.check(word), "{word} must be misspelled");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_suggest() -> Result<()> {
+        let dict = Hunspell::from_str(TEST_AFF, TEST_DIC)?;
+        assert_eq!(dict.suggest("фаил", 5), vec!["файл"]);
+        assert_eq!(dict.suggest("Фйали", 5), vec!["Файли"]);
+        assert!(
+            dict.suggest("віідкрити", 5)
+                .contains(&"відкрити".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flag_aliases() -> Result<()> {
+        let aff = "AF 2
+AF A
+AF AB
+AM 1
+AM po:noun
+SFX A Y 1
+SFX A 0 s .
+SFX B Y 1
+SFX B 0 ful/1 .
+";
+        let dict = Hunspell::from_str(aff, "2\nfile/1 1\nhope/2\n")?;
+        for word in ["file", "files", "hopes", "hopeful", "hopefuls"] {
+            assert!(dict.check(word), "{word} must be correct");
+        }
+        for word in ["file1", "fileful", "hopefulful"] {
+            assert!(!dict.check(word), "{word} must be misspelled");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_case() -> Result<()> {
+        let dict = Hunspell::from_str("KEEPCASE K\n", "2\nkg/K\nfile\n")?;
+        assert!(dict.check("kg"));
+        assert!(!dict.check("KG"));
+        assert!(!dict.check("Kg"));
+        assert!(dict.check("FILE"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_flags() -> Result<()> {
+        let aff = "COMPOUNDFLAG X
+COMPOUNDMIN 3
+ONLYINCOMPOUND O
+SFX A Y 1
+SFX A 0 s .
+";
+        let dict = Hunspell::from_str(aff, "3\nfoot/XA\nball/XA\nberg/XO\n")?;
+        for word in ["football", "footballs", "ballfootball", "footberg"] {
+            assert!(dict.check(word), "{word} must be correct");
+        }
+        // Suffix inside of a compound word, and a word, which is allowed in compounds only
+        for word in ["footsball", "berg", "footbal"] {
+            assert!(!dict.check(word), "{word} must be misspelled");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_rule() -> Result<()> {
+        let aff = "COMPOUNDMIN 1
+ONLYINCOMPOUND c
+COMPOUNDRULE 1
+COMPOUNDRULE n*1t
+";
+        let dict = Hunspell::from_str(aff, "3\n0/n\n1/n1\nst/tc\n")?;
+        assert!(dict.check("1st"));
+        assert!(dict.check("101st"));
+        assert!(!dict.check("10st"));
+        assert!(!dict.check("st"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_word() -> Result<()> {
+        let mut dict = Hunspell::from_str(TEST_AFF, TEST_DIC)?;
+        assert!(!dict.check("PO/MO"));
+        dict.add_word("PO/MO")?;
+        assert!(dict.check("PO/MO"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xf4\xc1\xcb", "KOI8-U").unwrap(), "Так");
+        assert!(decode(b"", "ISCII-DEVANAGARI").is_err());
+    }
+
+    #[test]
+    fn test_find_dictionary() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("xx_XX.aff"), TEST_AFF)?;
+        fs::write(dir.path().join("xx_XX.dic"), TEST_DIC)?;
+
+        let dir_name = dir.path().to_str().unwrap();
+        let (aff, dic) = find_dictionary("xx_XX", &[dir_name])?;
+        assert!(aff.ends_with("xx_XX.aff") && dic.ends_with("xx_XX.dic"));
+
+        let dict = Hunspell::from_files(&aff, &dic)?;
+        assert!(dict.check("файли"));
+
+        assert!(find_dictionary("missing_XX", &[dir_name]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Length constraints of translations, e.g. for labels of buttons on small displays.
+//!
+//! Limits are taken from the `#, range: MIN..MAX` flag and from the `#. max-length: N`
+//! extracted comment of a message. In plural messages, `range:` is the range of values of
+//! the plural argument, as produced by xgettext, so it's not used as a length limit there. The maximum ratio of msgstr length to msgid length
+//! is set by the global `--max-ratio` option.
+
+use crate::diagnostics::Diagnostic;
+use crate::parser::{PoMessage, escape_comment};
+
+/// Messages with shorter msgid are not checked by ratio, because translation of a short
+/// word, e.g. "OK", is often a few times longer.
+const MIN_LENGTH_FOR_RATIO: usize = 10;
+
+/// Returns minimum and maximum length of msgstr, from message flags and extracted comments.
+pub fn length_limits(message: &PoMessage) -> (Option<usize>, Option<usize>) {
+    let mut min = None;
+    let mut max: Option<usize> = None;
+    // The strictest limit wins, when both are given
+    let mut limit_max = |limit: usize| max = Some(max.map_or(limit, |max| max.min(limit)));
+
+    for comment in &message.comments {
+        if let Some(flags) = comment.strip_prefix("#,")
+            && message.msgid_plural.is_none()
+        {
+            for flag in flags.split(',') {
+                if let Some((from, to)) = flag
+                    .trim()
+                    .strip_prefix("range:")
+                    .and_then(|range| range.trim().split_once(".."))
+                {
+                    min = from.trim().parse().ok().or(min);
+                    if let Ok(to) = to.trim().parse() {
+                        limit_max(to);
+                    }
+                }
+            }
+        } else if let Some(text) = comment.strip_prefix("#.")
+            && let Some(limit) = text.trim().strip_prefix("max-length:")
+            && let Ok(limit) = limit.trim().parse::<usize>()
+        {
+            limit_max(limit);
+        }
+    }
+
+    (min, max)
+}
+
+/// Checks length of msgstr against limits of the message and the maximum ratio to msgid length.
+///
+/// Length is measured in characters. Returns errors for violations.
+pub fn check_length(message: &PoMessage, max_ratio: Option<f64>) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    if message.is_header() || !message.is_translated() {
+        return errors;
+    }
+
+    let (min, max) = length_limits(message);
+
+    for (n, msgstr) in message.msgstr.iter().enumerate() {
+        let msgid = match &message.msgid_plural {
+            Some(msgid_plural) if n > 0 => msgid_plural,
+            _ => &message.msgid,
+        };
+        let length = msgstr.chars().count();
+
+        if let Some(max) = max
+            && length > max
+        {
+            errors.push(Diagnostic::error(
+                tr!("msgstr is too long: {length} characters, but maximum length is {max}. Actual msgstr: \"{actual}\"")
+                    .replace("{length}", &length.to_string())
+                    .replace("{max}", &max.to_string())
+                    .replace("{actual}", &escape_comment(msgstr)),
+            ));
+        }
+        if let Some(min) = min
+            && length < min
+        {
+            errors.push(Diagnostic::error(
+                tr!("msgstr is too short: {length} characters, but minimum length is {min}. Actual msgstr: \"{actual}\"")
+                    .replace("{length}", &length.to_string())
+                    .replace("{min}", &min.to_string())
+                    .replace("{actual}", &escape_comment(msgstr)),
+            ));
+        }
+
+        let msgid_length = msgid.chars().count();
+        if let Some(max_ratio) = max_ratio
+            && msgid_length >= MIN_LENGTH_FOR_RATIO
+        {
+            let ratio = length as f64 / msgid_length as f64;
+            if ratio > max_ratio {
+                errors.push(Diagnostic::error(
+                    tr!("msgstr is {ratio} times longer than msgid, but maximum ratio is {max_ratio}. Actual msgstr: \"{actual}\"")
+                        .replace("{ratio}", &format!("{ratio:.1}"))
+                        .replace("{max_ratio}", &max_ratio.to_string())
+                        .replace("{actual}", &escape_comment(msgstr)),
+                ));
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::to_comments;
+    use crate::parser::Parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_length_limits() -> Result<()> {
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str(
+            "#. max-length: 20\n#, c-format, range: 2..30\nmsgid \"a\"\nmsgstr \"b\"\n",
+        )?;
+        assert_eq!(length_limits(&message), (Some(2), Some(20)));
+
+        // Range of the plural argument is not a length limit
+        let message = parser.parse_message_from_str(
+            "#, c-format, range: 2..5\nmsgid \"%d file\"\nmsgid_plural \"%d files\"\nmsgstr[0] \"%d файл\"\nmsgstr[1] \"%d файли\"\nmsgstr[2] \"%d файлів\"\n",
+        )?;
+        assert_eq!(length_limits(&message), (None, None));
+        assert!(check_length(&message, None).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_length() -> Result<()> {
+        let parser = Parser::new(None);
+
+        let message = parser
+            .parse_message_from_str("#. max-length: 8\nmsgid \"Save\"\nmsgstr \"Зберегти\"\n")?;
+        assert!(check_length(&message, None).is_empty());
+
+        let message = parser.parse_message_from_str(
+            "#. max-length: 8\nmsgid \"Save all\"\nmsgstr \"Зберегти все\"\n",
+        )?;
+        assert_eq!(
+            to_comments(&check_length(&message, None)),
+            "# Error: msgstr is too long: 12 characters, but maximum length is 8. Actual msgstr: \"Зберегти все\"\n"
+        );
+
+        let message =
+            parser.parse_message_from_str("#, range: 3..10\nmsgid \"Yes\"\nmsgstr \"Так\"\n")?;
+        assert!(check_length(&message, None).is_empty());
+        let message =
+            parser.parse_message_from_str("#, range: 3..10\nmsgid \"Yes\"\nmsgstr \"Да\"\n")?;
+        assert!(check_length(&message, None)[0].text.contains("too short"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_ratio() -> Result<()> {
+        let parser = Parser::new(None);
+
+        let message = parser.parse_message_from_str(
+            "msgid \"Open a file\"\nmsgstr \"Відкрити файл з диска або з мережевого ресурсу\"\n",
+        )?;
+        assert!(
+            check_length(&message, Some(2.0))[0]
+                .text
+                .contains("times longer than msgid, but maximum ratio is 2.")
+        );
+        assert!(check_length(&message, None).is_empty());
+
+        // Short messages are not checked by ratio
+        let message = parser.parse_message_from_str("msgid \"OK\"\nmsgstr \"Гаразд\"\n")?;
+        assert!(check_length(&message, Some(2.0)).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiline_msgstr() -> Result<()> {
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str(
+            "#. max-length: 5\nmsgid \"Line one\\nLine two\"\nmsgstr \"Рядок один\\nРядок два\"\n",
+        )?;
+        let errors = to_comments(&check_length(&message, None));
+        assert_eq!(
+            errors,
+            "# Error: msgstr is too long: 20 characters, but maximum length is 5. Actual msgstr: \"Рядок один\\nРядок два\"\n"
+        );
+
+        // Comments with errors don't break the message
+        let text = format!("{errors}{message}");
+        assert_eq!(parser.parse_message_from_str(&text)?.msgstr, message.msgstr);
+        Ok(())
+    }
+}
@@ -59,16 +59,69 @@ use crate::command_translate_and_print::command_translate_and_print;
 mod command_review_files_and_print;
 use crate::command_review_files_and_print::command_review_files_and_print;
 
+mod command_score_with_ai_and_print;
+use crate::command_score_with_ai_and_print::command_score_with_ai_and_print;
+
+mod command_pretranslate_and_print;
+use crate::command_pretranslate_and_print::command_pretranslate_and_print;
+
+mod command_compendium;
+use crate::command_compendium::command_compendium;
+
 mod command_erase_and_print;
 use crate::command_erase_and_print::command_erase_and_print;
 
 mod command_check_symbols;
 use crate::command_check_symbols::command_check_symbols;
 
+mod command_check_format;
+use crate::command_check_format::command_check_format;
+
+mod command_check_markup;
+use crate::command_check_markup::command_check_markup;
+
+mod command_check_accelerators;
+use crate::command_check_accelerators::command_check_accelerators;
+
+mod command_lint;
+use crate::command_lint::command_lint;
+
+mod command_spellcheck;
+use crate::command_spellcheck::command_spellcheck;
+
+mod command_check_length;
+use crate::command_check_length::command_check_length;
+
+mod command_check_consistency;
+use crate::command_check_consistency::command_check_consistency;
+
+mod command_check_terms;
+use crate::command_check_terms::command_check_terms;
+
 mod util;
 
+mod diagnostics;
+
+mod ai_backend;
+
 mod dictionary;
 
+mod ensemble;
+
+mod stemmer;
+
+mod format_string;
+
+mod length;
+
+mod hunspell;
+
+mod back_translation;
+
+mod source_context;
+
+mod translation_memory;
+
 fn main() -> Result<()> {
     // Initial localization call
     localization::load_translations(&Parser::new(None));
@@ -76,6 +129,7 @@ fn main() -> Result<()> {
     // Options
     let mut number_of_plural_cases: Option<usize> = None;
     let mut strip_comments = false;
+    let mut options = util::GlobalOptions::default();
 
     // Parse arguments
     let args = std::env::args().collect::<Vec<String>>();
@@ -106,6 +160,20 @@ fn main() -> Result<()> {
                 strip_comments = true;
                 tail = rest;
             }
+            ["--format", name, ref rest @ ..] => {
+                options.output_format = diagnostics::OutputFormat::from_name(name)?;
+                tail = rest;
+            }
+            ["--max-ratio", ratio, ref rest @ ..] => {
+                match ratio.parse::<f64>() {
+                    Ok(ratio) if ratio > 0.0 => {
+                        options.max_length_ratio = Some(ratio);
+                        tail = rest;
+                    }
+                    _ => bail!(tr!("Invalid argument for --max-ratio option. Expected: positive number, e.g. 1.5. Actual value: \"{value}\".")
+              .replace("{value}", ratio)),
+                }
+            }
 
             ["-h", ..] | ["--help", ..] => {
                 help(&mut ctx)?;
@@ -129,9 +197,18 @@ fn main() -> Result<()> {
     // Parse arguments
     match tail[..] {
         ["parse", ref cmdline @ ..] => command_parse_and_dump(&parser, cmdline, &mut ctx)?,
-        ["translate", ref cmdline @ ..] => command_translate_and_print(&parser, cmdline, &mut ctx)?,
+        ["translate", ref cmdline @ ..] => {
+            command_translate_and_print(&parser, &options, cmdline, &mut ctx)?
+        }
         ["erase", ref cmdline @ ..] => command_erase_and_print(&parser, cmdline, &mut ctx)?,
-        ["review", ref cmdline @ ..] => command_review_files_and_print(&parser, cmdline, &mut ctx)?,
+        ["review", ref cmdline @ ..] => {
+            command_review_files_and_print(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["score", ref cmdline @ ..] => command_score_with_ai_and_print(&parser, cmdline, &mut ctx)?,
+        ["pretranslate", ref cmdline @ ..] => {
+            command_pretranslate_and_print(&parser, cmdline, &mut ctx)?
+        }
+        ["compendium", ref cmdline @ ..] => command_compendium(&parser, cmdline, &mut ctx)?,
         ["compare", ref cmdline @ ..] => {
             command_compare_files_and_print(&parser, cmdline, &mut ctx)?
         }
@@ -156,9 +233,33 @@ fn main() -> Result<()> {
             command_print_with_wordstr(&parser, cmdline, &mut ctx)?
         }
         ["with-unequal-linebreaks", ref cmdline @ ..] => {
-            command_print_with_unequal_linebreaks(&parser, cmdline, &mut ctx)?
+            command_print_with_unequal_linebreaks(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-symbols", ref cmdline @ ..] => {
+            command_check_symbols(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-format", ref cmdline @ ..] => {
+            command_check_format(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-length", ref cmdline @ ..] => {
+            command_check_length(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-markup", ref cmdline @ ..] => {
+            command_check_markup(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-accelerators", ref cmdline @ ..] => {
+            command_check_accelerators(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["lint", ref cmdline @ ..] => command_lint(&parser, &options, cmdline, &mut ctx)?,
+        ["spellcheck", ref cmdline @ ..] => {
+            command_spellcheck(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-consistency", ref cmdline @ ..] => {
+            command_check_consistency(&parser, &options, cmdline, &mut ctx)?
+        }
+        ["check-terms", ref cmdline @ ..] => {
+            command_check_terms(&parser, &options, cmdline, &mut ctx)?
         }
-        ["check-symbols", ref cmdline @ ..] => command_check_symbols(&parser, cmdline, &mut ctx)?,
 
         ["help", ..] | [] => help(&mut ctx)?,
         [arg, ..] => bail!(
@@ -182,6 +283,9 @@ COMMANDS
 
   * translate [OPTIONS] FILE - WIP! Translate PO file using AI.
   * review [OPTIONS] FILE [FILE...] - WIP! Review multiple translations of _same_ file using AI.
+  * score [OPTIONS] FILE... - Rate translations from 0 to 100 using AI, and mark ones below threshold as fuzzy.
+  * pretranslate --tm TM_FILE [OPTIONS] FILE... - Fill untranslated messages using translation memory only.
+  * compendium [-o FILE] DIR_OR_FILE... - Collect translations from many PO files into one translation memory file.
   * compare FILE1 FILE[...] - List different variants of translation for the same file.
 
   * merge FILE1 FILE2 - Merge two files by overwriting messages from FILE1 with messages from FILE2.
@@ -202,6 +306,14 @@ COMMANDS
   * with-wordstr WORD FILE - Print messages with given word in msgstr.
   * with-unequal-linebreaks - Print messages where msgstr does not contain same number of linebreaks as msgid.
   * check-symbols - Print messages where special symbols are not same.
+  * check-format FILE... - Print messages where format strings (printf, Python, Qt, brace) don't match, like `msgfmt -c`.
+  * check-markup FILE... - Print messages where HTML/XML/Pango tags, attributes, or entities don't match.
+  * check-accelerators [-m MARKER] FILE... - Print messages with missing or extra accelerator keys (`&File`, `_Open`), and duplicate accelerators in the same menu.
+  * lint [-l LANG] [--disable RULES] FILE... - Check whitespace, capitalization, punctuation, ellipsis, and quotes of translations.
+  * spellcheck --dict DICT [--ignore FILE] FILE... - Check spelling of msgstr with Hunspell dictionaries and suggest corrections.
+  * check-length FILE... - Print messages where msgstr violates `#, range: MIN..MAX` or `#. max-length: N` limits, or the --max-ratio option.
+  * check-consistency FILE... - Print msgids with different translations and translations used for different msgids.
+  * check-terms -d DICT_FILE FILE... - Print messages where glossary terms are not translated as in the dictionary.
 
   * sort FILE - Sort messages in lexical order.
   * parse - Parse file and dump (for debugging).
@@ -210,6 +322,10 @@ OPTIONS
 
   -c | --cases PLURAL_CASES    Number of plural cases to use in messages. If message has fewer than PLURAL_CASES, then empty ones will be added.
   --strip-comments             Strip comments from PO files during parsing (ignore all lines starting with #).
+  --format po|json|sarif|gcc   Output format of problems found by check commands (check-*, lint, spellcheck,
+                               with-unequal-linebreaks). Default: po, i.e. comments before messages.
+  --max-ratio RATIO            Maximum ratio of msgstr length to msgid length, e.g. 1.5. Longer translations
+                               are reported by check-length, and sent back to AI by translate and review.
 "#
         )
     )?;
@@ -142,6 +142,24 @@ pub fn escape_string(s: &str) -> String {
     result
 }
 
+/// Escapes a string for use in a single-line comment, e.g. to quote msgstr in an error message.
+///
+/// Unlike `escape_string()`, newlines are always escaped, so text cannot break out of the comment.
+pub fn escape_comment(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\r' => result.push_str("\\r"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
 impl std::fmt::Display for PoMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Comments
@@ -586,9 +604,22 @@ impl Parser {
         &self,
         stream: impl std::io::BufRead,
     ) -> Result<Vec<PoMessage>> {
+        Ok(self
+            .parse_numbered_messages_from_stream(stream)?
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect())
+    }
+
+    /// Parses multiple messages from a stream, with number of the first line of each message.
+    fn parse_numbered_messages_from_stream(
+        &self,
+        stream: impl std::io::BufRead,
+    ) -> Result<Vec<(usize, PoMessage)>> {
         // Read lines from stdin, break at empty line, parse message
-        let mut messages: Vec<PoMessage> = Vec::new();
+        let mut messages: Vec<(usize, PoMessage)> = Vec::new();
         let mut buf = String::new();
+        let mut first_line = 0;
         for (line_number, line) in stream.lines().enumerate() {
             let line = line?;
             let line = line.trim();
@@ -597,14 +628,16 @@ impl Parser {
                 let message = self.parse_message_from_str(&buf).context(format!(
                     "Cannot parse message at line #{line_number}. Message:\n\n{buf}"
                 ))?;
-                messages.push(message);
+                messages.push((first_line, message));
 
                 buf.truncate(0);
             } else {
                 if line.starts_with('#') && self.strip_comments {
                     continue;
                 }
-                if !buf.is_empty() {
+                if buf.is_empty() {
+                    first_line = line_number + 1;
+                } else {
                     buf += "\n";
                 }
                 buf += line;
@@ -614,7 +647,7 @@ impl Parser {
             let message = self.parse_message_from_str(&buf).context(format!(
                 "Cannot parse message at end of stream. Message:\n\n{buf}"
             ))?;
-            messages.push(message);
+            messages.push((first_line, message));
         }
 
         Ok(messages)
@@ -638,6 +671,19 @@ impl Parser {
         }
     }
 
+    /// Parses multiple messages from a file, with number of the first line of each message,
+    /// for diagnostics. If path is "-", reads from stdin.
+    pub fn parse_numbered_messages_from_file(&self, file: &str) -> Result<Vec<(usize, PoMessage)>> {
+        if file == "-" {
+            self.parse_numbered_messages_from_stream(std::io::stdin().lock())
+        } else {
+            let f = std::fs::File::open(file)?;
+            let f = std::io::BufReader::new(f);
+
+            self.parse_numbered_messages_from_read(f)
+        }
+    }
+
     /// Parses messages from a stream with BOM detection.
     ///
     /// This method reads the stream, checks for a Byte Order Mark (BOM),
@@ -650,6 +696,18 @@ impl Parser {
     /// It is private for now and is used for unit testing.
     /// If we decide to make it public, we would need to come with better name.
     fn parse_messages_from_read(&self, f: impl Read + Seek) -> Result<Vec<PoMessage>> {
+        Ok(self
+            .parse_numbered_messages_from_read(f)?
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect())
+    }
+
+    /// Parses messages from a stream with BOM detection, with number of the first line of each message.
+    fn parse_numbered_messages_from_read(
+        &self,
+        f: impl Read + Seek,
+    ) -> Result<Vec<(usize, PoMessage)>> {
         let mut f = std::io::BufReader::new(f);
 
         // The maximum byte length of BOM is 4 bytes
@@ -675,7 +733,7 @@ impl Parser {
         // If Bom is `Bom::Null` then we would read a file from the start, otherwise we would have real BOM length
         f.seek(SeekFrom::Start(bom.len() as u64))?;
 
-        self.parse_messages_from_stream(f)
+        self.parse_numbered_messages_from_stream(f)
     }
 }
 
@@ -684,6 +742,19 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
+    #[test]
+    fn parse_numbered_messages() {
+        let text = b"\xEF\xBB\xBFmsgid \"\"\nmsgstr \"header\"\n\n\n# Comment\nmsgid \"hello\"\nmsgstr \"\"\n";
+        let parser = Parser::new(None);
+        let messages = parser
+            .parse_numbered_messages_from_read(Cursor::new(&text[..]))
+            .expect("should parse successfully");
+
+        let lines: Vec<usize> = messages.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 5]);
+        assert_eq!(messages[1].1.msgid, "hello");
+    }
+
     #[test]
     fn parse_stream_with_utf8_bom() {
         let bom_and_content =
@@ -0,0 +1,202 @@
+//! Source code context for translation prompts.
+//!
+//! This module resolves `#: file:line` references of PO messages to source files
+//! and extracts lines around them, so AI models can see how strings are used.
+
+use crate::parser::PoMessage;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Rough number of characters per token, used to estimate size of prompt.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Extracts source code around references of messages.
+#[derive(Debug)]
+pub struct SourceContext {
+    /// Directory, relative to which references are resolved.
+    pub root: PathBuf,
+    /// Canonical path of the root directory. `None` when the directory doesn't exist.
+    canonical_root: Option<PathBuf>,
+    /// Number of lines to show before and after referenced line.
+    pub context_lines: usize,
+    /// Maximum number of tokens for source code in a single prompt.
+    pub max_tokens: usize,
+    /// Cache of source files. `None` when the file cannot be read.
+    files: Mutex<HashMap<PathBuf, Option<Vec<String>>>>,
+}
+
+impl SourceContext {
+    /// Creates a new source context extractor.
+    pub fn new(root: impl Into<PathBuf>, context_lines: usize, max_tokens: usize) -> Self {
+        let root = root.into();
+        SourceContext {
+            canonical_root: fs::canonicalize(&root).ok(),
+            root,
+            context_lines,
+            max_tokens,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Builds `<source>` block with source code around references of given messages.
+    ///
+    /// Snippets are added in order of references until token budget is exhausted.
+    /// Unresolvable references are ignored.
+    pub fn context_for(&self, messages: &[&PoMessage]) -> String {
+        let budget = self.max_tokens * CHARS_PER_TOKEN;
+        let mut text = String::new();
+
+        'outer: for message in messages {
+            for (file, line) in references(message) {
+                let Some(snippet) = self.snippet(&file, line) else {
+                    continue;
+                };
+
+                if text.len() + snippet.len() > budget {
+                    break 'outer;
+                }
+                text.push_str(&snippet);
+            }
+        }
+
+        if text.is_empty() {
+            text
+        } else {
+            format!("<source>\n{text}</source>\n")
+        }
+    }
+
+    /// Resolves referenced file relative to the root directory.
+    ///
+    /// References come from untrusted PO files, so files outside of the root directory,
+    /// e.g. absolute paths, `..` components, or symlinks, are rejected, to not send
+    /// arbitrary local files to the AI model.
+    fn resolve(&self, file: &str) -> Option<PathBuf> {
+        let root = self.canonical_root.as_deref()?;
+        let path = fs::canonicalize(self.root.join(Path::new(file))).ok()?;
+        path.starts_with(root).then_some(path)
+    }
+
+    /// Returns lines around given line of the file, with the referenced line marked by `>`.
+    fn snippet(&self, file: &str, line: usize) -> Option<String> {
+        let path = self.resolve(file)?;
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let lines = files
+            .entry(path.clone())
+            .or_insert_with(|| {
+                fs::read(&path).ok().map(|bytes| {
+                    String::from_utf8_lossy(&bytes)
+                        .lines()
+                        .map(String::from)
+                        .collect()
+                })
+            })
+            .as_ref()?;
+
+        if line == 0 || line > lines.len() {
+            return None;
+        }
+
+        let first = line.saturating_sub(self.context_lines).max(1);
+        let last = (line + self.context_lines).min(lines.len());
+
+        let mut text = format!("# {file}:{line}\n");
+        for n in first..=last {
+            let marker = if n == line { '>' } else { ' ' };
+            text.push_str(&format!("{marker}{n:5} | {}\n", lines[n - 1]));
+        }
+        Some(text)
+    }
+}
+
+/// Parses `#: file:line` references of the message.
+///
+/// References without line number are skipped, because there is nothing to show for them.
+pub fn references(message: &PoMessage) -> Vec<(String, usize)> {
+    message
+        .comments
+        .iter()
+        .filter_map(|comment| comment.strip_prefix("#:"))
+        .flat_map(|refs| refs.split_whitespace())
+        .filter_map(|reference| {
+            let (file, line) = reference.rsplit_once(':')?;
+            let line = line.parse().ok()?;
+            Some((file.to_string(), line))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_references() -> Result<()> {
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str(
+            "#: src/a.c:12 src/b.c:3\n#: src/no_line.c\nmsgid \"Open\"\nmsgstr \"\"\n",
+        )?;
+
+        assert_eq!(
+            references(&message),
+            vec![("src/a.c".to_string(), 12), ("src/b.c".to_string(), 3)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_for() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(
+            dir.path().join("src/menu.c"),
+            "line1\nline2\nmenu_add(_(\"Open\"));\nline4\nline5\n",
+        )?;
+
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str(
+            "#: src/menu.c:3 src/missing.c:1 src/menu.c:100\nmsgid \"Open\"\nmsgstr \"\"\n",
+        )?;
+
+        let source = SourceContext::new(dir.path(), 1, 1000);
+        let text = source.context_for(&[&message]);
+        assert_eq!(
+            text,
+            "<source>\n# src/menu.c:3\n     2 | line2\n>    3 | menu_add(_(\"Open\"));\n     4 | line4\n</source>\n"
+        );
+
+        // Nothing fits into tiny budget
+        let source = SourceContext::new(dir.path(), 1, 2);
+        assert_eq!(source.context_for(&[&message]), "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_outside_of_root() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("project");
+        fs::create_dir(&root)?;
+        fs::write(dir.path().join("secret.txt"), "password\n")?;
+        let secret = dir.path().join("secret.txt");
+
+        let parser = Parser::new(None);
+        let source = SourceContext::new(&root, 1, 1000);
+
+        // Absolute path
+        let message = parser.parse_message_from_str(&format!(
+            "#: {}:1\nmsgid \"Open\"\nmsgstr \"\"\n",
+            secret.display()
+        ))?;
+        assert_eq!(source.context_for(&[&message]), "");
+
+        // Relative path with ".."
+        let message =
+            parser.parse_message_from_str("#: ../secret.txt:1\nmsgid \"Open\"\nmsgstr \"\"\n")?;
+        assert_eq!(source.context_for(&[&message]), "");
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Stemming of words, to match inflected forms of dictionary terms.
+//!
+//! Stemmers are pluggable via the `Stemmer` trait. The built-in English stemmer
+//! uses the Snowball (Porter 2) algorithm, with a table of common irregular plurals.
+
+use anyhow::{Result, bail};
+use std::fmt;
+use std::sync::Arc;
+
+/// Reduces words to their stems, so inflected forms of a word have the same stem.
+pub trait Stemmer: fmt::Debug + Send + Sync {
+    /// Returns stem of the lowercase word.
+    fn stem(&self, word: &str) -> String;
+}
+
+/// English stemmer based on the Snowball algorithm.
+pub struct EnglishStemmer {
+    stemmer: rust_stemmers::Stemmer,
+}
+
+impl fmt::Debug for EnglishStemmer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EnglishStemmer")
+    }
+}
+
+impl Default for EnglishStemmer {
+    fn default() -> Self {
+        EnglishStemmer {
+            stemmer: rust_stemmers::Stemmer::create(rust_stemmers::Algorithm::English),
+        }
+    }
+}
+
+/// Irregular plural forms, which Snowball algorithm doesn't handle.
+const IRREGULAR_PLURALS: &[(&str, &str)] = &[
+    ("indices", "index"),
+    ("vertices", "vertex"),
+    ("matrices", "matrix"),
+    ("appendices", "appendix"),
+    ("children", "child"),
+    ("mice", "mouse"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("geese", "goose"),
+    ("people", "person"),
+    ("criteria", "criterion"),
+    ("phenomena", "phenomenon"),
+    ("analyses", "analysis"),
+    ("axes", "axis"),
+];
+
+impl Stemmer for EnglishStemmer {
+    fn stem(&self, word: &str) -> String {
+        let word = IRREGULAR_PLURALS
+            .iter()
+            .find(|(plural, _)| *plural == word)
+            .map_or(word, |(_, singular)| singular);
+        self.stemmer.stem(word).into_owned()
+    }
+}
+
+/// Returns stemmer by its name: "english" or "none".
+pub fn stemmer_by_name(name: &str) -> Result<Option<Arc<dyn Stemmer>>> {
+    match name {
+        "english" | "en" => Ok(Some(Arc::new(EnglishStemmer::default()))),
+        "none" => Ok(None),
+        _ => bail!(
+            "{}",
+            tr!("Unknown stemmer: \"{name}\". Expected: english or none.").replace("{name}", name)
+        ),
+    }
+}
+
+/// Splits text into lowercase words and returns their stems.
+pub fn stem_words(stemmer: &dyn Stemmer, text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| stemmer.stem(&word.to_lowercase()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_stemmer() {
+        let stemmer = EnglishStemmer::default();
+        for (a, b) in [
+            ("entries", "entry"),
+            ("indices", "index"),
+            ("copied", "copy"),
+            ("files", "file"),
+            ("running", "run"),
+        ] {
+            assert_eq!(stemmer.stem(a), stemmer.stem(b), "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn test_stem_words() {
+        let stemmer = EnglishStemmer::default();
+        assert_eq!(
+            stem_words(&stemmer, "Copied 5 Entries!"),
+            vec!["copi", "5", "entri"]
+        );
+    }
+
+    #[test]
+    fn test_stemmer_by_name() {
+        assert!(stemmer_by_name("english").unwrap().is_some());
+        assert!(stemmer_by_name("none").unwrap().is_none());
+        assert!(stemmer_by_name("klingon").is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! Translation memory with fast fuzzy search.
+//!
+//! Messages are indexed by character trigrams of their msgid, so only messages
+//! sharing enough trigrams with the query are compared using Levenshtein distance.
+
+use crate::parser::{Parser, PoMessage};
+use crate::util::IoContext;
+use anyhow::{Context, Result, bail};
+use std::collections::{HashMap, HashSet};
+use strsim::normalized_levenshtein;
+
+/// Default minimal similarity of a match, from 0.0 to 1.0.
+pub const DEFAULT_MIN_SCORE: f64 = 0.5;
+
+/// Default maximum number of matches to return.
+pub const DEFAULT_MAX_MATCHES: usize = 5;
+
+/// Number of best candidates by trigrams to compare using Levenshtein distance, per requested match.
+const CANDIDATES_PER_MATCH: usize = 10;
+
+/// A message stored in the translation memory.
+#[derive(Debug)]
+struct TmEntry {
+    message: PoMessage,
+    /// Name of the file, where message is loaded from.
+    source: String,
+    /// Priority of the source file. Higher wins among matches with same msgid or same score.
+    priority: usize,
+    /// Number of distinct trigrams in msgid.
+    trigram_count: usize,
+}
+
+/// A message found in the translation memory.
+#[derive(Debug, PartialEq)]
+pub struct TmMatch<'a> {
+    /// The message from translation memory.
+    pub message: &'a PoMessage,
+    /// Name of the file, where message is loaded from.
+    pub source: &'a str,
+    /// Similarity of msgid to the query, from 0.0 to 1.0.
+    pub score: f64,
+}
+
+/// Translation memory indexed by character trigrams.
+#[derive(Debug)]
+pub struct TranslationMemory {
+    /// Minimal similarity of a match, from 0.0 to 1.0.
+    pub min_score: f64,
+    /// Maximum number of matches to return.
+    pub max_matches: usize,
+    entries: Vec<TmEntry>,
+    /// Inverted index: trigram to indexes of entries containing it.
+    index: HashMap<String, Vec<usize>>,
+    /// Index of entries with highest priority by msgctxt and msgid.
+    exact: HashMap<(Option<String>, String), usize>,
+}
+
+impl Default for TranslationMemory {
+    fn default() -> Self {
+        TranslationMemory::new(DEFAULT_MIN_SCORE, DEFAULT_MAX_MATCHES)
+    }
+}
+
+impl TranslationMemory {
+    /// Creates an empty translation memory.
+    pub fn new(min_score: f64, max_matches: usize) -> Self {
+        TranslationMemory {
+            min_score,
+            max_matches,
+            entries: Vec::new(),
+            index: HashMap::new(),
+            exact: HashMap::new(),
+        }
+    }
+
+    /// Adds translated messages with given priority to the translation memory.
+    ///
+    /// Header, untranslated and fuzzy messages are ignored.
+    pub fn add_messages(&mut self, messages: Vec<PoMessage>, priority: usize, source: &str) {
+        for message in messages {
+            if message.is_header() || !message.is_translated() || message.is_fuzzy() {
+                continue;
+            }
+
+            let trigrams = trigrams(&message.msgid);
+            let id = self.entries.len();
+            for trigram in &trigrams {
+                self.index.entry(trigram.clone()).or_default().push(id);
+            }
+
+            let key = (message.msgctxt.clone(), message.msgid.clone());
+            match self.exact.get(&key) {
+                Some(&other) if self.entries[other].priority >= priority => {}
+                _ => {
+                    self.exact.insert(key, id);
+                }
+            }
+
+            self.entries.push(TmEntry {
+                message,
+                source: source.to_string(),
+                priority,
+                trigram_count: trigrams.len(),
+            });
+        }
+    }
+
+    /// Finds message with same msgctxt and msgid, preferring files with higher priority.
+    pub fn find_exact(&self, message: &PoMessage) -> Option<TmMatch<'_>> {
+        let key = (message.msgctxt.clone(), message.msgid.clone());
+        let entry = &self.entries[*self.exact.get(&key)?];
+        Some(TmMatch {
+            message: &entry.message,
+            source: &entry.source,
+            score: 1.0,
+        })
+    }
+
+    /// Finds messages with msgid similar to msgid of the given message.
+    ///
+    /// Matches are sorted by similarity, then by priority. Only one match
+    /// per msgid is returned.
+    pub fn find_matches(&self, message: &PoMessage) -> Vec<TmMatch<'_>> {
+        if message.is_header() || self.max_matches == 0 {
+            return Vec::new();
+        }
+        let msgid = &message.msgid;
+
+        // Count shared trigrams for each entry
+        let query = trigrams(msgid);
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for trigram in &query {
+            if let Some(ids) = self.index.get(trigram) {
+                for &id in ids {
+                    *shared.entry(id).or_default() += 1;
+                }
+            }
+        }
+
+        // Select best candidates by Dice coefficient of trigram sets
+        let mut candidates: Vec<(f64, usize)> = shared
+            .into_iter()
+            .map(|(id, count)| {
+                let total = query.len() + self.entries[id].trigram_count;
+                (2.0 * count as f64 / total as f64, id)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        candidates.truncate(self.max_matches * CANDIDATES_PER_MATCH);
+
+        // Compute exact similarity for candidates
+        let mut matches: Vec<(&TmEntry, f64)> = candidates
+            .into_iter()
+            .map(|(_, id)| &self.entries[id])
+            .map(|entry| (entry, normalized_levenshtein(msgid, &entry.message.msgid)))
+            .filter(|(_, score)| *score >= self.min_score)
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.priority.cmp(&a.0.priority)));
+
+        let mut seen = HashSet::new();
+        matches
+            .into_iter()
+            .filter(|(entry, _)| seen.insert(&entry.message.msgid))
+            .take(self.max_matches)
+            .map(|(entry, score)| TmMatch {
+                message: &entry.message,
+                source: &entry.source,
+                score,
+            })
+            .collect()
+    }
+}
+
+/// Options to load translation memory, shared by commands.
+#[derive(Debug)]
+pub struct TranslationMemoryOptions<'a> {
+    /// Translation memory files, from the highest priority to the lowest.
+    pub files: Vec<&'a str>,
+    /// Minimal similarity of a match, from 0.0 to 1.0.
+    pub min_score: f64,
+    /// Maximum number of matches to return.
+    pub max_matches: usize,
+}
+
+impl Default for TranslationMemoryOptions<'_> {
+    fn default() -> Self {
+        TranslationMemoryOptions {
+            files: Vec::new(),
+            min_score: DEFAULT_MIN_SCORE,
+            max_matches: DEFAULT_MAX_MATCHES,
+        }
+    }
+}
+
+impl<'a> TranslationMemoryOptions<'a> {
+    /// Tries to parse a translation memory option at the start of the command line.
+    ///
+    /// Returns the rest of the command line, if option is recognized.
+    pub fn parse_option<'c>(&mut self, cmdline: &'c [&'a str]) -> Result<Option<&'c [&'a str]>> {
+        match cmdline {
+            ["-M", file, rest @ ..]
+            | ["--tm", file, rest @ ..]
+            | ["--translation-memory", file, rest @ ..] => {
+                self.files.push(file);
+                Ok(Some(rest))
+            }
+            ["--tm-min-score", value, rest @ ..] => {
+                self.min_score = match value.parse::<f64>() {
+                    Ok(score) if (0.0..=100.0).contains(&score) => score / 100.0,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --tm-min-score option. Expected: percentage from 0 to 100. Actual value: \"{value}\".")
+                            .replace("{value}", value)
+                    ),
+                };
+                Ok(Some(rest))
+            }
+            ["--tm-max-matches", value, rest @ ..] => {
+                self.max_matches = match value.parse::<usize>() {
+                    Ok(n) => n,
+                    _ => bail!(
+                        "{}",
+                        tr!("Invalid argument for --tm-max-matches option. Expected: number of matches. Actual value: \"{value}\".")
+                            .replace("{value}", value)
+                    ),
+                };
+                Ok(Some(rest))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Loads translation memory files. Files given first have the highest priority.
+    pub fn load(&self, parser: &Parser, ctx: &mut IoContext) -> Result<TranslationMemory> {
+        let mut translation_memory = TranslationMemory::new(self.min_score, self.max_matches);
+        for (n, file) in self.files.iter().enumerate() {
+            let msgs = parser.parse_messages_from_file(file).with_context(|| {
+                tr!("Cannot open file \"{file}\" with translation memory.").replace("{file}", file)
+            })?;
+            writeln!(
+                ctx.err,
+                "{}: {}",
+                tr!("INFO"),
+                tr!("Loaded {count} messages from \"{file}\" file with translation memory.")
+                    .replace("{count}", &msgs.len().to_string())
+                    .replace("{file}", file)
+            )?;
+            translation_memory.add_messages(msgs, self.files.len() - n, file);
+        }
+        Ok(translation_memory)
+    }
+}
+
+/// Returns help text for translation memory options.
+pub fn help_translation_memory_options() -> &'static str {
+    tr!(r#"TRANSLATION MEMORY OPTIONS:
+
+  -M | --tm FILE        Local Translation Memory file (PO format) to use for fuzzy matching.
+                        Can be used multiple times; files given first have higher priority.
+
+  --tm-min-score PERCENT  Minimal similarity of fuzzy matches, in percents. Default value: 50.
+
+  --tm-max-matches N    Maximum number of fuzzy matches per message. Default value: 5.
+"#)
+}
+
+/// Returns set of lowercase character trigrams of the text, padded with spaces.
+fn trigrams(text: &str) -> HashSet<String> {
+    let chars: Vec<char> = format!("  {}  ", text.to_lowercase()).chars().collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use anyhow::Result;
+
+    fn tm_from_str(text: &str, priority: usize, tm: &mut TranslationMemory) -> Result<()> {
+        let parser = Parser::new(None);
+        tm.add_messages(parser.parse_messages_from_str(text)?, priority, "tm.po");
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matches() -> Result<()> {
+        let mut tm = TranslationMemory::new(0.5, 5);
+        tm_from_str(
+            r#"
+msgid ""
+msgstr "Content-Type: text/plain; charset=UTF-8\n"
+
+msgid "Open file"
+msgstr "Відкрити файл"
+
+msgid "Open files"
+msgstr "Відкрити файли"
+
+msgid "Close window"
+msgstr "Закрити вікно"
+
+msgid "Open folder"
+msgstr ""
+"#,
+            1,
+            &mut tm,
+        )?;
+        // Header and untranslated messages are not stored
+        assert_eq!(tm.entries.len(), 3);
+
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Open file\"\nmsgstr \"\"\n")?;
+        let matches = tm.find_matches(&message);
+
+        let msgids: Vec<&str> = matches.iter().map(|m| m.message.msgid.as_str()).collect();
+        assert_eq!(msgids, vec!["Open file", "Open files"]);
+        assert_eq!(matches[0].score, 1.0);
+        assert!(matches[1].score < 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matches_priority_and_limit() -> Result<()> {
+        let mut tm = TranslationMemory::new(0.0, 1);
+        tm_from_str("msgid \"Save\"\nmsgstr \"Зберегти (low)\"\n", 1, &mut tm)?;
+        tm_from_str("msgid \"Save\"\nmsgstr \"Зберегти (high)\"\n", 2, &mut tm)?;
+        tm_from_str("msgid \"Save as\"\nmsgstr \"Зберегти як\"\n", 3, &mut tm)?;
+
+        let parser = Parser::new(None);
+        let message = parser.parse_message_from_str("msgid \"Save\"\nmsgstr \"\"\n")?;
+        let matches = tm.find_matches(&message);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].message.msgstr_first(), "Зберегти (high)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_matches_large_tm() -> Result<()> {
+        let mut tm = TranslationMemory::default();
+        let parser = Parser::new(None);
+        let messages = (0..20000)
+            .map(|n| {
+                parser.parse_message_from_str(&format!(
+                    "msgid \"Message number {n}\"\nmsgstr \"Повідомлення номер {n}\"\n"
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tm.add_messages(messages, 1, "tm.po");
+
+        let message =
+            parser.parse_message_from_str("msgid \"Message number 12345\"\nmsgstr \"\"\n")?;
+        let matches = tm.find_matches(&message);
+        assert_eq!(matches.len(), DEFAULT_MAX_MATCHES);
+        assert_eq!(matches[0].message.msgid, "Message number 12345");
+        Ok(())
+    }
+}
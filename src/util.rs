@@ -4,9 +4,13 @@
 //! with piped input/output.
 
 use crate::command_check_symbols::check_symbols;
+use crate::diagnostics::{Diagnostic, OutputFormat};
+use crate::format_string::{check_format, has_format_flag};
+use crate::length::check_length;
 use crate::parser::PoMessage;
 use anyhow::{Context, Result, bail};
 use std::io::Write;
+use std::time::{Duration, Instant};
 
 /// Context for I/O operations, allowing for testable output and error streams.
 pub struct IoContext<'a> {
@@ -14,133 +18,117 @@ pub struct IoContext<'a> {
     pub err: &'a mut dyn Write,
 }
 
-/// Backend for calling an AI model.
-#[derive(Debug, Clone)]
-pub struct AiBackend {
-    command: String,
-    args: Vec<String>,
-    mock_response: Option<String>,
-}
-
-impl AiBackend {
-    /// Create a new backend with a specific command and arguments.
-    #[allow(dead_code)]
-    pub fn new(command: String, args: Vec<String>) -> Self {
-        Self {
-            command,
-            args,
-            mock_response: None,
-        }
-    }
-
-    /// Create a backend from a full command line string (e.g., from --ai-command).
-    pub fn from_command_line(cmd: &str) -> Self {
-        let parts: Vec<String> = cmd.split_whitespace().map(|s| s.to_string()).collect();
-        if parts.is_empty() {
-            // Default to aichat if empty, though this shouldn't normally happen if parsed correctly
-            return Self::with_aichat_defaults("ollama:translategemma:12b", "translate-po", None);
-        }
-        Self {
-            command: parts[0].clone(),
-            args: parts[1..].to_vec(),
-            mock_response: None,
-        }
-    }
-
-    /// Create a backend for aichat with default options.
-    pub fn with_aichat_defaults(model: &str, role: &str, rag: Option<&str>) -> Self {
-        let mut args = vec![
-            "-r".to_string(),
-            role.to_string(),
-            "-m".to_string(),
-            model.to_string(),
-        ];
-        if let Some(rag_val) = rag {
-            args.push("--rag".to_string());
-            args.push(rag_val.to_string());
-        }
-        Self {
-            command: "aichat".to_string(),
-            args,
-            mock_response: None,
-        }
-    }
-
-    /// Create a mock backend for testing.
-    #[allow(dead_code)]
-    pub fn mock(response: &str) -> Self {
-        Self {
-            command: String::new(),
-            args: Vec::new(),
-            mock_response: Some(response.to_string()),
-        }
-    }
-
-    /// Executes the AI request.
-    pub fn execute(&self, prompt: &str) -> Result<String> {
-        if let Some(ref mock) = self.mock_response {
-            return Ok(mock.clone());
-        }
-        let args_ref: Vec<&str> = self.args.iter().map(|s| s.as_str()).collect();
-        pipe_to_command(&self.command, &args_ref, prompt)
-    }
+/// Global options, given before the command, which affect several commands.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobalOptions {
+    /// Format of diagnostics, reported by check commands.
+    pub output_format: OutputFormat,
+    /// Maximum ratio of msgstr length to msgid length, checked when translations are validated.
+    pub max_length_ratio: Option<f64>,
 }
 
 /// Executes an external command, piping the given text to its stdin and capturing stdout.
 ///
 /// This is used extensively for interacting with AI tools like `aichat`.
+#[cfg(test)]
 pub fn pipe_to_command(command: &str, args: &[&str], text: &str) -> Result<String> {
-    use std::io::Write;
+    pipe_to_command_with_timeout(command, args, text, None)
+}
+
+/// Same as [`pipe_to_command`], but kills the command when it runs longer than `timeout`.
+pub fn pipe_to_command_with_timeout(
+    command: &str,
+    args: &[&str],
+    text: &str,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    use std::io::{Read, Write};
     use std::process::{Command, Stdio};
 
-    let mut child = Command::new(command)
-        .args(args)
+    let mut cmd = Command::new(command);
+    cmd.args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+        .stderr(Stdio::piped());
+    // Own process group allows to kill children of the command too, e.g. of `sh -c`
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd.spawn()?;
 
     let mut stdin = child.stdin.take().expect(
         "Failed to open stdin of child process; check if Command was spawned with Stdio::piped()",
     );
+    let mut stdout = child.stdout.take().expect(
+        "Failed to open stdout of child process; check if Command was spawned with Stdio::piped()",
+    );
+    let mut stderr = child.stderr.take().expect(
+        "Failed to open stderr of child process; check if Command was spawned with Stdio::piped()",
+    );
     let text = text.to_string();
 
-    let output = std::thread::scope(|s| {
-        let handle = s.spawn(move || stdin.write_all(text.as_bytes()));
+    let stdout = std::thread::scope(|s| {
+        let write_handle = s.spawn(move || match stdin.write_all(text.as_bytes()) {
+            // The command may exit without reading its input, e.g. "echo"
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            res => res,
+        });
+        let stdout_handle = s.spawn(move || {
+            let mut buf = Vec::new();
+            stdout.read_to_end(&mut buf).map(|_| buf)
+        });
+        let stderr_handle = s.spawn(move || {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        });
 
-        let output = child.wait_with_output();
+        let status = wait_with_timeout(&mut child, timeout);
 
-        let write_res = handle
+        let write_res = write_handle
             .join()
             .expect("Stdin writer thread panicked")
             .context(
                 tr!("Failed to write to stdin of \"{command}\"").replace("{command}", command),
             );
+        let stdout = stdout_handle.join().expect("Stdout reader thread panicked");
+        let stderr = stderr_handle.join().expect("Stderr reader thread panicked");
 
-        let output = output.context(tr!("Failed to wait for child process"));
+        let status = match status {
+            Ok(Some(status)) => status,
+            Ok(None) => bail!(
+                "{} {:?}",
+                tr!("Command \"{command}\" timed out after {seconds} seconds and was killed.")
+                    .replace("{command}", command)
+                    .replace(
+                        "{seconds}",
+                        &timeout.unwrap_or_default().as_secs().to_string()
+                    ),
+                args
+            ),
+            Err(e) => return Err(e.context(tr!("Failed to wait for child process"))),
+        };
 
-        match (write_res, output) {
-            (Ok(_), Ok(output)) if output.status.success() => Ok(output),
-            (write_res, Ok(output)) => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = stdout.context(tr!("Failed to wait for child process"))?;
+
+        match write_res {
+            Ok(_) if status.success() => Ok(stdout),
+            write_res => {
+                let stderr = String::from_utf8_lossy(&stderr.unwrap_or_default()).into_owned();
                 let mut err_msg = tr!("Command \"{command}\" failed").replace("{command}", command);
                 if let Err(e) = write_res {
                     err_msg.push_str(&format!(" ({e})"));
                 }
-                if !output.status.success() {
+                if !status.success() {
                     err_msg.push_str(&format!(
                         " {}",
-                        tr!("with exit code {code}").replace("{code}", &output.status.to_string())
+                        tr!("with exit code {code}").replace("{code}", &status.to_string())
                     ));
                 }
                 bail!("{} {:?}\nStderr: {}", err_msg, args, stderr);
             }
-            (Err(e), _) => Err(e),
-            (_, Err(e)) => Err(e),
         }
     })?;
 
-    let mut result = String::from_utf8_lossy(&output.stdout).into_owned();
+    let mut result = String::from_utf8_lossy(&stdout).into_owned();
     if cfg!(windows) {
         // Normalize Windows CRLF line endings to LF so output matches our expected text format.
         result = result.replace("\r\n", "\n");
@@ -148,36 +136,82 @@ pub fn pipe_to_command(command: &str, args: &[&str], text: &str) -> Result<Strin
 
     Ok(result)
 }
-/// Validates a message and returns a string with any found issues.
+
+/// Kills the child process and, on Unix, all processes of its group, which keep its
+/// pipes open otherwise.
+fn kill_process_group(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill() has no memory safety requirements. The group was created
+        // for the child by process_group(0), so it contains the child and its descendants.
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+    // Process may exit before kill(), so ignore error here
+    let _ = child.kill();
+}
+
+/// Waits for the child process to exit. Kills it and returns `None` on timeout.
+fn wait_with_timeout(
+    child: &mut std::process::Child,
+    timeout: Option<Duration>,
+) -> Result<Option<std::process::ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return Ok(Some(child.wait()?));
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(child);
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Validates a message and returns found problems.
 ///
 /// This is used by AI-based commands to check if the generated translation
-/// is technically sound.
-pub fn validate_message(message: &PoMessage) -> String {
+/// is technically sound. Length of msgstr is checked against limits of the message
+/// and against `max_length_ratio`, when given.
+pub fn validate_message(message: &PoMessage, max_length_ratio: Option<f64>) -> Vec<Diagnostic> {
     if message.is_header() {
-        return "".into();
+        return Vec::new();
     }
 
     if !message.is_plural() {
         if message.msgstr_first().is_empty() {
-            return tr!("# Error: Message is not translated.\n").to_string();
+            return vec![Diagnostic::error(tr!("Message is not translated."))];
         }
     } else {
         for msgstr in &message.msgstr {
             if msgstr.is_empty() {
-                return tr!("# Error: Message is not translated fully.\n").to_string();
+                return vec![Diagnostic::error(tr!("Message is not translated fully."))];
             }
         }
     }
 
-    match check_symbols(message) {
-        None => "".into(),
-        Some(errors) => errors,
+    let mut problems = check_length(message, max_length_ratio);
+
+    // Format strings with explicit flag are checked precisely, so punctuation may differ freely.
+    // Detected formats may be plain text, e.g. "{curly} braces", so only symbols are compared.
+    if has_format_flag(message) {
+        problems.extend(check_format(message));
+    } else {
+        problems.extend(check_symbols(message));
     }
+
+    problems
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diagnostics::to_comments;
 
     #[test]
     #[cfg(not(windows))]
@@ -196,24 +230,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_validate_message_length() {
+        let message = PoMessage {
+            msgid: "Delete the file".to_string(),
+            msgstr: vec!["Видалити файл з диска назавжди".to_string()],
+            comments: vec!["#. max-length: 20".to_string()],
+            ..Default::default()
+        };
+        let errors = to_comments(&validate_message(&message, Some(1.5)));
+        assert!(errors.contains("msgstr is too long: 30 characters"));
+        assert!(errors.contains("maximum ratio is 1.5"));
+        assert_eq!(validate_message(&message, None).len(), 1);
+    }
+
+    #[test]
+    fn test_validate_message_format() {
+        // Braces in plain text are not a format string without the flag
+        let mut message = PoMessage {
+            msgid: "Use {curly} braces to group".to_string(),
+            msgstr: vec!["Використовуйте {фігурні} дужки для групування".to_string()],
+            ..Default::default()
+        };
+        assert!(validate_message(&message, None).is_empty());
+
+        message.comments = vec!["#, python-brace-format".to_string()];
+        assert!(
+            to_comments(&validate_message(&message, None))
+                .starts_with("# Error: Format specification")
+        );
+    }
+
     #[test]
     fn test_pipe_to_command_error() {
         let result = pipe_to_command("non-existent-command-123", &[], "test");
         assert!(result.is_err());
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn test_pipe_to_command_timeout() {
+        let started = Instant::now();
+        let result =
+            pipe_to_command_with_timeout("sleep", &["10"], "", Some(Duration::from_millis(200)));
+        assert!(format!("{:#}", result.unwrap_err()).contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_pipe_to_command_timeout_kills_children() {
+        // Child of the shell keeps stdout open, until it's killed too
+        let started = Instant::now();
+        let result = pipe_to_command_with_timeout(
+            "sh",
+            &["-c", "sleep 10; echo done"],
+            "",
+            Some(Duration::from_millis(200)),
+        );
+        assert!(format!("{:#}", result.unwrap_err()).contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
     #[test]
     fn test_pipe_to_command_exit_failure() {
         // false command always returns 1
         let result = pipe_to_command("false", &[], "test");
         assert!(result.is_err());
     }
-
-    #[test]
-    fn test_ai_backend_mock() -> Result<()> {
-        let backend = AiBackend::mock("custom response");
-        let result = backend.execute("any prompt")?;
-        assert_eq!(result, "custom response");
-        Ok(())
-    }
 }